@@ -0,0 +1,452 @@
+//! Socket hooks that are preloaded into the consumer (AflSink) via LD_PRELOAD.
+//!
+//! The consumer runtime (consumer/aflpp-consumer) only reports listen() and bind()
+//! via the ft pipe. The hooks in this library report the remaining socket events
+//! the scheduler waits for and, if requested, data transfers, using the same pipe
+//! and message encoding. See scheduler/src/sink.rs for the receiving side.
+//!
+//! Only calls that go through the dynamic linker are observed. For example, a socket
+//! that is implicitly closed by dup2() is not reported as closed.
+
+use std::{
+    mem,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+};
+
+use libc::{c_int, c_void, iovec, msghdr, size_t, sockaddr, socklen_t, ssize_t};
+
+/// The fd the forkserver uses to send messages that are specific to our sink
+/// implementation. Must match `AFL_FT_WRITE_TO_PARENT_FD` in scheduler/src/sink.rs.
+const FT_PIPE_FD: c_int = 198 + 2;
+
+/// Send before entering accept(), since a server that entered accept() is ready
+/// to handle connections.
+pub const FT_MSG_BEFORE_ACCEPT: i32 = 3;
+/// Send after connect() established a connection. For non-blocking sockets, this is
+/// the case once getsockopt(SO_ERROR) reports that the pending connect() succeeded.
+/// Carries the fd of the connection (see [msg_with_fd]).
+pub const FT_MSG_AFTER_CONNECT: i32 = 4;
+/// Send after a connection established via accept() or connect() was closed.
+/// Carries the fd of the connection (see [msg_with_fd]).
+pub const FT_MSG_AFTER_CLOSE: i32 = 5;
+/// Send after data was send or received via a socket. Only reported if
+/// [FT_REPORT_SOCKET_DATA_ENV_VAR_NAME] is set and at most once per
+/// [SOCKET_DATA_REPORT_INTERVAL_MS].
+pub const FT_MSG_SOCKET_DATA: i32 = 6;
+/// Send once the library is loaded. Since this happens before the forkserver
+/// comes up, the scheduler knows whether it can rely on the messages above.
+pub const FT_MSG_SOCKET_HOOKS_LOADED: i32 = 7;
+/// Send after accept() returned a connection. Carries the fd of the connection
+/// (see [msg_with_fd]).
+pub const FT_MSG_AFTER_ACCEPT: i32 = 8;
+
+/// Messages that refer to a connection carry its fd in the bits above the
+/// message type, such that each message is still a single i32.
+const FT_MSG_FD_SHIFT: u32 = 8;
+
+/// Encode `fd` into the message `msg`.
+pub const fn msg_with_fd(msg: i32, fd: c_int) -> i32 {
+    msg | (fd << FT_MSG_FD_SHIFT)
+}
+
+/// Fds >= this limit are not tracked.
+const MAX_TRACKED_FD: usize = 4096;
+/// A connect() on the socket is in progress.
+const FD_CONNECT_PENDING: u8 = 1 << 0;
+/// The socket was returned by accept() or connected via connect().
+const FD_CONNECTED: u8 = 1 << 1;
+
+/// Must match `ENV_REPORT_SOCKET_DATA` in fuzztruction_shared/src/constants.rs.
+const FT_REPORT_SOCKET_DATA_ENV_VAR_NAME: &str = "FT_REPORT_SOCKET_DATA";
+const SOCKET_DATA_REPORT_INTERVAL_MS: u64 = 1;
+static REPORT_SOCKET_DATA: AtomicBool = AtomicBool::new(false);
+/// Time in ms (CLOCK_MONOTONIC_COARSE) data was last reported.
+static LAST_SOCKET_DATA_REPORT_MS: AtomicU64 = AtomicU64::new(0);
+
+/// State of each fd below [MAX_TRACKED_FD].
+static FD_STATE: [AtomicU8; MAX_TRACKED_FD] = [const { AtomicU8::new(0) }; MAX_TRACKED_FD];
+
+fn fd_state(fd: c_int) -> Option<&'static AtomicU8> {
+    usize::try_from(fd).ok().and_then(|fd| FD_STATE.get(fd))
+}
+
+fn set_fd_flags(fd: c_int, flags: u8) {
+    if let Some(state) = fd_state(fd) {
+        state.fetch_or(flags, Ordering::Relaxed);
+    }
+}
+
+fn report_connected(fd: c_int) {
+    set_fd_flags(fd, FD_CONNECTED);
+    send_msg(msg_with_fd(FT_MSG_AFTER_CONNECT, fd));
+}
+
+/// Called after accept() or accept4() returned `ret`.
+fn report_accepted(ret: c_int) {
+    // Fds we do not track would never be reported as closed.
+    if fd_state(ret).is_some() {
+        set_fd_flags(ret, FD_CONNECTED);
+        send_msg(msg_with_fd(FT_MSG_AFTER_ACCEPT, ret));
+    }
+}
+
+/// Called after each call that may transfer data via `fd`. `ret` is its return value.
+fn report_socket_io(fd: c_int, ret: ssize_t) {
+    if ret <= 0 {
+        return;
+    }
+
+    // Data on a socket with a pending connect() implies that the connect() succeeded.
+    let pending = fd_state(fd).map_or(0, |state| {
+        state.fetch_and(!FD_CONNECT_PENDING, Ordering::Relaxed) & FD_CONNECT_PENDING
+    });
+    if pending != 0 {
+        report_connected(fd);
+    }
+
+    if !REPORT_SOCKET_DATA.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut now: libc::timespec = unsafe { mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_COARSE, &mut now) };
+    let now_ms = now.tv_sec as u64 * 1000 + now.tv_nsec as u64 / 1_000_000;
+    let last_ms = LAST_SOCKET_DATA_REPORT_MS.load(Ordering::Relaxed);
+    if now_ms.saturating_sub(last_ms) < SOCKET_DATA_REPORT_INTERVAL_MS {
+        return;
+    }
+
+    // read() and write() are also used on files and pipes.
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    let errno = unsafe { *libc::__errno_location() };
+    let ret = unsafe { libc::fstat(fd, &mut stat) };
+    unsafe { *libc::__errno_location() = errno };
+    if ret != 0 || stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+        return;
+    }
+    LAST_SOCKET_DATA_REPORT_MS.store(now_ms, Ordering::Relaxed);
+    send_msg(FT_MSG_SOCKET_DATA);
+}
+
+/// Get the next definition of the libc function `$name` in the lookup order,
+/// i.e., the function we are hooking.
+macro_rules! real {
+    ($name:ident: $ty:ty) => {{
+        static ADDR: AtomicUsize = AtomicUsize::new(0);
+        let mut addr = ADDR.load(Ordering::Relaxed);
+        if addr == 0 {
+            let name = concat!(stringify!($name), "\0");
+            addr = libc::dlsym(libc::RTLD_NEXT, name.as_ptr() as *const libc::c_char) as usize;
+            if addr == 0 {
+                // Unwinding across the FFI boundary is not an option.
+                libc::abort();
+            }
+            ADDR.store(addr, Ordering::Relaxed);
+        }
+        mem::transmute::<usize, $ty>(addr)
+    }};
+}
+
+/// Send `msg` to the scheduler. The caller's errno is preserved.
+fn send_msg(msg: i32) {
+    let buf = msg.to_le_bytes();
+    unsafe {
+        let errno = *libc::__errno_location();
+        // Use the raw syscall, since write() itself might be hooked.
+        libc::syscall(libc::SYS_write, FT_PIPE_FD, buf.as_ptr(), buf.len());
+        *libc::__errno_location() = errno;
+    }
+}
+
+extern "C" fn announce_socket_hooks() {
+    let name = format!("{FT_REPORT_SOCKET_DATA_ENV_VAR_NAME}\0");
+    let report_data = unsafe { !libc::getenv(name.as_ptr() as *const libc::c_char).is_null() };
+    REPORT_SOCKET_DATA.store(report_data, Ordering::Relaxed);
+    send_msg(FT_MSG_SOCKET_HOOKS_LOADED);
+}
+
+#[used]
+#[link_section = ".init_array"]
+static ANNOUNCE_SOCKET_HOOKS: extern "C" fn() = announce_socket_hooks;
+
+/// # Safety
+/// Same as `accept(2)`.
+#[no_mangle]
+pub unsafe extern "C" fn accept(fd: c_int, addr: *mut sockaddr, len: *mut socklen_t) -> c_int {
+    let real = real!(accept: unsafe extern "C" fn(c_int, *mut sockaddr, *mut socklen_t) -> c_int);
+    send_msg(FT_MSG_BEFORE_ACCEPT);
+    let ret = real(fd, addr, len);
+    report_accepted(ret);
+    ret
+}
+
+/// # Safety
+/// Same as `accept4(2)`.
+#[no_mangle]
+pub unsafe extern "C" fn accept4(
+    fd: c_int,
+    addr: *mut sockaddr,
+    len: *mut socklen_t,
+    flags: c_int,
+) -> c_int {
+    let real =
+        real!(accept4: unsafe extern "C" fn(c_int, *mut sockaddr, *mut socklen_t, c_int) -> c_int);
+    send_msg(FT_MSG_BEFORE_ACCEPT);
+    let ret = real(fd, addr, len, flags);
+    report_accepted(ret);
+    ret
+}
+
+/// # Safety
+/// Same as `connect(2)`.
+#[no_mangle]
+pub unsafe extern "C" fn connect(fd: c_int, addr: *const sockaddr, len: socklen_t) -> c_int {
+    let real = real!(connect: unsafe extern "C" fn(c_int, *const sockaddr, socklen_t) -> c_int);
+    let ret = real(fd, addr, len);
+    if ret == 0 {
+        report_connected(fd);
+    } else if *libc::__errno_location() == libc::EINPROGRESS {
+        set_fd_flags(fd, FD_CONNECT_PENDING);
+    }
+    ret
+}
+
+/// # Safety
+/// Same as `getsockopt(2)`.
+#[no_mangle]
+pub unsafe extern "C" fn getsockopt(
+    fd: c_int,
+    level: c_int,
+    name: c_int,
+    value: *mut c_void,
+    len: *mut socklen_t,
+) -> c_int {
+    let real = real!(getsockopt: unsafe extern "C" fn(c_int, c_int, c_int, *mut c_void, *mut socklen_t) -> c_int);
+    let ret = real(fd, level, name, value, len);
+    // Non-blocking clients query SO_ERROR to learn whether their connect() succeeded.
+    if ret == 0
+        && level == libc::SOL_SOCKET
+        && name == libc::SO_ERROR
+        && *len as usize >= mem::size_of::<c_int>()
+        && *(value as *const c_int) == 0
+    {
+        let pending = fd_state(fd).map_or(0, |state| {
+            state.fetch_and(!FD_CONNECT_PENDING, Ordering::Relaxed) & FD_CONNECT_PENDING
+        });
+        if pending != 0 {
+            report_connected(fd);
+        }
+    }
+    ret
+}
+
+/// # Safety
+/// Same as `close(2)`.
+#[no_mangle]
+pub unsafe extern "C" fn close(fd: c_int) -> c_int {
+    let real = real!(close: unsafe extern "C" fn(c_int) -> c_int);
+    // Reset the state before the fd can be reused by another thread.
+    let state = fd_state(fd).map_or(0, |state| state.swap(0, Ordering::Relaxed));
+    let ret = real(fd);
+    if state & FD_CONNECTED != 0 {
+        send_msg(msg_with_fd(FT_MSG_AFTER_CLOSE, fd));
+    }
+    ret
+}
+
+/// Define a hook for a function that may transfer data via the fd passed as its first
+/// argument and returns the number of transferred bytes.
+macro_rules! io_hook {
+    ($name:ident($fd:ident: c_int $(, $arg:ident: $ty:ty)*)) => {
+        /// # Safety
+        #[doc = concat!("Same as `", stringify!($name), "(2)`.")]
+        #[no_mangle]
+        pub unsafe extern "C" fn $name($fd: c_int $(, $arg: $ty)*) -> ssize_t {
+            let real = real!($name: unsafe extern "C" fn(c_int $(, $ty)*) -> ssize_t);
+            let ret = real($fd $(, $arg)*);
+            report_socket_io($fd, ret);
+            ret
+        }
+    };
+}
+
+io_hook!(send(fd: c_int, buf: *const c_void, len: size_t, flags: c_int));
+io_hook!(sendto(
+    fd: c_int,
+    buf: *const c_void,
+    len: size_t,
+    flags: c_int,
+    addr: *const sockaddr,
+    addr_len: socklen_t
+));
+io_hook!(sendmsg(fd: c_int, msg: *const msghdr, flags: c_int));
+io_hook!(recv(fd: c_int, buf: *mut c_void, len: size_t, flags: c_int));
+io_hook!(recvfrom(
+    fd: c_int,
+    buf: *mut c_void,
+    len: size_t,
+    flags: c_int,
+    addr: *mut sockaddr,
+    addr_len: *mut socklen_t
+));
+io_hook!(recvmsg(fd: c_int, msg: *mut msghdr, flags: c_int));
+io_hook!(read(fd: c_int, buf: *mut c_void, len: size_t));
+io_hook!(write(fd: c_int, buf: *const c_void, len: size_t));
+io_hook!(readv(fd: c_int, iov: *const iovec, iov_cnt: c_int));
+io_hook!(writev(fd: c_int, iov: *const iovec, iov_cnt: c_int));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        os::unix::io::AsRawFd,
+        sync::Mutex,
+    };
+
+    /// The hooks of all tests report via the same fd.
+    static FT_PIPE_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Point FT_PIPE_FD to a new pipe and return the pipe's non-blocking read end.
+    fn setup_ft_pipe() -> c_int {
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(
+                libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC),
+                0
+            );
+            assert_eq!(libc::dup2(fds[1], FT_PIPE_FD), FT_PIPE_FD);
+            libc::close(fds[1]);
+        }
+        fds[0]
+    }
+
+    /// Read all messages that are currently pending on `fd`.
+    fn read_msgs(fd: c_int) -> Vec<i32> {
+        let mut msgs = Vec::new();
+        let mut buf = [0u8; 4];
+        while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) } == 4 {
+            msgs.push(i32::from_le_bytes(buf));
+        }
+        msgs
+    }
+
+    #[test]
+    fn test_accept_and_connect_are_reported() {
+        let _guard = FT_PIPE_LOCK.lock().unwrap();
+        let ft_pipe = setup_ft_pipe();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let client_fd = client.as_raw_fd();
+        let server_fd = server.as_raw_fd();
+
+        assert_eq!(
+            read_msgs(ft_pipe),
+            [
+                msg_with_fd(FT_MSG_AFTER_CONNECT, client_fd),
+                FT_MSG_BEFORE_ACCEPT,
+                msg_with_fd(FT_MSG_AFTER_ACCEPT, server_fd)
+            ]
+        );
+
+        drop(client);
+        drop(server);
+        drop(listener);
+        // Closing the listening socket is not reported.
+        assert_eq!(
+            read_msgs(ft_pipe),
+            [
+                msg_with_fd(FT_MSG_AFTER_CLOSE, client_fd),
+                msg_with_fd(FT_MSG_AFTER_CLOSE, server_fd)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_socket_data_is_reported() {
+        let _guard = FT_PIPE_LOCK.lock().unwrap();
+        let ft_pipe = setup_ft_pipe();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        read_msgs(ft_pipe);
+
+        // Not reported unless requested via the environment.
+        client.write_all(b"ping").unwrap();
+        assert_eq!(read_msgs(ft_pipe), []);
+
+        REPORT_SOCKET_DATA.store(true, Ordering::Relaxed);
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(read_msgs(ft_pipe), [FT_MSG_SOCKET_DATA]);
+
+        // Reports are throttled.
+        LAST_SOCKET_DATA_REPORT_MS.store(u64::MAX, Ordering::Relaxed);
+        server.write_all(b"pong").unwrap();
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(read_msgs(ft_pipe), []);
+
+        // Data transfers that are not via sockets are not reported.
+        LAST_SOCKET_DATA_REPORT_MS.store(0, Ordering::Relaxed);
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+            assert_eq!(write(fds[1], buf.as_ptr() as *const c_void, buf.len()), 4);
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        assert_eq!(read_msgs(ft_pipe), []);
+        REPORT_SOCKET_DATA.store(false, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_non_blocking_connect_is_reported_once_completed() {
+        let _guard = FT_PIPE_LOCK.lock().unwrap();
+        let ft_pipe = setup_ft_pipe();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        unsafe {
+            let fd = libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_NONBLOCK, 0);
+            assert!(fd >= 0);
+            let mut addr: libc::sockaddr_in = mem::zeroed();
+            addr.sin_family = libc::AF_INET as libc::sa_family_t;
+            addr.sin_port = port.to_be();
+            addr.sin_addr.s_addr = u32::from(std::net::Ipv4Addr::LOCALHOST).to_be();
+            let ret = libc::connect(
+                fd,
+                &addr as *const libc::sockaddr_in as *const sockaddr,
+                mem::size_of::<libc::sockaddr_in>() as socklen_t,
+            );
+
+            if ret != 0 {
+                assert_eq!(*libc::__errno_location(), libc::EINPROGRESS);
+                assert_eq!(read_msgs(ft_pipe), []);
+
+                let mut pollfd = libc::pollfd {
+                    fd,
+                    events: libc::POLLOUT,
+                    revents: 0,
+                };
+                assert_eq!(libc::poll(&mut pollfd, 1, 5000), 1);
+                let mut error: c_int = -1;
+                let mut len = mem::size_of::<c_int>() as socklen_t;
+                let ret = libc::getsockopt(
+                    fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_ERROR,
+                    &mut error as *mut c_int as *mut c_void,
+                    &mut len,
+                );
+                assert_eq!((ret, error), (0, 0));
+            }
+            assert_eq!(read_msgs(ft_pipe), [msg_with_fd(FT_MSG_AFTER_CONNECT, fd)]);
+
+            libc::close(fd);
+        }
+    }
+}
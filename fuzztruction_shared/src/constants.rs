@@ -2,3 +2,4 @@ pub const MAX_MUTATION_CACHE_ENTRIES: usize = 200000;
 pub const PATCH_POINT_SIZE: usize = 32;
 pub const ENV_SHM_NAME: &str = "FT_SHM_NAME";
 pub const ENV_LOG_LEVEL: &str = "FT_LOG_LEVEL";
+pub const ENV_REPORT_SOCKET_DATA: &str = "FT_REPORT_SOCKET_DATA";
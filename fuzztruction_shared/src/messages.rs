@@ -8,6 +8,7 @@
 use std::convert::TryFrom;
 
 use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::num::NonZeroU64;
 use std::ptr;
 use std::slice;
@@ -35,6 +36,8 @@ pub enum MessageType {
     AfterListen = 17,
     AfterBind = 18,
     AfterConnect = 19,
+    BeforeAccept = 20,
+    SocketData = 21,
 }
 
 impl Default for MessageType {
@@ -62,6 +65,8 @@ impl TryFrom<u8> for MessageType {
             x if x == MessageType::AfterListen as u8 => MessageType::AfterListen,
             x if x == MessageType::AfterBind as u8 => MessageType::AfterBind,
             x if x == MessageType::AfterConnect as u8 => MessageType::AfterConnect,
+            x if x == MessageType::BeforeAccept as u8 => MessageType::BeforeAccept,
+            x if x == MessageType::SocketData as u8 => MessageType::SocketData,
             _ => return Err(()),
         };
         Ok(ret)
@@ -335,6 +340,10 @@ impl Message for ShutdownMessage {
     }
 }
 
+/// Maximum number of values that are recorded per traced patch point. The value
+/// of the n-th hit is recorded if n is smaller than this limit.
+pub const MAX_TRACED_VALUES: usize = 16;
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct TracePointStat {
@@ -342,6 +351,10 @@ pub struct TracePointStat {
     pub patch_point_id: MutationSiteID,
     pub cnt: u64,
     pub execution_index: Option<NonZeroU64>,
+    /// Number of valid elements in `values`.
+    values_len: u64,
+    /// The values observed during the first hits.
+    values: [u64; MAX_TRACED_VALUES],
 }
 
 impl TracePointStat {
@@ -349,13 +362,26 @@ impl TracePointStat {
         patch_point_id: MutationSiteID,
         cnt: u64,
         execution_index: Option<NonZeroU64>,
+        values: &[u64],
     ) -> TracePointStat {
-        TracePointStat {
+        let mut ret = TracePointStat {
             patch_point_id,
             cnt,
             execution_index,
             ..Default::default()
-        }
+        };
+        let values = &values[..values.len().min(MAX_TRACED_VALUES)];
+        ret.values[..values.len()].copy_from_slice(values);
+        ret.values_len = values.len() as u64;
+        ret
+    }
+
+    /// The values of the spill slot observed during the first hits, where the
+    /// n-th element was observed during the n-th hit. The values are zero extended
+    /// and are not masked to the width of the value, i.e., they might contain
+    /// unrelated bits beyond it.
+    pub fn values(&self) -> &[u64] {
+        &self.values[..self.values_len as usize]
     }
 }
 
@@ -366,6 +392,8 @@ impl Default for TracePointStat {
             patch_point_id: MutationSiteID::invalid(),
             execution_index: None,
             cnt: 0,
+            values_len: 0,
+            values: [0; MAX_TRACED_VALUES],
         }
     }
 }
@@ -375,6 +403,13 @@ impl Message for TracePointStat {
     }
     fn sanitize(&self) -> Result<()> {
         self.header.sanitize()?;
+        if self.values_len > MAX_TRACED_VALUES as u64 {
+            return Err(anyhow!(
+                "TracePointStat contains {} values, but at most {} are supported",
+                self.values_len,
+                MAX_TRACED_VALUES
+            ));
+        }
         Ok(())
     }
 }
@@ -499,14 +534,69 @@ impl Message for ChildPid {
     }
 }
 
+/// The address an IP socket is bound to. Used by [AfterListen], [AfterBind] and [BeforeAccept]
+/// to report which socket the target operated on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct SocketAddress {
+    /// One of the SOCKET_ADDRESS_FAMILY_* constants.
+    pub family: u16,
+    /// The port in host byte order.
+    pub port: u16,
+    /// The IP address in network byte order. IPv4 addresses only use the first four bytes.
+    pub addr: [u8; 16],
+}
+
+/// The socket is not an IP socket or its address could not be determined.
+pub const SOCKET_ADDRESS_FAMILY_UNKNOWN: u16 = 0;
+pub const SOCKET_ADDRESS_FAMILY_IPV4: u16 = 4;
+pub const SOCKET_ADDRESS_FAMILY_IPV6: u16 = 6;
+
+impl SocketAddress {
+    pub fn to_socket_addr(&self) -> Option<SocketAddr> {
+        let ip: IpAddr = match self.family {
+            SOCKET_ADDRESS_FAMILY_IPV4 => {
+                Ipv4Addr::new(self.addr[0], self.addr[1], self.addr[2], self.addr[3]).into()
+            }
+            SOCKET_ADDRESS_FAMILY_IPV6 => Ipv6Addr::from(self.addr).into(),
+            _ => return None,
+        };
+        Some(SocketAddr::new(ip, self.port))
+    }
+}
+
+impl From<SocketAddr> for SocketAddress {
+    fn from(value: SocketAddr) -> Self {
+        let mut addr = [0u8; 16];
+        let family = match value.ip() {
+            IpAddr::V4(ip) => {
+                addr[..4].copy_from_slice(&ip.octets());
+                SOCKET_ADDRESS_FAMILY_IPV4
+            }
+            IpAddr::V6(ip) => {
+                addr.copy_from_slice(&ip.octets());
+                SOCKET_ADDRESS_FAMILY_IPV6
+            }
+        };
+        SocketAddress {
+            family,
+            port: value.port(),
+            addr,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct AfterListen {
     header: MsgHeader,
+    /// The local address of the socket.
+    pub address: SocketAddress,
 }
 impl AfterListen {
-    pub fn new() -> Self {
+    pub fn new(address: SocketAddress) -> Self {
         AfterListen {
+            address,
             ..Default::default()
         }
     }
@@ -516,6 +606,7 @@ impl Default for AfterListen {
     fn default() -> Self {
         AfterListen {
             header: MsgHeader::new(MessageType::AfterListen),
+            address: SocketAddress::default(),
         }
     }
 }
@@ -532,10 +623,13 @@ impl Message for AfterListen {
 #[repr(C)]
 pub struct AfterBind {
     header: MsgHeader,
+    /// The local address of the socket.
+    pub address: SocketAddress,
 }
 impl AfterBind {
-    pub fn new() -> Self {
+    pub fn new(address: SocketAddress) -> Self {
         AfterBind {
+            address,
             ..Default::default()
         }
     }
@@ -545,6 +639,7 @@ impl Default for AfterBind {
     fn default() -> Self {
         AfterBind {
             header: MsgHeader::new(MessageType::AfterBind),
+            address: SocketAddress::default(),
         }
     }
 }
@@ -586,6 +681,69 @@ impl Message for AfterConnect {
     }
 }
 
+#[derive(Debug)]
+#[repr(C)]
+pub struct BeforeAccept {
+    header: MsgHeader,
+    /// The local address of the listening socket.
+    pub address: SocketAddress,
+}
+impl BeforeAccept {
+    pub fn new(address: SocketAddress) -> Self {
+        BeforeAccept {
+            address,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for BeforeAccept {
+    fn default() -> Self {
+        BeforeAccept {
+            header: MsgHeader::new(MessageType::BeforeAccept),
+            address: SocketAddress::default(),
+        }
+    }
+}
+impl Message for BeforeAccept {
+    fn message_type() -> MessageType {
+        MessageType::BeforeAccept
+    }
+    fn sanitize(&self) -> Result<()> {
+        Ok(self.header.sanitize()?)
+    }
+}
+
+/// Send if data was send or received via a socket.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SocketData {
+    header: MsgHeader,
+}
+impl SocketData {
+    pub fn new() -> Self {
+        SocketData {
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for SocketData {
+    fn default() -> Self {
+        SocketData {
+            header: MsgHeader::new(MessageType::SocketData),
+        }
+    }
+}
+impl Message for SocketData {
+    fn message_type() -> MessageType {
+        MessageType::SocketData
+    }
+    fn sanitize(&self) -> Result<()> {
+        Ok(self.header.sanitize()?)
+    }
+}
+
 #[derive(Debug)]
 pub enum ReceivableMessages {
     HelloMessage(HelloMessage),
@@ -598,4 +756,5 @@ pub enum ReceivableMessages {
     ChildPid(ChildPid),
     AfterListen(AfterListen),
     AfterBind(AfterBind),
+    BeforeAccept(BeforeAccept),
 }
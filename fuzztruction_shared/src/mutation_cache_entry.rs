@@ -1,11 +1,66 @@
-use llvm_stackmap::LLVMInstruction;
+use llvm_stackmap::{LLVMInstruction, LocationType};
 use memoffset::offset_of;
+use serde::{Deserialize, Serialize};
 
 use crate::{mutation_cache::MutationCacheEntryFlags, types::MutationSiteID, util};
-use std::{alloc, convert::TryInto};
+use std::{
+    alloc,
+    convert::{TryFrom, TryInto},
+    ops::Range,
+};
 
 const MAX_MASK_LEN: usize = 1024 * 1024 * 64;
 
+/// The operation used to combine a chunk of the mask with the value at the
+/// mutated location. Independent of the operator, a chunk that is zero leaves
+/// the value untouched, thus an all-zero mask is always a no-op.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum MutationOperator {
+    /// value ^= chunk
+    #[default]
+    Xor = 0,
+    /// value |= chunk
+    Or = 1,
+    /// value &= !chunk
+    AndNot = 2,
+    /// value += chunk (wrapping)
+    Add = 3,
+    /// value -= chunk (wrapping)
+    Sub = 4,
+    /// value = chunk, if chunk != 0.
+    Set = 5,
+    /// value = chunk - 1, if chunk != 0. In contrast to [MutationOperator::Set],
+    /// this allows to set the value to zero. Values narrower than a byte are
+    /// applied in byte sized chunks, thus, e.g., a branch condition can be
+    /// forced to false (1) or true (2).
+    Force = 6,
+}
+
+impl MutationOperator {
+    pub const ALL: [MutationOperator; 7] = [
+        MutationOperator::Xor,
+        MutationOperator::Or,
+        MutationOperator::AndNot,
+        MutationOperator::Add,
+        MutationOperator::Sub,
+        MutationOperator::Set,
+        MutationOperator::Force,
+    ];
+}
+
+impl TryFrom<u8> for MutationOperator {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        MutationOperator::ALL
+            .iter()
+            .copied()
+            .find(|op| *op as u8 == value)
+            .ok_or(value)
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct MutationCacheEntryMetadata {
@@ -17,15 +72,27 @@ pub struct MutationCacheEntryMetadata {
 
     vma: u64,
     flags: u8,
+    /// The [MutationOperator] used to apply the mask.
+    operator: u8,
 
     spill_slot: llvm_stackmap::Location,
+    /// The value of the large constant a `spill_slot` of type ConstIndex refers to.
+    spill_slot_constant: u64,
     target_value_size_bits: u32,
 
     read_pos_bits: u32,
-    /// The length of the mask stored at MutationCacheEntry.msk. If `loc_size` is > 0,
-    /// the mask contains `loc_size` additional bytes that can be used in case the
+    /// Number of times the mutation stub of this entry was executed.
+    hit_cnt: u32,
+    /// If `hit_filter_len` is > 0, only the hits with an index in
+    /// [hit_filter_start, hit_filter_start + hit_filter_len) are mutated. All other
+    /// hits neither apply nor consume the mask.
+    hit_filter_start: u32,
+    hit_filter_len: u32,
+    /// The length of the mask stored at MutationCacheEntry.msk. If `msk_len` is > 0,
+    /// the mask is followed by padding bytes that can be used in case the
     /// mutation stub `read_pos` overflows and reads more then msk_len bytes, in this case
     /// any following invocation will read these padding bytes that are not mutated (see agent.rs).
+    /// See [MutationCacheEntry::overflow_padding_len] for its size.
     msk_len: u32,
 }
 
@@ -33,8 +100,8 @@ pub struct MutationCacheEntryMetadata {
 pub struct MutationCacheEntry {
     pub metadata: MutationCacheEntryMetadata,
     /// The mask that is applied in chunks of size `loc_size` each time the mutated
-    /// location is accessed. If `loc_size` > 0, then the mask is msk_len + loc_size bytes
-    /// long, else it is msk_len bytes in size.
+    /// location is accessed. If `msk_len` > 0, then the mask is followed by
+    /// [MutationCacheEntry::overflow_padding_len] bytes of padding.
     pub msk: [u8; 0],
 }
 
@@ -65,19 +132,14 @@ impl MutationCacheEntry {
         assert!(msk_len < MAX_MASK_LEN as u32);
         let target_value_size_byte = target_value_size_bits.div_ceil(8);
 
-        // In case loc_size > 0, we pad the msk_len by loc_size bytes thus if the
-        // mutation site is hit more often than anticipated, we can apply these
-        // additional `loc_size` bytes as mask (which is always 0, since it does not belong to the mutated part of the mask)
-        let mut padded_msk_len = msk_len as usize;
+        // We pad the msk thus if the mutation site is hit more often than anticipated,
+        // we can apply these additional bytes as mask (which are always 0, since they
+        // do not belong to the mutated part of the mask).
+        let padded_msk_len =
+            msk_len as usize + Self::overflow_padding_len(msk_len, target_value_size_byte);
 
         // This is a DST, so we need to calculate the size.
-        let mut alloc_size = std::mem::size_of::<MutationCacheEntry>() + msk_len as usize;
-
-        // Add the padding explained above (also see jit gen_mutation_gpr).
-        if msk_len > 0 {
-            alloc_size += target_value_size_byte as usize;
-            padded_msk_len += 8;
-        }
+        let mut alloc_size = std::mem::size_of::<MutationCacheEntry>() + padded_msk_len;
 
         // Align to 8 byte, thus if stored consectively in memory the next struct is properly aligned.
         // This is not important for the Box allocated below, but later when we are managing our own
@@ -90,9 +152,14 @@ impl MutationCacheEntry {
             llvm_instruction,
             vma,
             flags,
+            operator: MutationOperator::Xor as u8,
             spill_slot,
+            spill_slot_constant: 0,
             target_value_size_bits,
             read_pos_bits: 0,
+            hit_cnt: 0,
+            hit_filter_start: 0,
+            hit_filter_len: 0,
             msk_len,
         };
 
@@ -137,12 +204,10 @@ impl MutationCacheEntry {
             new_msk_len
         );
 
-        let mut new_size = std::mem::size_of_val(self) + new_msk_len as usize;
-
-        // Padding for read overlow (see new())
-        if new_msk_len > 0 {
-            new_size += 8;
-        }
+        // Padding for read overlow (see new()).
+        let mut new_size = std::mem::size_of_val(self)
+            + new_msk_len as usize
+            + Self::overflow_padding_len(new_msk_len, self.target_value_size_byte());
 
         // Alignment
         new_size += 8 - (new_size % 8);
@@ -193,7 +258,25 @@ impl MutationCacheEntry {
         &self.metadata.spill_slot
     }
 
-    fn target_value_size_bit(&self) -> u32 {
+    /// The address of the spilled value if the spill slot is a constant, i.e.,
+    /// of type Constant or ConstIndex.
+    pub fn spill_slot_constant(&self) -> Option<u64> {
+        match self.metadata.spill_slot.loc_type {
+            LocationType::Constant => {
+                Some(self.metadata.spill_slot.offset_or_constant as i64 as u64)
+            }
+            LocationType::ConstIndex => Some(self.metadata.spill_slot_constant),
+            _ => None,
+        }
+    }
+
+    /// Set the value of the large constant referred to by a spill slot of type ConstIndex.
+    pub fn set_spill_slot_constant(&mut self, val: u64) -> &mut Self {
+        self.metadata.spill_slot_constant = val;
+        self
+    }
+
+    pub fn target_value_size_bit(&self) -> u32 {
         self.metadata.target_value_size_bits
     }
 
@@ -252,15 +335,25 @@ impl MutationCacheEntry {
         (self.metadata.flags & flag as u8) > 0
     }
 
+    pub fn operator(&self) -> MutationOperator {
+        self.metadata
+            .operator
+            .try_into()
+            .expect("Invalid mutation operator")
+    }
+
+    /// Set the operator used to apply the mask. Changing the operator of an entry
+    /// that is already in use by the agent requires to resync the mutations.
+    pub fn set_operator(&mut self, operator: MutationOperator) -> &mut Self {
+        self.metadata.operator = operator as u8;
+        self
+    }
+
     /// The size in bytes of the whole entry. Cloning a MutationCacheEntry requires
     /// to copy .size() bytes from a pointer of type MutationCacheEntry.
     pub fn size(&self) -> usize {
-        let mut ret = self.size_wo_overflow_padding();
-        if self.msk_len() > 0 {
-            // The msk is padded with an additional element which is used in case
-            // read_pos overflows.
-            ret += self.target_value_size_byte() as usize;
-        }
+        let mut ret = self.size_wo_overflow_padding()
+            + Self::overflow_padding_len(self.msk_len(), self.target_value_size_byte());
 
         // Size is always a multiple of 8 to guarantee proper alignment
         ret += 8 - (ret % 8);
@@ -268,6 +361,17 @@ impl MutationCacheEntry {
         ret
     }
 
+    /// The number of padding bytes behind a mask of `msk_len` bytes, which are read
+    /// in case read_pos overflows. A mutation stub reads at most 8 byte or, for values
+    /// wider than 8 byte, chunks of the value's size at once.
+    fn overflow_padding_len(msk_len: u32, target_value_size_byte: u32) -> usize {
+        if msk_len > 0 {
+            (target_value_size_byte as usize).max(8)
+        } else {
+            0
+        }
+    }
+
     fn size_wo_overflow_padding(&self) -> usize {
         std::mem::size_of::<MutationCacheEntryMetadata>() + self.msk_len() as usize
     }
@@ -299,13 +403,13 @@ impl MutationCacheEntry {
 
     pub fn get_padding_as_slice(&self) -> Option<&[u8]> {
         if self.msk_len() > 0 {
-            // The msk is padded with an additional element which is used in case
+            // The msk is padded with additional bytes which are used in case
             // read_pos overflows.
             unsafe {
                 let ret = std::slice::from_raw_parts_mut(
                     self.get_msk_as_ptr::<u8>()
                         .offset(self.metadata.msk_len as isize),
-                    self.target_value_size_byte() as usize,
+                    Self::overflow_padding_len(self.msk_len(), self.target_value_size_byte()),
                 );
                 Some(ret)
             }
@@ -323,6 +427,16 @@ impl MutationCacheEntry {
     }
 
     pub fn chunk_size_bits(&self) -> u16 {
+        let chunk_size_bits = self.value_chunk_size_bits();
+        if self.operator() == MutationOperator::Force {
+            // Force needs an additional state for "do not touch", thus it
+            // operates on whole bytes.
+            return chunk_size_bits.next_multiple_of(8);
+        }
+        chunk_size_bits
+    }
+
+    fn value_chunk_size_bits(&self) -> u16 {
         match self.llvm_instruction() {
             LLVMInstruction::Br => {
                 let ret = self.target_value_size_bit().try_into().unwrap();
@@ -336,11 +450,8 @@ impl MutationCacheEntry {
             LLVMInstruction::Switch => self.target_value_size_bit().try_into().unwrap(),
             LLVMInstruction::Select => self.target_value_size_bit().try_into().unwrap(),
             LLVMInstruction::ICmp => self.target_value_size_bit().try_into().unwrap(),
-            LLVMInstruction::Call => {
-                let ret = self.target_value_size_bit().try_into().unwrap();
-                assert!(ret == 1);
-                ret
-            }
+            // Either the condition of a skippable call or a recorded return value.
+            LLVMInstruction::Call => self.target_value_size_bit().try_into().unwrap(),
             _ => {
                 todo!("{:#?}", self.llvm_instruction())
             }
@@ -360,6 +471,34 @@ impl MutationCacheEntry {
         self.metadata.read_pos_bits
     }
 
+    /// The indices of the hits that are mutated. If None, all hits are mutated.
+    pub fn hit_filter(&self) -> Option<Range<u32>> {
+        let start = self.metadata.hit_filter_start;
+        (self.metadata.hit_filter_len > 0).then(|| start..(start + self.metadata.hit_filter_len))
+    }
+
+    /// Only mutate the hits with an index in `filter` or all hits if None.
+    pub fn set_hit_filter(&mut self, filter: Option<Range<u32>>) -> &mut Self {
+        let filter = filter.unwrap_or(0..0);
+        self.metadata.hit_filter_start = filter.start;
+        self.metadata.hit_filter_len = filter.len() as u32;
+        self
+    }
+
+    pub fn hit_cnt_offset() -> usize {
+        offset_of!(MutationCacheEntry, metadata) + offset_of!(MutationCacheEntryMetadata, hit_cnt)
+    }
+
+    pub fn hit_filter_start_offset() -> usize {
+        offset_of!(MutationCacheEntry, metadata)
+            + offset_of!(MutationCacheEntryMetadata, hit_filter_start)
+    }
+
+    pub fn hit_filter_len_offset() -> usize {
+        offset_of!(MutationCacheEntry, metadata)
+            + offset_of!(MutationCacheEntryMetadata, hit_filter_len)
+    }
+
     pub fn read_pos_bits_offset() -> usize {
         let offset = offset_of!(MutationCacheEntry, metadata);
         // Just so that we know that this was changed.
@@ -1,7 +1,7 @@
 use fuzztruction_shared::messages;
 use fuzztruction_shared::{
     communication_channel::{CommunicationChannel, CommunicationChannelError},
-    constants::ENV_LOG_LEVEL,
+    constants::{ENV_LOG_LEVEL, ENV_REPORT_SOCKET_DATA},
     dwarf,
     messages::ReceivableMessages,
 };
@@ -13,12 +13,14 @@ use libc::waitpid;
 use log::*;
 use messages::{Message, RunMessage, SyncMutations, TerminatedMessage};
 use mutation_cache::{MutationCache, MutationCacheEntryFlags};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::Instant;
 use std::{
     collections::HashSet,
+    mem,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Mutex,
     },
 };
@@ -40,6 +42,12 @@ pub static IS_CHILD: AtomicBool = AtomicBool::new(false);
 /// Whether we are running as a standalone application and are not connected
 /// to the coordinator.
 static STANDALONE: AtomicBool = AtomicBool::new(true);
+/// Whether data transfers via sockets are reported to the coordinator.
+static REPORT_SOCKET_DATA: AtomicBool = AtomicBool::new(false);
+/// Time in ms (CLOCK_MONOTONIC_COARSE) data was last reported.
+static LAST_SOCKET_DATA_REPORT_MS: AtomicU64 = AtomicU64::new(0);
+/// Report data transfers at most once per this many ms.
+const SOCKET_DATA_REPORT_INTERVAL_MS: u64 = 1;
 
 /// Map used to trace coverage and execution count of the patch points.
 static TRACE_MAP: Mutex<Option<tracing::TraceMap<u64>>> = Mutex::new(None);
@@ -98,20 +106,61 @@ pub extern "C" fn __ft_auto_init() {
     }
 }
 
+/// Get the local address `fd` is bound to. If `fd` is not an IP socket, the
+/// returned address has family SOCKET_ADDRESS_FAMILY_UNKNOWN.
+fn socket_local_address(fd: libc::c_int) -> messages::SocketAddress {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockname(
+            fd,
+            &mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return messages::SocketAddress::default();
+    }
+
+    let addr: SocketAddr = match storage.ss_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr));
+            (ip, u16::from_be(sin.sin_port)).into()
+        }
+        libc::AF_INET6 => {
+            let sin6 = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(sin6.sin6_addr.s6_addr);
+            (ip, u16::from_be(sin6.sin6_port)).into()
+        }
+        _ => return messages::SocketAddress::default(),
+    };
+    addr.into()
+}
+
 #[no_mangle]
-pub extern "C" fn __ft_after_listen() {
+pub extern "C" fn __ft_after_listen(fd: libc::c_int) {
     log::trace!("__ft_after_listen");
     if !STANDALONE.load(Ordering::SeqCst) {
-        let msg = messages::AfterListen::new();
+        let msg = messages::AfterListen::new(socket_local_address(fd));
         send_message(msg, DEFAULT_TIMEOUT_MS).unwrap();
     }
 }
 
 #[no_mangle]
-pub extern "C" fn __ft_after_bind() {
+pub extern "C" fn __ft_after_bind(fd: libc::c_int) {
     log::trace!("__ft_after_bind");
     if !STANDALONE.load(Ordering::SeqCst) {
-        let msg = messages::AfterBind::new();
+        let msg = messages::AfterBind::new(socket_local_address(fd));
+        send_message(msg, DEFAULT_TIMEOUT_MS).unwrap();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __ft_before_accept(fd: libc::c_int) {
+    log::trace!("__ft_before_accept");
+    if !STANDALONE.load(Ordering::SeqCst) {
+        let msg = messages::BeforeAccept::new(socket_local_address(fd));
         send_message(msg, DEFAULT_TIMEOUT_MS).unwrap();
     }
 }
@@ -135,6 +184,38 @@ pub extern "C" fn __ft_after_connect() {
     }
 }
 
+/// Called after each call that may transfer data via `fd`, e.g., send() or read().
+/// `ret` is the return value of the call.
+#[no_mangle]
+pub extern "C" fn __ft_after_socket_io(fd: libc::c_int, ret: libc::ssize_t) {
+    if ret <= 0 || !REPORT_SOCKET_DATA.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut now: libc::timespec = unsafe { mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_COARSE, &mut now) };
+    let now_ms = now.tv_sec as u64 * 1000 + now.tv_nsec as u64 / 1_000_000;
+    let last_ms = LAST_SOCKET_DATA_REPORT_MS.load(Ordering::Relaxed);
+    if now_ms.saturating_sub(last_ms) < SOCKET_DATA_REPORT_INTERVAL_MS {
+        return;
+    }
+
+    // read() and write() are also used on files.
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 || stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+        return;
+    }
+    LAST_SOCKET_DATA_REPORT_MS.store(now_ms, Ordering::Relaxed);
+
+    // Reports are only used to detect idle sessions, thus we drop them instead of
+    // blocking the target if the queue is full or the channel is busy.
+    if let Ok(cc_guard) = COMMUNICATION_CHANNEL.try_lock() {
+        if let Some(cc) = cc_guard.as_ref() {
+            let _ = cc.send_message(messages::SocketData::new(), 0);
+        }
+    }
+}
+
 /// Update our globally stored mappings in `PROC_MAPPINGS`. Must be called each
 /// time after mapping or unmapping memory.
 pub fn update_proc_mappings() {
@@ -163,6 +244,10 @@ pub fn start_forkserver() {
     drop(cc_guard);
 
     STANDALONE.store(false, Ordering::SeqCst);
+    REPORT_SOCKET_DATA.store(
+        std::env::var_os(ENV_REPORT_SOCKET_DATA).is_some(),
+        Ordering::SeqCst,
+    );
 
     let level = std::env::var(ENV_LOG_LEVEL).unwrap_or("Debug".to_owned());
     let level = log::Level::from_str(&level).unwrap();
@@ -212,7 +297,19 @@ pub unsafe extern "C" fn __tracing_cb(id: u64) {
     trace_map_guard
         .as_mut()
         .expect("Called tracing_cb without initializing TRACE_MAP")
-        .report_hit(id);
+        .report_hit(id, None);
+}
+
+/// Same as `__tracing_cb`, but the calling patch point also reports the `value`
+/// stored in its spill slot.
+#[no_mangle]
+pub unsafe extern "C" fn __tracing_value_cb(id: u64, value: u64) {
+    // ! NOTE: See `__tracing_cb`.
+    let mut trace_map_guard = TRACE_MAP.lock().unwrap();
+    trace_map_guard
+        .as_mut()
+        .expect("Called tracing_value_cb without initializing TRACE_MAP")
+        .report_hit(id, Some(value));
 }
 
 /// Revert all changes applied to the binary and reapply all mutations that are
@@ -254,6 +351,7 @@ fn sync_mutations(agent: &mut Agent, _msg: &SyncMutations) {
 
     // The function used to report patch point hits during tracing.
     let tracing_cb_fn = jit::NativeFunction::from_fn(__tracing_cb as usize, 1);
+    let tracing_value_cb_fn = jit::NativeFunction::from_fn(__tracing_value_cb as usize, 2);
 
     for entry in entries.iter() {
         let mut callables = Vec::new();
@@ -266,7 +364,14 @@ fn sync_mutations(agent: &mut Agent, _msg: &SyncMutations) {
         );
 
         if entry.msk_len() > 0 {
-            let mutation_stub = agent.jit.gen_mutation(&entry);
+            // If tracing is enabled, the patch point calls the multiplexer, which then
+            // calls our stub.
+            let call_depth = if entry.is_flag_set(MutationCacheEntryFlags::TracingEnabled) {
+                2
+            } else {
+                1
+            };
+            let mutation_stub = agent.jit.gen_mutation(&entry, call_depth);
             let mutation_stub = match mutation_stub {
                 Ok(e) => e,
                 Err(JitError::UnsupportedMutation(e)) => {
@@ -282,18 +387,25 @@ fn sync_mutations(agent: &mut Agent, _msg: &SyncMutations) {
         if entry.is_flag_set(MutationCacheEntryFlags::TracingEnabled) {
             // Tracing for this entry was requested.
 
-            // We pass our own id as argument to the callback.
+            // We pass our own id and, if the spill slot can be read, the value
+            // stored in it as arguments to the callback.
             let id: u64 = entry.id().into();
-            let args = vec![jit::FunctionArg::Constant(id)];
-
-            let tracing_stub = agent.jit.gen_call(
-                &tracing_cb_fn,
-                args,
-                true,
-                Some(dwarf::GENERAL_PURPOSE_REGISTERS.to_owned()),
-            );
+            let call_depth = if callables.is_empty() { 1 } else { 2 };
+            let tracing_stub = agent
+                .jit
+                .gen_tracing_stub(&tracing_value_cb_fn, &entry, call_depth)
+                .unwrap_or_else(|_| {
+                    agent.jit.gen_call(
+                        &tracing_cb_fn,
+                        vec![jit::FunctionArg::Constant(id)],
+                        true,
+                        Some(dwarf::GENERAL_PURPOSE_REGISTERS.to_owned()),
+                    )
+                });
             let tracing_stub = agent.jit.allocate(tracing_stub).unwrap();
-            callables.push(tracing_stub);
+            // The tracing stub is called first, thus it observes the value before
+            // it is mutated.
+            callables.insert(0, tracing_stub);
 
             // Notify the trace map that the given vma might report a execution hit
             // during tracing.
@@ -424,7 +536,7 @@ fn postprocess_trace_map() {
             if e.hits > 0 {
                 trace_hits += e.hits;
                 send_message(
-                    messages::TracePointStat::new(e.value.into(), e.hits, e.order),
+                    messages::TracePointStat::new(e.value.into(), e.hits, e.order, e.values()),
                     DEFAULT_TIMEOUT_MS,
                 )
                 .unwrap();
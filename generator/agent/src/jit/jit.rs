@@ -2,8 +2,8 @@ use crate::mem::MappedMemoryConfig;
 use core::panic;
 use fuzztruction_shared::abi;
 use fuzztruction_shared::constants::PATCH_POINT_SIZE;
-use fuzztruction_shared::dwarf::DwarfReg;
-use fuzztruction_shared::mutation_cache_entry::MutationCacheEntry;
+use fuzztruction_shared::dwarf::{self, DwarfReg};
+use fuzztruction_shared::mutation_cache_entry::{MutationCacheEntry, MutationOperator};
 use fuzztruction_shared::types::VAddr;
 use keystone::{Arch, Keystone, OptionType};
 use llvm_stackmap::LocationType;
@@ -29,6 +29,11 @@ macro_rules! MiB {
 }
 
 const DEFAULT_CODE_CACHE_SIZE: usize = MiB!(64);
+/// The widest value (in bits) a mutation stub can mutate, i.e., the size of a YMM register.
+const MAX_CHUNK_SIZE_BITS: u16 = 256;
+/// Number of bytes the stubs generated by `gen_call` (without arguments and trashed
+/// registers) and `gen_call_multiplexer` push onto the stack before entering the callee.
+pub const CALL_STACK_USAGE: usize = 3 * 8;
 const NOP_PATTERN: &[u8; 32] = b"\x66\x66\x66\x66\x66\x2e\x66\x0f\x1f\x84\x00\x00\x02\x00\x00\x66\x66\x66\x66\x66\x2e\x66\x0f\x1f\x84\x00\x00\x02\x00\x00\x66\x90";
 
 #[derive(Debug, Clone, Copy)]
@@ -573,27 +578,175 @@ impl<'a> Jit<'a> {
         FunctionTemplate::new(asm_body, returns)
     }
 
+    /// Generate a stub that calls `to(id, value)`, where `id` is the id of `mce` and
+    /// `value` the content of its spill slot at the patch point. Values narrower
+    /// than 8 byte are zero extended and wider ones are truncated to their first
+    /// 8 byte. The stub preserves all general purpose registers and `call_depth`
+    /// has the same meaning as for `gen_mutation_stub`.
+    pub fn gen_tracing_stub(
+        &self,
+        to: &impl CallableFunction,
+        mce: &MutationCacheEntry,
+        call_depth: usize,
+    ) -> Result<FunctionTemplate, JitError> {
+        let spill_slot = mce.spill_slot();
+        let spill_slot_constant = mce.spill_slot_constant();
+        let value_size_bytes = mce.target_value_size_bit().div_ceil(8) as usize;
+        if value_size_bytes == 0 {
+            return Err(JitError::UnsupportedMutation(format!(
+                "Can not trace values of size zero {:#?}",
+                mce
+            )));
+        }
+
+        let spill_slot_reg = match spill_slot_constant {
+            Some(_) => None,
+            None => Some(DwarfReg::try_from(spill_slot.dwarf_regnum).unwrap()),
+        };
+
+        let mut abi_args_order = abi::ARGUMENT_PASSING_ORDER.iter();
+        let id_arg_reg = abi_args_order.next().unwrap();
+        let value_arg_reg = abi_args_order.next().unwrap();
+        let value_arg_reg_str = value_arg_reg.name();
+
+        let saved_regs = dwarf::GENERAL_PURPOSE_REGISTERS
+            .iter()
+            .filter(|reg| **reg != DwarfReg::Rsp)
+            .collect::<Vec<_>>();
+        let mut asm = saved_regs
+            .iter()
+            .map(|reg| format!("push {}", reg.name()))
+            .collect::<Vec<_>>();
+
+        // The distance between RSP and the RSP at the patch point.
+        let rsp_offset = call_depth * CALL_STACK_USAGE + saved_regs.len() * 8;
+        // Nothing was clobbered so far, thus all registers, except RSP, still hold
+        // the value they had at the patch point.
+        let load_spill_slot_ptr = match spill_slot_reg {
+            Some(DwarfReg::Rsp) => format!("lea {}, [rsp + 0x{:x}]", value_arg_reg_str, rsp_offset),
+            Some(reg) => format!("mov {}, {}", value_arg_reg_str, reg.name()),
+            None => String::new(),
+        };
+
+        // value_arg_reg = address of the spilled value
+        match spill_slot.loc_type {
+            LocationType::Register => {
+                asm.push(load_spill_slot_ptr);
+            }
+            LocationType::Direct => {
+                asm.push(load_spill_slot_ptr);
+                let offset = spill_slot.offset_or_constant;
+                if offset > 0 {
+                    asm.push(format!("add {}, 0x{:x}", value_arg_reg_str, offset));
+                } else if offset < 0 {
+                    asm.push(format!("sub {}, 0x{:x}", value_arg_reg_str, offset * -1));
+                }
+            }
+            LocationType::Indirect => {
+                asm.push(load_spill_slot_ptr);
+                let offset = spill_slot.offset_or_constant;
+                if offset >= 0 {
+                    asm.push(format!(
+                        "mov {0}, [{0} + 0x{1:x}]",
+                        value_arg_reg_str, offset
+                    ));
+                } else {
+                    asm.push(format!(
+                        "mov {0}, [{0} - 0x{1:x}]",
+                        value_arg_reg_str,
+                        offset * -1
+                    ));
+                }
+            }
+            LocationType::Constant | LocationType::ConstIndex => {
+                asm.push(format!(
+                    "movabs {}, 0x{:x}",
+                    value_arg_reg_str,
+                    spill_slot_constant.unwrap()
+                ));
+            }
+            _ => {
+                return Err(JitError::UnsupportedMutation(format!(
+                    "Unsupported location type: {:#?}",
+                    mce
+                )))
+            }
+        }
+
+        // value_arg_reg = value, the upper 32 bits are zeroed by 32 bit moves.
+        let load_size = value_size_bytes.next_power_of_two().min(8);
+        let value_arg_reg_str32 = value_arg_reg.name_with_size(4).unwrap();
+        match load_size {
+            1 | 2 => asm.push(format!(
+                "movzx {}, {} [{}]",
+                value_arg_reg_str32,
+                DwarfReg::mem_ptr_prefix(load_size),
+                value_arg_reg_str
+            )),
+            4 => asm.push(format!(
+                "mov {}, dword [{}]",
+                value_arg_reg_str32, value_arg_reg_str
+            )),
+            _ => asm.push(format!("mov {0}, qword [{0}]", value_arg_reg_str)),
+        }
+
+        let id: u64 = mce.id().into();
+        asm.push(format!("movabs {}, 0x{:x}", id_arg_reg.name(), id));
+
+        asm.extend_from_slice(&[
+            "push rax".to_owned(),
+            format!("mov rax, 0x{:x}", to.vma().0),
+            "push rax".to_owned(),
+            "mov rax, [rsp+8]".to_owned(),
+            "call [rsp]".to_owned(),
+            "add rsp, 0x10".to_owned(),
+        ]);
+
+        for reg in saved_regs.iter().rev() {
+            asm.push(format!("pop {}", reg.name()));
+        }
+
+        Ok(FunctionTemplate::new(asm, true))
+    }
+
     /// Generate a mutation stub for a `MutationCacheEntry` that
-    /// mutates a stack slot.
-    fn gen_mutation_reg_and_direct(
+    /// mutates a stack slot. The address of the stack slot is determined by the
+    /// type of the entry's spill slot location:
+    /// - Register: The register holds the address.
+    /// - Direct: The address is reg + offset.
+    /// - Indirect: The address was spilled to [reg + offset] and is loaded from there.
+    /// - Constant, ConstIndex: The address is a constant, thus we mutate the memory
+    ///   every consumer of the constant is reading from.
+    ///
+    /// Locations relative to RSP refer to the RSP at the patch point. `call_depth`
+    /// is the number of call stubs between the patch point and this stub, which
+    /// is required to compensate for their stack usage.
+    fn gen_mutation_stub(
         &self,
         mce: &MutationCacheEntry,
+        call_depth: usize,
     ) -> Result<FunctionTemplate, JitError> {
         let mut asm = Vec::<String>::new();
-        let spill_reg: DwarfReg = mce.spill_slot().dwarf_regnum.try_into().unwrap();
-        assert_ne!(
-            spill_reg,
-            DwarfReg::Rsp,
-            "RSP is changed in our caller, so this is currently not supported"
-        );
+        let spill_slot = mce.spill_slot();
+        let spill_slot_constant = mce.spill_slot_constant();
+
+        // Register the address is derived from, if the address is not a constant.
+        let spill_slot_ptr_reg = match spill_slot_constant {
+            Some(_) => None,
+            None => Some(DwarfReg::try_from(spill_slot.dwarf_regnum).unwrap()),
+        };
 
         // Number of bits read each time this mutation is executed.
         let chunk_size_bits = mce.chunk_size_bits();
         let mut chunk_size_bytes_ceiled = chunk_size_bits.div_ceil(8);
 
-        if chunk_size_bits > (8 * 8) {
+        // Chunks that do not fit into a general purpose register are applied
+        // in 8 byte steps.
+        let is_wide = chunk_size_bits > (8 * 8);
+        if is_wide && (chunk_size_bits % 64 != 0 || chunk_size_bits > MAX_CHUNK_SIZE_BITS) {
             return Err(JitError::UnsupportedMutation(format!(
-                "Chunks with size > 8 byte are currently not supported {:#?}",
+                "Chunks with size > 8 byte must be a multiple of 8 byte and at most {} byte {:#?}",
+                MAX_CHUNK_SIZE_BITS / 8,
                 mce
             )));
         }
@@ -610,10 +763,27 @@ impl<'a> Jit<'a> {
 
         // Make it match register sizes
         let chunk_size_bytes_ceiled = chunk_size_bytes_ceiled.next_power_of_two();
-        assert!(chunk_size_bytes_ceiled <= 8);
+        assert!(is_wide || chunk_size_bytes_ceiled <= 8);
 
-        // Pointer to the spilled value
-        let spill_slot_ptr_reg = DwarfReg::try_from(mce.spill_slot().dwarf_regnum).unwrap();
+        // Except XOR, all operators touch the bits of the value that are not covered
+        // by the chunk (e.g., carries of ADD), thus they require the chunk to be as wide
+        // as the register we are operating on.
+        let operator = mce.operator();
+        if is_wide && operator == MutationOperator::Force {
+            return Err(JitError::UnsupportedMutation(format!(
+                "Operator {:?} is not supported for chunks with size > 8 byte {:#?}",
+                operator, mce
+            )));
+        }
+        if !is_wide
+            && operator != MutationOperator::Xor
+            && chunk_size_bits != chunk_size_bytes_ceiled * 8
+        {
+            return Err(JitError::UnsupportedMutation(format!(
+                "Operator {:?} requires a chunk size of 8, 16, 32, or 64 bits {:#?}",
+                operator, mce
+            )));
+        }
 
         // Scratch registers
         asm.push(format!("push rax"));
@@ -622,9 +792,21 @@ impl<'a> Jit<'a> {
         asm.push(format!("push rdx"));
         asm.push(format!("push r11"));
 
+        // The distance between RSP and the RSP at the patch point.
+        let rsp_offset = call_depth * CALL_STACK_USAGE + 5 * 8;
+
         // Make sure we backup the stack slot pointer, since this could also be one
-        // of the registers above (that we are going to clobber).
-        asm.push(format!("push {}", spill_slot_ptr_reg.name()));
+        // of the registers above (that we are going to clobber). RSP is not pushed,
+        // but calculated via `rsp_offset`.
+        let pushed_spill_slot_ptr_reg = spill_slot_ptr_reg.filter(|reg| *reg != DwarfReg::Rsp);
+        if let Some(spill_slot_ptr_reg) = pushed_spill_slot_ptr_reg {
+            asm.push(format!("push {}", spill_slot_ptr_reg.name()));
+        }
+        // rdx = value of the spill slot pointer register at the patch point
+        let load_spill_slot_ptr = match pushed_spill_slot_ptr_reg {
+            Some(_) => format!("mov rdx, [rsp]"),
+            None => format!("lea rdx, [rsp + 0x{:x}]", rsp_offset),
+        };
 
         // rax = &MutationCacheEntry
         asm.push(format!(
@@ -632,6 +814,34 @@ impl<'a> Jit<'a> {
             mce as *const MutationCacheEntry as u64
         ));
 
+        // ebx = hit_cnt++
+        asm.push(format!(
+            "mov ebx, [rax + 0x{:x}]",
+            MutationCacheEntry::hit_cnt_offset()
+        ));
+        asm.push(format!(
+            "inc dword [rax + 0x{:x}]",
+            MutationCacheEntry::hit_cnt_offset()
+        ));
+
+        // The hit filter is read at runtime, thus it can be changed without
+        // regenerating this stub. Hits that are filtered out neither mutate the
+        // value nor advance read_pos.
+        asm.push(format!(
+            "mov ecx, [rax + 0x{:x}]",
+            MutationCacheEntry::hit_filter_len_offset()
+        ));
+        asm.push(format!("test ecx, ecx"));
+        asm.push(format!("jz apply_mutation"));
+        asm.push(format!(
+            "sub ebx, [rax + 0x{:x}]",
+            MutationCacheEntry::hit_filter_start_offset()
+        ));
+        // (hit_cnt - start) as unsigned >= len -> hit is not in the filter
+        asm.push(format!("cmp ebx, ecx"));
+        asm.push(format!("jae skip_mutation"));
+        asm.push(format!("apply_mutation:"));
+
         // rbx = read_pos_bits
         asm.push(format!(
             "mov ebx, [rax + 0x{:x}]",
@@ -655,38 +865,68 @@ impl<'a> Jit<'a> {
         //asm.push(format!("mov {}, 0x0", msk_value_reg.name()));
 
         let msk_value_reg_str = msk_value_reg
-            .name_with_size(chunk_size_bytes_ceiled as u8)
+            .name_with_size(chunk_size_bytes_ceiled.min(8) as u8)
             .unwrap();
 
-        // read chunk_size many bits
-        // msk_value_reg = msk_value
-        asm.push(format!(
-            "mov {}, {} [rax + rdx + 0x{:x}]",
-            msk_value_reg_str,
-            DwarfReg::mem_ptr_prefix(chunk_size_bytes_ceiled as usize),
-            MutationCacheEntry::msk_start_offset()
-        ));
-
-        // Shift out bits that were already read
-        asm.push(format!("shr {}, cl", msk_value_reg.name()));
-
-        // Load the reg of the spill slot
-        asm.push(format!("mov rdx, [rsp]"));
+        if is_wide {
+            // Wide chunks are byte aligned, thus rcx is zero.
+            // msk_value_reg = &msk_value
+            asm.push(format!(
+                "lea {}, [rax + rdx + 0x{:x}]",
+                msk_value_reg.name(),
+                MutationCacheEntry::msk_start_offset()
+            ));
+        } else {
+            // read chunk_size many bits
+            // msk_value_reg = msk_value
+            asm.push(format!(
+                "mov {}, {} [rax + rdx + 0x{:x}]",
+                msk_value_reg_str,
+                DwarfReg::mem_ptr_prefix(chunk_size_bytes_ceiled as usize),
+                MutationCacheEntry::msk_start_offset()
+            ));
+
+            // Shift out bits that were already read
+            asm.push(format!("shr {}, cl", msk_value_reg.name()));
+        }
 
-        if mce.spill_slot().loc_type == LocationType::Direct {
-            let offset = mce.spill_slot().offset_or_constant;
-            if offset > 0 {
-                asm.push(format!("add rdx, 0x{:x}", offset));
-            } else if offset < 0 {
-                asm.push(format!("sub rdx, 0x{:x}", offset * -1));
+        // rdx = address of the spilled value
+        match spill_slot.loc_type {
+            LocationType::Register => {
+                asm.push(load_spill_slot_ptr);
             }
+            LocationType::Direct => {
+                asm.push(load_spill_slot_ptr);
+                let offset = spill_slot.offset_or_constant;
+                if offset > 0 {
+                    asm.push(format!("add rdx, 0x{:x}", offset));
+                } else if offset < 0 {
+                    asm.push(format!("sub rdx, 0x{:x}", offset * -1));
+                }
+            }
+            LocationType::Indirect => {
+                asm.push(load_spill_slot_ptr);
+                let offset = spill_slot.offset_or_constant;
+                if offset >= 0 {
+                    asm.push(format!("mov rdx, [rdx + 0x{:x}]", offset));
+                } else {
+                    asm.push(format!("mov rdx, [rdx - 0x{:x}]", offset * -1));
+                }
+            }
+            LocationType::Constant | LocationType::ConstIndex => {
+                asm.push(format!("movabs rdx, 0x{:x}", spill_slot_constant.unwrap()));
+            }
+            _ => unreachable!("{:#?}", spill_slot),
         }
 
-        match chunk_size_bits {
-            v if v % 8 == 0 => {
+        match (operator, chunk_size_bits) {
+            (_, v) if is_wide => {
+                Self::gen_wide_apply(&mut asm, operator, v as usize / 8);
+            }
+            (MutationOperator::Xor, v) if v % 8 == 0 => {
                 asm.push(format!("xor [rdx], {}", msk_value_reg_str));
             }
-            v => {
+            (MutationOperator::Xor, v) => {
                 asm.push(format!(
                     "and {}, 0x{:x}",
                     msk_value_reg_str,
@@ -694,6 +934,38 @@ impl<'a> Jit<'a> {
                 ));
                 asm.push(format!("xor [rdx], {}", msk_value_reg_str));
             }
+            (MutationOperator::Or, _) => {
+                asm.push(format!("or [rdx], {}", msk_value_reg_str));
+            }
+            (MutationOperator::AndNot, _) => {
+                asm.push(format!("not {}", msk_value_reg_str));
+                asm.push(format!("and [rdx], {}", msk_value_reg_str));
+            }
+            (MutationOperator::Add, _) => {
+                asm.push(format!("add [rdx], {}", msk_value_reg_str));
+            }
+            (MutationOperator::Sub, _) => {
+                asm.push(format!("sub [rdx], {}", msk_value_reg_str));
+            }
+            (MutationOperator::Set, _) => {
+                // rcx is free at this point. Since cmov does not support 8 bit
+                // operands, we select on the full registers and only store the
+                // lower chunk_size_bits.
+                let value_reg_str = DwarfReg::Rcx
+                    .name_with_size(chunk_size_bytes_ceiled as u8)
+                    .unwrap();
+                asm.push(format!("mov {}, [rdx]", value_reg_str));
+                asm.push(format!("test {}, {}", msk_value_reg_str, msk_value_reg_str));
+                asm.push(format!("cmovnz rcx, {}", msk_value_reg.name()));
+                asm.push(format!("mov [rdx], {}", value_reg_str));
+            }
+            (MutationOperator::Force, _) => {
+                asm.push(format!("test {}, {}", msk_value_reg_str, msk_value_reg_str));
+                asm.push(format!("jz force_done"));
+                asm.push(format!("dec {}", msk_value_reg_str));
+                asm.push(format!("mov [rdx], {}", msk_value_reg_str));
+                asm.push(format!("force_done:"));
+            }
         }
 
         // Current register content
@@ -726,7 +998,10 @@ impl<'a> Jit<'a> {
             MutationCacheEntry::read_pos_bits_offset()
         ));
 
-        asm.push(format!("pop {}", spill_slot_ptr_reg.name()));
+        asm.push(format!("skip_mutation:"));
+        if let Some(spill_slot_ptr_reg) = pushed_spill_slot_ptr_reg {
+            asm.push(format!("pop {}", spill_slot_ptr_reg.name()));
+        }
         asm.push(format!("pop r11"));
         asm.push(format!("pop rdx"));
         asm.push(format!("pop rcx"));
@@ -737,12 +1012,64 @@ impl<'a> Jit<'a> {
         Ok(FunctionTemplate::new(asm, true))
     }
 
+    /// Apply the `chunk_size_bytes` bytes mask chunk at [r11] to the value at [rdx]
+    /// in 8 byte steps. Clobbers rcx.
+    fn gen_wide_apply(asm: &mut Vec<String>, operator: MutationOperator, chunk_size_bytes: usize) {
+        assert!(chunk_size_bytes % 8 == 0);
+        let offsets = (0..chunk_size_bytes).step_by(8);
+
+        if operator == MutationOperator::Set {
+            // ZF = chunk == 0
+            asm.push(format!("mov rcx, [r11]"));
+            for offset in offsets.clone().skip(1) {
+                asm.push(format!("or rcx, [r11 + 0x{:x}]", offset));
+            }
+            // mov and cmov do not modify the flags.
+            for offset in offsets {
+                asm.push(format!("mov rcx, [rdx + 0x{:x}]", offset));
+                asm.push(format!("cmovnz rcx, [r11 + 0x{:x}]", offset));
+                asm.push(format!("mov [rdx + 0x{:x}], rcx", offset));
+            }
+            return;
+        }
+
+        for offset in offsets {
+            asm.push(format!("mov rcx, [r11 + 0x{:x}]", offset));
+            // The carry of ADD and SUB is propagated to the next step, since mov does
+            // not modify the flags.
+            let instruction = match (operator, offset) {
+                (MutationOperator::Xor, _) => "xor",
+                (MutationOperator::Or, _) => "or",
+                (MutationOperator::AndNot, _) => {
+                    asm.push(format!("not rcx"));
+                    "and"
+                }
+                (MutationOperator::Add, 0) => "add",
+                (MutationOperator::Add, _) => "adc",
+                (MutationOperator::Sub, 0) => "sub",
+                (MutationOperator::Sub, _) => "sbb",
+                (MutationOperator::Set | MutationOperator::Force, _) => unreachable!(),
+            };
+            asm.push(format!("{} [rdx + 0x{:x}], rcx", instruction, offset));
+        }
+    }
+
     /// Generate an assembler template that implements everything necessary to apply the mutations to the values in the `mce`.`
-    pub fn gen_mutation(&self, mce: &MutationCacheEntry) -> Result<FunctionTemplate, JitError> {
+    /// `call_depth` is the number of stubs generated via `gen_call` or `gen_call_multiplexer`
+    /// that are on the path between the patch point and the returned stub.
+    pub fn gen_mutation(
+        &self,
+        mce: &MutationCacheEntry,
+        call_depth: usize,
+    ) -> Result<FunctionTemplate, JitError> {
         assert!(mce.msk_len() > 0);
         match mce.spill_slot().loc_type {
-            LocationType::Register | LocationType::Direct => {
-                return self.gen_mutation_reg_and_direct(mce);
+            LocationType::Register
+            | LocationType::Direct
+            | LocationType::Indirect
+            | LocationType::Constant
+            | LocationType::ConstIndex => {
+                return self.gen_mutation_stub(mce, call_depth);
             }
             _ => Err(JitError::UnsupportedMutation(format!(
                 "Unsupported location type: {:#?}",
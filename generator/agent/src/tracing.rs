@@ -3,6 +3,7 @@ use std::slice;
 use std::sync::atomic::Ordering;
 use std::{collections::HashSet, mem::transmute};
 
+use fuzztruction_shared::messages::MAX_TRACED_VALUES;
 use fuzztruction_shared::util::ExpectNone;
 use libc::{self, c_void, MAP_ANONYMOUS, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE};
 use std::fmt::Debug;
@@ -34,6 +35,8 @@ pub struct TraceEntry<T> {
     /// time of discovery. Odering entries ascending according to their `order`
     /// id allows to determine whether a entry was covered before or after anotherone.
     pub order: Option<NonZeroU64>,
+    /// The values reported by the first `MAX_TRACED_VALUES` hits.
+    pub values: [u64; MAX_TRACED_VALUES],
 }
 
 impl<T> TraceEntry<T> {
@@ -42,8 +45,15 @@ impl<T> TraceEntry<T> {
             value,
             hits: 0,
             order: None,
+            values: [0; MAX_TRACED_VALUES],
         }
     }
+
+    /// The values reported by the recorded hits.
+    pub fn values(&self) -> &[u64] {
+        let len = usize::try_from(self.hits).unwrap_or(usize::MAX);
+        &self.values[..len.min(MAX_TRACED_VALUES)]
+    }
 }
 
 impl<'a, T> TraceMap<'a, T>
@@ -119,9 +129,10 @@ where
         self.data.as_deref()
     }
 
-    /// Increment the count for `id` by one. Id must have been registered with alloc_slot().
+    /// Increment the count for `id` by one and record `value`, if any, as value
+    /// observed during this hit. Id must have been registered with alloc_slot().
     /// If not, this function panics.
-    pub fn report_hit(&mut self, id: T) {
+    pub fn report_hit(&mut self, id: T, value: Option<u64>) {
         if !IS_CHILD.load(Ordering::Relaxed) {
             return;
         }
@@ -138,7 +149,12 @@ where
         if entry.order.is_none() {
             entry.order = NonZeroU64::new(self.total_hits);
         }
-        data[idx].hits += 1;
+        if let (Some(value), Ok(hit_idx)) = (value, usize::try_from(entry.hits)) {
+            if hit_idx < MAX_TRACED_VALUES {
+                entry.values[hit_idx] = value;
+            }
+        }
+        entry.hits += 1;
     }
 
     /// Reset that map and all state.
@@ -2,3 +2,9 @@
 
 #define FT_PATCH_POINT_SIZE 32
 #define MQ_DEFAULT_TIMEOUT_MS 1000 * 10
+
+/* Kinds of call fault sites. The kind, the hash of the callee's name, and the ID
+   of the call are recorded as additional live values of the patch point. */
+#define FT_CALL_FAULT_RETURN_INT 1
+#define FT_CALL_FAULT_RETURN_PTR 2
+#define FT_CALL_FAULT_ERRNO 3
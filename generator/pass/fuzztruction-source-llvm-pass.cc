@@ -60,19 +60,23 @@ namespace {
     class FuzztructionSourcePass : public PassInfoMixin<FuzztructionSourcePass> {
     private:
         std::set<Value *> instrumented_values;
+        /* Number of calls instrumented by instrumentCallReturn in the current module. */
+        uint64_t call_fault_count = 0;
 
     public:
         static bool allow_ptr_ty;
         static bool allow_vec_ty;
 
-        enum InsTy {Random = 0, Load = 1, Store = 2, Add = 3, Sub = 4, Icmp = 5, Select = 6, Branch = 7, Switch = 8, Call = 9};
-        static std::string insTyNames[10];
+        enum InsTy {Random = 0, Load = 1, Store = 2, Add = 3, Sub = 4, Icmp = 5, Select = 6, Branch = 7, Switch = 8, Call = 9, CallRet = 10};
+        static std::string insTyNames[11];
 
         bool initializeFuzzingStub(Module &M);
         bool injectPatchPoints(Module &M);
         std::vector<Value *> getPatchpointArgs(Module &M, uint32_t id);
-        bool instrumentInsArg(Module &M, Function *stackmap_intr, Instruction *ins, uint8_t op_idx);
+        bool instrumentInsArg(Module &M, Function *stackmap_intr, Instruction *ins, uint8_t op_idx,
+                              const std::vector<Value *> &extra_live_values = {});
         bool instrumentCall(Module &M, Function *stackmap_intr, CallInst *ins);
+        bool instrumentCallReturn(Module &M, Function *stackmap_intr, CallInst *call);
         bool instrumentInsOutput(Module &M, Function *stackmap_intr, Instruction *ins);
         bool maybeDeleteFunctionCall(Module &M, CallInst *call_ins, std::set<std::string> &target_functions);
         bool filterInvalidPatchPoints(Module &M);
@@ -199,6 +203,8 @@ FuzztructionSourcePass::InsTy to_InsTy(std::string input) {
         return FuzztructionSourcePass::InsTy::Switch;
     if (input == "call")
         return FuzztructionSourcePass::InsTy::Call;
+    if (input == "call_ret")
+        return FuzztructionSourcePass::InsTy::CallRet;
 
     errs() << "Unsupported instruction string received: " << input << "\n";
     exit(1);
@@ -233,8 +239,12 @@ bool FuzztructionSourcePass::initializeFuzzingStub(Module &M) {
         ins_builder.CreateCall(init_hook_fn);
     }
 
+    // The listen and bind hooks receive the socket fd, thus the agent can report the address the socket is bound to.
+    auto int32_ty = Type::getInt32Ty(M.getContext());
+    auto socket_hook_ty = FunctionType::get(Type::getVoidTy(M.getContext()), {int32_ty}, false);
+
     // Insert a call to __ft_after_listen after the 'listen' function returns.
-    auto listen_hook_fn = M.getOrInsertFunction("__ft_after_listen", FunctionType::getVoidTy(M.getContext()));
+    auto listen_hook_fn = M.getOrInsertFunction("__ft_after_listen", socket_hook_ty);
     auto listen_fn = M.getFunction("listen");
     if (listen_fn && listen_fn->arg_size() == 2) {
         // check if arg types match those of the type of listen function we are interested in.
@@ -245,21 +255,40 @@ bool FuzztructionSourcePass::initializeFuzzingStub(Module &M) {
                 if(CallInst* call_ins = dyn_cast<CallInst>(user)) {
                     dbgs() << *call_ins << "\n";
                     IRBuilder<> ins_builder(call_ins->getNextNode());
-                    ins_builder.CreateCall(listen_hook_fn);
+                    auto fd = ins_builder.CreateIntCast(call_ins->getArgOperand(0), int32_ty, true);
+                    ins_builder.CreateCall(listen_hook_fn, {fd});
                 }
             }
         }
     }
 
     // Insert a call to __ft_after_bind after the 'bind' function returns.
-    auto bind_hook_fn = M.getOrInsertFunction("__ft_after_bind", FunctionType::getVoidTy(M.getContext()));
+    auto bind_hook_fn = M.getOrInsertFunction("__ft_after_bind", socket_hook_ty);
     auto bind_fn = M.getFunction("bind");
-    if (bind_fn && bind_fn->arg_size() == 3) {
+    if (bind_fn && bind_fn->arg_size() == 3 && bind_fn->getArg(0)->getType()->isIntegerTy()) {
         for (const auto& user : bind_fn->users()) {
             if(CallInst* call_ins = dyn_cast<CallInst>(user)) {
                 dbgs() << *call_ins << "\n";
                 IRBuilder<> ins_builder(call_ins->getNextNode());
-                ins_builder.CreateCall(bind_hook_fn);
+                auto fd = ins_builder.CreateIntCast(call_ins->getArgOperand(0), int32_ty, true);
+                ins_builder.CreateCall(bind_hook_fn, {fd});
+            }
+        }
+    }
+
+    // Insert a call to __ft_before_accept before each 'accept'/'accept4' call, since a server that
+    // entered accept is ready to handle connections.
+    auto accept_hook_fn = M.getOrInsertFunction("__ft_before_accept", socket_hook_ty);
+    for (auto accept_fn_name : {"accept", "accept4"}) {
+        auto accept_fn = M.getFunction(accept_fn_name);
+        if (!accept_fn || accept_fn->arg_size() < 3 || !accept_fn->getArg(0)->getType()->isIntegerTy())
+            continue;
+        for (const auto& user : accept_fn->users()) {
+            if(CallInst* call_ins = dyn_cast<CallInst>(user)) {
+                dbgs() << "patching accept call: " << *call_ins << "\n";
+                IRBuilder<> ins_builder(call_ins);
+                auto fd = ins_builder.CreateIntCast(call_ins->getArgOperand(0), int32_ty, true);
+                ins_builder.CreateCall(accept_hook_fn, {fd});
             }
         }
     }
@@ -276,6 +305,29 @@ bool FuzztructionSourcePass::initializeFuzzingStub(Module &M) {
         }
     }
 
+    // Insert a call to __ft_after_socket_io after each call that may transfer data via a socket.
+    // The hook receives the fd and the return value, thus it can ignore failed calls and non-socket fds.
+    auto int64_ty = Type::getInt64Ty(M.getContext());
+    auto io_hook_fn = M.getOrInsertFunction("__ft_after_socket_io",
+        FunctionType::get(Type::getVoidTy(M.getContext()), {int32_ty, int64_ty}, false));
+    for (auto io_fn_name : {"send", "sendto", "sendmsg", "recv", "recvfrom", "recvmsg", "read", "write", "readv", "writev"}) {
+        auto io_fn = M.getFunction(io_fn_name);
+        if (!io_fn || io_fn->arg_size() < 3 || !io_fn->getArg(0)->getType()->isIntegerTy()
+            || !io_fn->getReturnType()->isIntegerTy())
+            continue;
+        for (const auto& user : io_fn->users()) {
+            if(CallInst* call_ins = dyn_cast<CallInst>(user)) {
+                if (call_ins->getCalledFunction() != io_fn)
+                    continue;
+                dbgs() << "patching socket io call: " << *call_ins << "\n";
+                IRBuilder<> ins_builder(call_ins->getNextNode());
+                auto fd = ins_builder.CreateIntCast(call_ins->getArgOperand(0), int32_ty, true);
+                auto ret = ins_builder.CreateIntCast(call_ins, int64_ty, true);
+                ins_builder.CreateCall(io_hook_fn, {fd, ret});
+            }
+        }
+    }
+
     return true;
 }
 
@@ -584,7 +636,8 @@ Instrument (one of) the input value(s) to the instruction (as specified by opera
 This input value is the live value connected to the patchpoint, where it can be modified before being
 processed by the instruction.
 */
-bool FuzztructionSourcePass::instrumentInsArg(Module &M, Function *stackmap_intr, Instruction *ins, uint8_t op_idx) {
+bool FuzztructionSourcePass::instrumentInsArg(Module &M, Function *stackmap_intr, Instruction *ins, uint8_t op_idx,
+                                              const std::vector<Value *> &extra_live_values) {
     if (!ins)
         return false;
 
@@ -609,6 +662,7 @@ bool FuzztructionSourcePass::instrumentInsArg(Module &M, Function *stackmap_intr
     // record stack slot
     patchpoint_args.push_back(slot);
     patchpoint_args.push_back(irb.getInt64(get_alloction_size_in_bits(slot, op)));
+    patchpoint_args.insert(patchpoint_args.end(), extra_live_values.begin(), extra_live_values.end());
     irb.CreateCall(stackmap_intr, patchpoint_args);
 
     // Load from stack
@@ -620,6 +674,39 @@ bool FuzztructionSourcePass::instrumentInsArg(Module &M, Function *stackmap_intr
     return true;
 }
 
+/*
+Get the condition values that select the successors of `switch_ins` in successor
+order, i.e., the first value selects the default destination. These are recorded
+as additional live values, which allows the fuzzer to force a specific successor.
+Returns an empty vector if the condition is wider than 64 bit, the switch has too
+many cases, or there is no value that selects the default destination.
+*/
+std::vector<Value *> getSwitchSuccessorValues(SwitchInst *switch_ins) {
+    const unsigned max_cases = 64;
+    std::vector<Value *> ret;
+
+    auto cond_ty = dyn_cast<IntegerType>(switch_ins->getCondition()->getType());
+    if (!cond_ty || cond_ty->getBitWidth() > 64 || switch_ins->getNumCases() > max_cases)
+        return ret;
+
+    std::set<uint64_t> case_values;
+    for (auto &c : switch_ins->cases())
+        case_values.insert(c.getCaseValue()->getZExtValue());
+
+    // Any value that is not a case selects the default destination.
+    uint64_t default_value = 0;
+    while (case_values.count(default_value) > 0)
+        default_value++;
+    if (cond_ty->getBitWidth() < 64 && default_value >> cond_ty->getBitWidth())
+        return ret;
+
+    IntegerType *i64_type = IntegerType::getInt64Ty(switch_ins->getContext());
+    ret.push_back(ConstantInt::get(i64_type, default_value));
+    for (auto &c : switch_ins->cases())
+        ret.push_back(ConstantInt::get(i64_type, c.getCaseValue()->getZExtValue()));
+    return ret;
+}
+
 bool FuzztructionSourcePass::instrumentCall(Module &M, Function *stackmap_intr, CallInst *call) {
     if (!call->user_empty())
         return false;
@@ -654,7 +741,105 @@ bool FuzztructionSourcePass::instrumentCall(Module &M, Function *stackmap_intr,
     return true;
 }
 
+/*
+64 bit FNV-1a hash of `name`. The fuzzer uses the same hash to match callees
+against the callees that may be faulted.
+*/
+uint64_t fnv1a_hash(StringRef name) {
+    uint64_t hash = 0xcbf29ce484222325;
+    for (unsigned char c : name) {
+        hash ^= c;
+        hash *= 0x100000001b3;
+    }
+    return hash;
+}
+
+/*
+Record the return value of `call` such that it can be replaced by an error value, e.g.,
+NULL or -1. For integer return values, an additional patch point allows to set errno,
+since callers often check it after a failed call. Both patch points record the same
+call ID, which allows the fuzzer to pair the errno site with its return value site.
+*/
+bool FuzztructionSourcePass::instrumentCallReturn(Module &M, Function *stackmap_intr, CallInst *call) {
+    auto callee = call->getCalledFunction();
+    if (!callee || callee->isIntrinsic() || callee->getName().starts_with("__ft"))
+        return false;
+    if (call->user_empty() || call->isMustTailCall())
+        return false;
+
+    auto ret_ty = call->getType();
+    uint64_t kind;
+    if (ret_ty->isPointerTy())
+        kind = FT_CALL_FAULT_RETURN_PTR;
+    else if (ret_ty->isIntegerTy() && ret_ty->getIntegerBitWidth() <= 64)
+        kind = FT_CALL_FAULT_RETURN_INT;
+    else
+        return false;
+
+    if (this->instrumented_values.count(call) > 0) {
+        return false;
+    } else {
+        this->instrumented_values.insert(call);
+    }
+
+    auto callee_hash = fnv1a_hash(callee->getName());
+    // The fuzzer pairs sites by their function and this ID, thus a per-module counter suffices.
+    auto call_id = this->call_fault_count++;
+    auto parent_fn = call->getParent()->getParent();
+    auto insertion_point = parent_fn->getEntryBlock().getFirstInsertionPt();
+    IRBuilder<> irb(&*insertion_point);
+    auto *slot = irb.CreateAlloca(ret_ty);
 
+    std::vector<User *> users;
+    for (auto user: call->users()) {
+        users.push_back(user);
+    }
+
+    Instruction *next_ins = call->getNextNode();
+    irb.SetInsertPoint(next_ins);
+
+    irb.CreateStore(call, slot);
+    std::vector<Value *> patchpoint_args = getPatchpointArgs(M, call->getOpcode());
+    patchpoint_args.push_back(slot);
+    patchpoint_args.push_back(irb.getInt64(get_alloction_size_in_bits(slot, call)));
+    patchpoint_args.push_back(irb.getInt64(kind));
+    patchpoint_args.push_back(irb.getInt64(callee_hash));
+    patchpoint_args.push_back(irb.getInt64(call_id));
+    irb.CreateCall(stackmap_intr, patchpoint_args);
+
+    auto mutated_ret = irb.CreateLoad(ret_ty, slot);
+    for (auto user : users) {
+        user->replaceUsesOfWith(call, mutated_ret);
+    }
+
+    if (kind != FT_CALL_FAULT_RETURN_INT)
+        return true;
+
+    irb.SetInsertPoint(&*parent_fn->getEntryBlock().getFirstInsertionPt());
+    auto *errno_slot = irb.CreateAlloca(irb.getInt32Ty());
+
+    // errno is only written if the value taken from the patch point is not zero.
+    irb.SetInsertPoint(next_ins);
+    irb.CreateStore(irb.getInt32(0), errno_slot);
+    patchpoint_args = getPatchpointArgs(M, call->getOpcode());
+    patchpoint_args.push_back(errno_slot);
+    patchpoint_args.push_back(irb.getInt64(32));
+    patchpoint_args.push_back(irb.getInt64(FT_CALL_FAULT_ERRNO));
+    patchpoint_args.push_back(irb.getInt64(callee_hash));
+    patchpoint_args.push_back(irb.getInt64(call_id));
+    irb.CreateCall(stackmap_intr, patchpoint_args);
+
+    auto errno_value = irb.CreateLoad(irb.getInt32Ty(), errno_slot);
+    auto errno_is_set = irb.CreateICmpNE(errno_value, irb.getInt32(0));
+    auto *set_errno_block = llvm::SplitBlockAndInsertIfThen(errno_is_set, next_ins, false);
+    irb.SetInsertPoint(set_errno_block);
+    auto errno_location_fn = M.getOrInsertFunction("__errno_location",
+        FunctionType::get(PointerType::get(irb.getInt32Ty(), 0), false));
+    auto errno_ptr = irb.CreateCall(errno_location_fn);
+    irb.CreateStore(errno_value, errno_ptr);
+
+    return true;
+}
 
 bool isValidTy(Type* ty) {
     if (ty->isIntegerTy())
@@ -718,6 +903,9 @@ Instruction types are specified in FT_HOOK_INS=store:50,load,add
 Format is 'instruction_name':'probability of selecting a specific instance'.
 Instruction name must be one of the following: add, sub, store, load, random
 
+The value call_ret records the return values of calls to named functions, which allows to
+fault them, e.g., to let malloc return NULL or write return -1 with a chosen errno.
+
 The value random is special in the sense that each instruction we can instrument, is actually instrumented.
 We recommend to set a probability, at least for random (to avoid instrumenting too many instructions).
 */
@@ -904,7 +1092,8 @@ bool FuzztructionSourcePass::injectPatchPoints(Module &M) {
                         case FuzztructionSourcePass::InsTy::Switch:
                             if (I->getOpcode() == Instruction::Switch && distr(gen) <= ins_hook.probability) {
                                 // Arg 0 is the switch condition (intty)
-                                ins_modified = instrumentInsArg(M, stackmap_intr, I, 0);
+                                auto successor_values = getSwitchSuccessorValues(cast<SwitchInst>(I));
+                                ins_modified = instrumentInsArg(M, stackmap_intr, I, 0, successor_values);
                             }
                             break;
                         case FuzztructionSourcePass::InsTy::Random:
@@ -914,12 +1103,20 @@ bool FuzztructionSourcePass::injectPatchPoints(Module &M) {
                                 ins_modified = instrumentInsOutput(M, stackmap_intr, I);
                             }
                             break;
-                        case FuzztructionSourcePass::InsTy::Call:
+                        case FuzztructionSourcePass::InsTy::Call: {
                             auto *call = dyn_cast<CallInst>(I);
                             if (call && distr(gen) <= ins_hook.probability) {
                                 ins_modified = instrumentCall(M, stackmap_intr, call);
                             }
                             break;
+                        }
+                        case FuzztructionSourcePass::InsTy::CallRet: {
+                            auto *call = dyn_cast<CallInst>(I);
+                            if (call && distr(gen) <= ins_hook.probability) {
+                                ins_modified = instrumentCallReturn(M, stackmap_intr, call);
+                            }
+                            break;
+                        }
                     }
                     if (ins_modified) {
                         modified = true;
@@ -990,7 +1187,7 @@ bool FuzztructionSourcePass::filterInvalidPatchPoints(Module &M) {
 
 bool FuzztructionSourcePass::allow_ptr_ty = false;
 bool FuzztructionSourcePass::allow_vec_ty = false;
-std::string FuzztructionSourcePass::insTyNames[] = {"random", "load", "store", "add", "sub", "icmp", "select", "branch", "switch", "call"};
+std::string FuzztructionSourcePass::insTyNames[] = {"random", "load", "store", "add", "sub", "icmp", "select", "branch", "switch", "call", "call_ret"};
 
 extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
 llvmGetPassPluginInfo() {
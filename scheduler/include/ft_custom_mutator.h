@@ -0,0 +1,43 @@
+#pragma once
+#include <stdlib.h>
+#include <stdint.h>
+#include <stdbool.h>
+
+/*
+ * Interface of custom mutator plugins, see scheduler/src/custom_mutator.rs.
+ * A plugin is a shared library that exports ft_custom_mutator_descriptor().
+ */
+
+#define FT_CUSTOM_MUTATOR_API_VERSION 1
+
+typedef struct {
+    uint64_t site_id;
+    uint32_t llvm_instruction;
+    uint32_t value_size_bits;
+    uint32_t chunk_size_bits;
+    uint64_t hits;
+} ft_site_info_t;
+
+typedef struct {
+    uint64_t entry_id;
+    /* UINT64_MAX if the entry has no parent. */
+    uint64_t parent_id;
+    uint64_t generation;
+    const uint8_t* input;
+    size_t input_len;
+} ft_entry_info_t;
+
+typedef struct {
+    /* Must be FT_CUSTOM_MUTATOR_API_VERSION. */
+    uint32_t api_version;
+    const char* name;
+    void* (*init)(uint64_t seed);
+    void (*deinit)(void* state);
+    /* Returns the number of masks the mutator wants to produce. */
+    size_t (*begin)(void* state, const ft_site_info_t* site, const ft_entry_info_t* entry,
+                    const uint8_t* msk, size_t msk_len);
+    /* Writes the next mask into msk or returns false if there are none left. */
+    bool (*next)(void* state, uint8_t* msk, size_t msk_len);
+} ft_custom_mutator_descriptor_t;
+
+const ft_custom_mutator_descriptor_t* ft_custom_mutator_descriptor(void);
@@ -126,6 +126,19 @@ fn parse_args() -> ArgMatches {
                     .help("Depending on the load of the system additional jobs are spawned")
                     .action(clap::ArgAction::SetTrue)
                 )
+                .arg(
+                    Arg::new("resume")
+                    .long("resume")
+                    .help("Resume the campaign found in the workdir instead of starting from the seed files. The timeout applies to the resumed run only")
+                    .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    Arg::new("cerebrum-prior")
+                    .long("cerebrum-prior")
+                    .help("Path to the cerebrum-stats.json of a previous campaign, e.g., against an older build of the source. Its knowledge about patch points and mutators is imported as a prior")
+                    .takes_value(true)
+                    .conflicts_with("resume")
+                )
         )
         .subcommand(
             Command::new("benchmark")
@@ -192,6 +205,20 @@ fn parse_args() -> ArgMatches {
                     .long("crashes-only")
                     .action(clap::ArgAction::SetTrue)
             )
+            .arg(
+                Arg::new("transcript")
+                    .help("Print the messages exchanged by the peers of networked targets")
+                    .long("transcript")
+                    .action(clap::ArgAction::SetTrue)
+            )
+            .arg(
+                Arg::new("replay-script")
+                    .help("Directory to write scripts to that replay the source's side of each transcript")
+                    .long("replay-script")
+                    .value_parser(value_parser!(PathBuf))
+                    .takes_value(true)
+                    .required(false)
+            )
             .arg(
                 Arg::new("id")
                 .help("ID's of the to be dumped queue entry")
@@ -289,6 +316,33 @@ fn parse_args() -> ArgMatches {
                     .action(clap::ArgAction::SetTrue)
                 )
         )
+        .subcommand(
+            Command::new("minimize")
+                .about("Minimize the mutations and the input of a crashing queue entry while the crash still reproduces.")
+                .arg(
+                    Arg::new("id")
+                        .help("Queue entry ID of the crash to minimize")
+                        .long("id")
+                        .takes_value(true)
+                        .required(true)
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .help("Timeout after that an execution is considered hanging")
+                        .short('t')
+                        .long("timeout")
+                        .takes_value(true)
+                        .default_value("10s"),
+                )
+                .arg(
+                    Arg::new("attempts")
+                        .help("Number of executions a candidate has to trigger the crash. Increase this for crashes that do not reproduce reliably.")
+                        .long("attempts")
+                        .short('a')
+                        .takes_value(true)
+                        .default_value("1")
+                )
+        )
         .subcommand(
             Command::new("extract-pcaps")
                 .about("Extract pcaps from all queue entries.")
@@ -374,10 +428,27 @@ fn parse_args() -> ArgMatches {
 
 /// Check whether the workdir already exists and raises an error if --purge
 /// was not passed. If this function returns `Ok`, the workdir is empty, but
-/// exists.
+/// exists. If a campaign is resumed, the workdir must exist and is kept as it is.
 fn check_workdir(config: &mut Config, matches: &ArgMatches) -> Result<()> {
     let purge_flag = matches.get_flag("purge");
 
+    let resume_flag = matches
+        .subcommand_matches("fuzz")
+        .map(|m| m.get_flag("resume"))
+        .unwrap_or(false);
+    if resume_flag {
+        if purge_flag {
+            return Err(anyhow!("--resume and --purge are mutually exclusive"));
+        }
+        if !config.general.work_dir.exists() {
+            return Err(anyhow!(
+                "Workdir {:?} does not exist, thus there is nothing to resume",
+                config.general.work_dir
+            ));
+        }
+        return Ok(());
+    }
+
     // We only purge if this is the fuzz or benchmark subcommand.
     let expects_empty_dir = matches!(
         matches.subcommand_name().unwrap_or(""),
@@ -528,6 +599,9 @@ fn real_main() -> Result<()> {
         Some(("crash-reproduction", matches)) => {
             handler::handle_crash_reproduction_subcommand(matches, &config)?;
         }
+        Some(("minimize", matches)) => {
+            handler::handle_minimize_subcommand(matches, &config)?;
+        }
         Some(("extract-pcaps", matches)) => {
             handler::handle_extract_pcaps_subcommand(matches, &config)?;
         }
@@ -1,4 +1,5 @@
 use std::{
+    path::PathBuf,
     sync::{self, atomic::AtomicBool, Arc},
     time::{Duration, Instant},
 };
@@ -43,7 +44,7 @@ pub(crate) fn handle_cli_test_patchpoints_subcommand(
 
 pub(crate) fn handle_cli_fuzz_subcommand(
     fuzz_matches: &ArgMatches,
-    config: scheduler::config::Config,
+    mut config: scheduler::config::Config,
     termination_requested_flag: Arc<AtomicBool>,
 ) {
     let timeout = fuzz_matches
@@ -62,7 +63,20 @@ pub(crate) fn handle_cli_fuzz_subcommand(
         .value_of("jobs")
         .map(|e| e.parse().unwrap())
         .unwrap();
-    let mut campaign = FuzzingCampaign::new(&config).unwrap();
+    if let Some(prior) = fuzz_matches.value_of("cerebrum-prior") {
+        let prior = PathBuf::from(prior);
+        if !prior.is_file() {
+            log::error!("Cerebrum prior {:?} does not exist", prior);
+            std::process::exit(1);
+        }
+        config.general.cerebrum_prior = Some(prior);
+    }
+
+    let mut campaign = if fuzz_matches.get_flag("resume") {
+        FuzzingCampaign::resume(&config).unwrap()
+    } else {
+        FuzzingCampaign::new(&config).unwrap()
+    };
     campaign.start(job_cnt).unwrap();
     log::info!("Fuzzing campaign timeout is set to {:?}", timeout);
 
@@ -163,13 +177,10 @@ pub(crate) fn handle_cli_benchmark_subcommand(
 
     let with_mutations = benchmark_matches.is_present("with-mutations");
 
-    if matches!(
-        config.source.input_type,
-        InputChannel::Tcp | InputChannel::Udp
-    ) {
+    if config.target_uses_network() {
         assert!(matches!(
             config.sink.input_type,
-            InputChannel::Tcp | InputChannel::Udp
+            InputChannel::Tcp | InputChannel::Udp | InputChannel::Unix
         ));
         networked_benchmark::benchmark_target(
             config,
@@ -386,6 +397,23 @@ pub(crate) fn handle_crash_reproduction_subcommand(
     Ok(())
 }
 
+pub(crate) fn handle_minimize_subcommand(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let termination_flag = register_on_termination_flag();
+    let timeout = matches
+        .value_of("timeout")
+        .map(|e| e.parse::<CliDuration>().unwrap())
+        .map(|v| v.0)
+        .unwrap();
+    let attempts = matches
+        .value_of("attempts")
+        .map(|e| e.parse().unwrap())
+        .unwrap();
+    let target_qe_id: u64 = matches.value_of("id").map(|v| v.parse().unwrap()).unwrap();
+
+    postprocessing::ft_minimize_crash(config, termination_flag, timeout, target_qe_id, attempts)?;
+    Ok(())
+}
+
 pub(crate) fn handle_extract_pcaps_subcommand(
     _matches: &ArgMatches,
     config: &Config,
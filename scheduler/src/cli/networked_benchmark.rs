@@ -1,8 +1,10 @@
 use clap::ArgMatches;
-use nix::{sys::signal::Signal::SIGTERM, unistd::Pid};
 use scheduler::{
     config::Config,
-    networked::{configure_tcpdump, get_consumer, get_producer, Client, Server, WaitForPeerResult},
+    networked::{
+        configure_pcap_recording, get_consumer_and_producer, get_producer,
+        wait_for_consumer_termination, Client, Server, WaitForPeerResult,
+    },
     sink::{self, AflSink},
     source::Source,
 };
@@ -12,7 +14,6 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    thread,
     time::{Duration, Instant},
 };
 
@@ -24,8 +25,19 @@ pub fn benchmark_target(
     termination_requested_flag: Arc<AtomicBool>,
     benchmark_matches: &ArgMatches,
 ) {
-    let pacap_recording_cmd = if benchmark_matches.get_flag("record-pcap") {
-        configure_tcpdump(config, None, false)
+    if config.topology.is_some() {
+        log::error!("Benchmarking is not supported for targets with a topology configured");
+        return;
+    }
+
+    let pcap_recording = if benchmark_matches.get_flag("record-pcap") {
+        match configure_pcap_recording(config, None, false) {
+            Ok(recording) => recording,
+            Err(err) => {
+                log::error!("Failed to record pcap: {err:#}");
+                return;
+            }
+        }
     } else {
         None
     };
@@ -75,10 +87,8 @@ pub fn benchmark_target(
         let client_spawn_ts = Instant::now();
         client.spawn(timeout).unwrap();
 
-        let consumer = get_consumer(&mut client, &mut server);
-        let ret = consumer
-            .wait_for_child_termination(timeout, false, None)
-            .unwrap();
+        let (consumer, producer) = get_consumer_and_producer(&mut client, &mut server);
+        let ret = wait_for_consumer_termination(consumer, producer, config, timeout, None).unwrap();
 
         log::info!("Client took {:?} to terminate.", client_spawn_ts.elapsed());
 
@@ -125,13 +135,9 @@ pub fn benchmark_target(
         .count();
     log::info!("{number_of_unstable_edges} #edges with unstable hit counts");
 
-    if let Some(mut pacap_recording_cmd) = pacap_recording_cmd {
-        // Give it some time to process all captured packages.
-        thread::sleep(Duration::from_secs(2));
-        let child_pid = pacap_recording_cmd.id();
-        let child_pid = Pid::from_raw(child_pid.try_into().unwrap());
-        let _ = nix::sys::signal::kill(child_pid, SIGTERM);
-        log::info!("Waiting for pcap recorder to terminate");
-        pacap_recording_cmd.wait().unwrap();
+    if let Some(pcap_recording) = pcap_recording {
+        if let Err(err) = pcap_recording.finish() {
+            log::error!("Failed to record pcap: {err:#}");
+        }
     }
 }
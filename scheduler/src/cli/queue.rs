@@ -1,17 +1,53 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::ArgMatches;
 use fuzztruction_shared::mutation_cache::MutationCache;
 use scheduler::{
     config::Config,
     fuzzer::queue::{Queue, QueueEntryId},
+    transcript::{Sender, Transcript},
 };
 
+fn print_transcript(transcript: &Transcript) {
+    println!("transcript:");
+    for (idx, connection) in transcript.connections.iter().enumerate() {
+        println!(
+            "connection #{idx}: {} {} -> {}",
+            connection.protocol, connection.client, connection.server
+        );
+    }
+    for message in transcript.messages.iter() {
+        println!(
+            "[{:?}] #{} {} ({} bytes):",
+            message.timestamp,
+            message.connection,
+            message.sender,
+            message.data.len()
+        );
+        hexdump::hexdump(&message.data);
+    }
+}
+
 pub fn queue_cli(config: &Config, matches: &ArgMatches) -> Result<()> {
     let id_allowlist: Option<Vec<usize>> =
         matches.get_many("id").map(|coll| coll.copied().collect());
     let crashes_only = matches.get_flag("crashes-only");
+    let show_transcript = matches.get_flag("transcript");
+    let replay_script_dir: Option<&PathBuf> = matches.get_one("replay-script");
+    if let Some(dir) = replay_script_dir {
+        fs::create_dir_all(dir)?;
+    }
+    // The replay script takes the place of the source.
+    let replay_role = if config.source.is_server.unwrap_or(false) {
+        Sender::Server
+    } else {
+        Sender::Client
+    };
 
     let queue_path = config.general.queue_path();
     let queue = Queue::load(&queue_path, None)?;
@@ -58,6 +94,18 @@ pub fn queue_cli(config: &Config, matches: &ArgMatches) -> Result<()> {
         patchpoints.remove(&None);
         dbg!(patchpoints);
 
+        if let Some(transcript) = entry.transcript() {
+            if show_transcript {
+                print_transcript(transcript);
+            }
+            if let Some(dir) = replay_script_dir {
+                let path = dir.join(format!("id:{}.py", entry.id().0));
+                fs::write(&path, transcript.replay_script(replay_role))
+                    .context(format!("Failed to write replay script to {path:?}"))?;
+                println!("Replay script written to {path:?}");
+            }
+        }
+
         if let Some(mutations) = entry.mutations() {
             let mut mc = MutationCache::new().unwrap();
             mc.load_bytes(mutations).unwrap();
@@ -1,4 +1,5 @@
 use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::Path;
 use std::{collections::HashSet, path::PathBuf};
 
@@ -26,13 +27,14 @@ pub struct FromStrDuration(pub time::Duration);
 pub enum TransportType {
     TCP,
     UDP,
+    UNIX,
 }
 
 impl FromStr for FromStrDuration {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new("([0-9]+)(s|m|h|d|a)").unwrap();
+        let re = Regex::new("([0-9]+)(ms|s|m|h|d|a)").unwrap();
         let matches = re
             .captures(s)
             .ok_or(format!("Invalid duration format ({})!", s))?;
@@ -47,6 +49,7 @@ impl FromStr for FromStrDuration {
 
         let millis = match suffix {
             "" => amount,
+            "ms" => amount,
             "s" => amount * 1000,
             "m" => amount * 1000 * 60,
             "h" => amount * 1000 * 3600,
@@ -77,14 +80,29 @@ pub struct SourceConfig {
     /// Whether this is a server application.
     pub is_server: Option<bool>,
     pub server_port: Option<String>,
+    /// Address (IPv4 or IPv6 and port) the server is reachable at, e.g., `[::1]:8080`.
+    pub server_address: Option<SocketAddr>,
+    /// Path of the AF_UNIX socket the server binds to (only used by the Unix channel).
+    pub server_socket_path: Option<PathBuf>,
     pub server_ready_on: Option<ServerReadySignalKind>,
     /// List of PatchPointIDs that are allowed to be mutated.
     pub allowed_patch_points: Option<Vec<MutationSiteID>>,
     pub max_patch_points: Option<i64>,
     pub blocked_patchpoint_instructions: Option<Vec<LLVMInstruction>>,
+    /// Names of the functions whose calls may be faulted, e.g., `malloc` or `write`.
+    /// If not set, all recorded call sites may be faulted.
+    pub fault_callees: Option<Vec<String>>,
     pub working_dir: Option<PathBuf>,
 }
 
+impl SourceConfig {
+    /// The address of the server. Falls back to `127.0.0.1:<server-port>` if
+    /// no `server-address` is configured.
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        resolve_server_addr(self.server_address, self.server_port.as_deref())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct PhasesConfig {
     /// Only fuzz entries from generation <= `generation_ceiling`
@@ -97,6 +115,8 @@ pub struct PhasesConfig {
     pub add: AddPhaseConfig,
     /// Parameters of the combine phase.
     pub combine: CombinePhaseConfig,
+    /// Parameters of the dictionary phase.
+    pub dictionary: DictionaryPhaseConfig,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -129,6 +149,11 @@ impl Default for DiscoveryPhaseConfig {
 pub struct MutatePhaseConfig {
     pub weight: u32,
     pub entry_cov_timeout: Duration,
+    /// Allocate the iterations of the mutators according to their yields via a
+    /// multi-armed bandit policy. If false, all mutators get the same iterations.
+    pub adaptive_mutators: bool,
+    /// Shared libraries implementing the [custom mutator interface](crate::custom_mutator).
+    pub custom_mutators: Vec<PathBuf>,
 }
 
 impl Default for MutatePhaseConfig {
@@ -136,6 +161,8 @@ impl Default for MutatePhaseConfig {
         Self {
             weight: 40,
             entry_cov_timeout: Duration::from_secs(60 * 15),
+            adaptive_mutators: true,
+            custom_mutators: Vec::new(),
         }
     }
 }
@@ -189,6 +216,25 @@ impl Default for CombinePhaseConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryPhaseConfig {
+    pub weight: u32,
+    pub entry_cov_timeout: Duration,
+    /// Path to an AFL-style dictionary whose tokens are used in addition to
+    /// the strings found in the source and sink binaries.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for DictionaryPhaseConfig {
+    fn default() -> Self {
+        Self {
+            weight: 5,
+            entry_cov_timeout: Duration::from_secs(60 * 10),
+            path: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SinkConfig {
     /// Path to the Sink binary.
@@ -210,10 +256,32 @@ pub struct SinkConfig {
     /// Whether this is a server application.
     pub is_server: Option<bool>,
     pub server_port: Option<String>,
+    /// Address (IPv4 or IPv6 and port) the server is reachable at, e.g., `[::1]:8080`.
+    pub server_address: Option<SocketAddr>,
+    /// Path of the AF_UNIX socket the server binds to (only used by the Unix channel).
+    pub server_socket_path: Option<PathBuf>,
     pub server_ready_on: Option<ServerReadySignalKind>,
     /// The working directory that should be used.
     pub working_dir: Option<PathBuf>,
     pub send_sigterm: bool,
+    /// Terminate the sink via SIGTERM once neither of the peers exchanged data nor
+    /// reported a socket event for this long. Only supported by networked targets.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl SinkConfig {
+    /// The address of the server. Falls back to `127.0.0.1:<server-port>` if
+    /// no `server-address` is configured.
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        resolve_server_addr(self.server_address, self.server_port.as_deref())
+    }
+}
+
+fn resolve_server_addr(address: Option<SocketAddr>, port: Option<&str>) -> Option<SocketAddr> {
+    address.or_else(|| {
+        port.and_then(|port| port.parse::<u16>().ok())
+            .map(|port| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -240,16 +308,123 @@ pub struct AflNetConfig {
 
 impl AflNetConfig {
     pub fn transport_type(&self) -> TransportType {
-        if self.netinfo.to_lowercase().starts_with("tcp") {
-            TransportType::TCP
-        } else if self.netinfo.to_lowercase().starts_with("udp") {
-            TransportType::UDP
-        } else {
-            panic!("Unknown transport protocol in netinfo: {}", self.netinfo);
+        parse_netinfo(&self.netinfo).0
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        parse_netinfo(&self.netinfo).1
+    }
+}
+
+/// Parse an AFLNet style netinfo string, e.g., `tcp://127.0.0.1/8554` or `udp://::1/53`.
+/// IPv6 addresses may optionally be enclosed in brackets.
+fn parse_netinfo(netinfo: &str) -> (TransportType, SocketAddr) {
+    let (protocol, remainder) = netinfo
+        .split_once("://")
+        .unwrap_or_else(|| panic!("Malformed netinfo: {}", netinfo));
+
+    let transport_type = match &protocol.to_lowercase()[..] {
+        "tcp" => TransportType::TCP,
+        "udp" => TransportType::UDP,
+        _ => panic!("Unknown transport protocol in netinfo: {}", netinfo),
+    };
+
+    let (ip, port) = remainder
+        .rsplit_once('/')
+        .unwrap_or_else(|| panic!("Missing port in netinfo: {}", netinfo));
+    let ip: IpAddr = ip
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid IP address in netinfo: {}", netinfo));
+    let port: u16 = port
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid port in netinfo: {}", netinfo));
+
+    (transport_type, SocketAddr::new(ip, port))
+}
+
+/// How the connections of a session are established.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub enum SessionMode {
+    /// Each connection must be established within the timeout after the previous one.
+    Sequential,
+    /// All connections must be established within a single timeout. The connections
+    /// are still counted in the order the client reports them, i.e., this does not
+    /// track connections that are open at the same time individually.
+    SharedTimeout,
+    /// Connections may be open at the same time. The session is established with
+    /// the first connection and ends once at least `connections` connections were
+    /// opened and the last open one was closed. Connections are tracked by their
+    /// fd as reported by the sink's socket hooks.
+    Concurrent,
+}
+
+/// Describes sessions that consist of more than one connection between the peers,
+/// e.g., FTP with a separate data channel or HTTP clients that reconnect.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionConfig {
+    /// Number of connections the client establishes during a single execution.
+    pub connections: usize,
+    pub mode: SessionMode,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            connections: 1,
+            mode: SessionMode::Sequential,
         }
     }
 }
 
+/// Describes setups that consist of more than two peers, e.g., a client, a middlebox,
+/// and a server.
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologyConfig {
+    /// The peers in the order they are spawned. Each peer is only spawned after
+    /// the previous one got ready, thus servers usually come first.
+    pub peers: Vec<TopologyPeerConfig>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopologyPeerConfig {
+    pub peer: TopologyPeer,
+    /// The event after which the peer is considered ready. If not set, the peer is
+    /// considered ready right after it was spawned.
+    pub ready_on: Option<ServerReadySignalKind>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum TopologyPeer {
+    /// The weird peer described by the source section.
+    Source,
+    /// The coverage instrumented peer described by the sink section.
+    Sink,
+    /// An uninstrumented process that is not fuzzed.
+    Helper(HelperConfig),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HelperConfig {
+    /// Path to the helper binary.
+    pub bin_path: PathBuf,
+    pub arguments: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub working_dir: Option<PathBuf>,
+    pub server_port: Option<String>,
+    /// Address (IPv4 or IPv6 and port) the helper is listening on.
+    pub server_address: Option<SocketAddr>,
+}
+
+impl HelperConfig {
+    /// The address the helper is listening on. Falls back to `127.0.0.1:<server-port>`
+    /// if no `server-address` is configured.
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        resolve_server_addr(self.server_address, self.server_port.as_deref())
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SGFuzzConfig {
     pub bin_path: PathBuf,
@@ -263,18 +438,15 @@ pub struct SGFuzzConfig {
 
 impl SGFuzzConfig {
     pub fn dst_port(&self) -> u16 {
-        let netinfo = self.netinfo.split('/');
-        netinfo.last().unwrap().parse().unwrap()
+        self.server_addr().port()
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        parse_netinfo(&self.netinfo).1
     }
 
     pub fn transport_type(&self) -> TransportType {
-        if self.netinfo.to_lowercase().starts_with("tcp") {
-            TransportType::TCP
-        } else if self.netinfo.to_lowercase().starts_with("udp") {
-            TransportType::UDP
-        } else {
-            panic!("Unknown transport protocol in netinfo: {}", self.netinfo);
-        }
+        parse_netinfo(&self.netinfo).0
     }
 }
 
@@ -306,6 +478,9 @@ pub struct GeneralConfig {
     pub jail_uid: Option<u32>,
     pub jail_gid: Option<u32>,
     pub jail_drop_to_sudo_callee: bool,
+    /// The cerebrum statistics of a previous campaign that are imported as prior
+    /// knowledge into a fresh campaign. This is set via the command line.
+    pub cerebrum_prior: Option<PathBuf>,
 }
 
 impl GeneralConfig {
@@ -381,6 +556,13 @@ impl GeneralConfig {
         ret
     }
 
+    /// The buckets the crashes found during fuzzing are sorted into.
+    pub fn crash_buckets_path(&self) -> PathBuf {
+        let mut ret = self.work_dir.clone();
+        ret.push("crashes");
+        ret
+    }
+
     pub fn pcap_path(&self) -> PathBuf {
         let mut ret = self.work_dir.clone();
         ret.push("pcaps");
@@ -399,6 +581,27 @@ impl GeneralConfig {
         ret
     }
 
+    /// The stats of the queue entries that are required to resume a campaign.
+    pub fn queue_stats_path(&self) -> PathBuf {
+        let mut ret = self.work_dir.clone();
+        ret.push("queue-stats.json");
+        ret
+    }
+
+    /// The cerebrum statistics that are required to resume a campaign.
+    pub fn cerebrum_stats_path(&self) -> PathBuf {
+        let mut ret = self.work_dir.clone();
+        ret.push("cerebrum-stats.json");
+        ret
+    }
+
+    /// The minimized crashes and the reports of their minimization.
+    pub fn minimized_path(&self) -> PathBuf {
+        let mut ret = self.work_dir.clone();
+        ret.push("minimized");
+        ret
+    }
+
     pub fn patch_points_path(&self) -> PathBuf {
         let mut ret = self.work_dir.clone();
         ret.push("0/source/state/patch_points.json");
@@ -444,16 +647,32 @@ pub struct Config {
     pub stateafl: Option<StateAflConfig>,
     /// Config for the SGFuzz fuzzer.
     pub sgfuzz: Option<SGFuzzConfig>,
+    /// Session model of networked targets. If not set, a session consists of
+    /// exactly one connection and ends when the consumer terminates.
+    pub session: Option<SessionConfig>,
+    /// Peers of networked targets that consist of more than a client and a server.
+    pub topology: Option<TopologyConfig>,
 }
 
 impl Config {
+    /// Whether the sink is terminated once its session ended instead of waiting for it
+    /// to terminate on its own. See [crate::networked::wait_for_consumer_termination].
+    pub fn detects_session_end(&self) -> bool {
+        self.session.is_some() || self.sink.idle_timeout.is_some()
+    }
+
     pub fn target_uses_network(&self) -> bool {
         matches!(
             self.source.input_type,
-            InputChannel::Tcp | InputChannel::Udp
+            InputChannel::Tcp | InputChannel::Udp | InputChannel::Unix
         )
     }
 
+    /// Whether the peers communicate via an AF_UNIX socket instead of an IP socket.
+    pub fn target_uses_unix_socket(&self) -> bool {
+        self.source.input_type == InputChannel::Unix
+    }
+
     pub fn server_port(&self) -> Option<String> {
         if self.source.is_server.unwrap_or(false) {
             self.source.server_port.clone()
@@ -463,6 +682,27 @@ impl Config {
             unreachable!()
         }
     }
+
+    /// The address the server peer is reachable at.
+    pub fn server_addr(&self) -> Option<SocketAddr> {
+        if self.source.is_server.unwrap_or(false) {
+            self.source.server_addr()
+        } else if self.sink.is_server.unwrap_or(false) {
+            self.sink.server_addr()
+        } else {
+            unreachable!()
+        }
+    }
+
+    pub fn server_socket_path(&self) -> Option<PathBuf> {
+        if self.source.is_server.unwrap_or(false) {
+            self.source.server_socket_path.clone()
+        } else if self.sink.is_server.unwrap_or(false) {
+            self.sink.server_socket_path.clone()
+        } else {
+            unreachable!()
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -575,6 +815,25 @@ impl Validator for Config {
         self.vanilla
             .validate()
             .context("Failed to validate VanillaConfig")?;
+        if self.sink.idle_timeout.is_some() && !self.target_uses_network() {
+            return Err(anyhow!("idle-timeout requires a networked target"));
+        }
+        if let Some(session) = self.session.as_ref() {
+            if !self.target_uses_network() {
+                return Err(anyhow!("The session section requires a networked target"));
+            }
+            session
+                .validate()
+                .context("Failed to validate SessionConfig")?;
+        }
+        if let Some(topology) = self.topology.as_ref() {
+            if !self.target_uses_network() {
+                return Err(anyhow!("The topology section requires a networked target"));
+            }
+            topology
+                .validate()
+                .context("Failed to validate TopologyConfig")?;
+        }
         Ok(())
     }
 }
@@ -594,7 +853,14 @@ impl Validator for SourceConfig {
     fn validate(&self) -> Result<()> {
         self.bin_path
             .path_exists()
-            .context("Failed to validate bin_path")
+            .context("Failed to validate bin_path")?;
+        validate_server_address(self.server_address, self.server_port.as_deref())?;
+        validate_server_ready_on(self.is_server, self.server_ready_on)?;
+        validate_server_socket_path(
+            self.is_server,
+            self.input_type,
+            self.server_socket_path.as_ref(),
+        )
     }
 }
 
@@ -602,7 +868,127 @@ impl Validator for SinkConfig {
     fn validate(&self) -> Result<()> {
         self.bin_path
             .path_exists()
-            .context("Failed to validate bin_path")
+            .context("Failed to validate bin_path")?;
+        validate_server_address(self.server_address, self.server_port.as_deref())?;
+        validate_server_ready_on(self.is_server, self.server_ready_on)?;
+        validate_server_socket_path(
+            self.is_server,
+            self.input_type,
+            self.server_socket_path.as_ref(),
+        )?;
+        if self.idle_timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(anyhow!("idle-timeout must be greater than zero"));
+        }
+        Ok(())
+    }
+}
+
+/// If both, `server-address` and `server-port`, are given, they must agree on the port.
+fn validate_server_address(address: Option<SocketAddr>, port: Option<&str>) -> Result<()> {
+    if let (Some(address), Some(port)) = (address, port) {
+        if port.parse::<u16>().ok() != Some(address.port()) {
+            return Err(anyhow!(
+                "server-port {} does not match the port of server-address {}",
+                port,
+                address
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Connect(n) describes a client event and can not be used to detect server readiness.
+fn validate_server_ready_on(
+    is_server: Option<bool>,
+    server_ready_on: Option<ServerReadySignalKind>,
+) -> Result<()> {
+    if is_server.unwrap_or(false)
+        && matches!(server_ready_on, Some(ServerReadySignalKind::Connect(_)))
+    {
+        return Err(anyhow!(
+            "server-ready-on: Connect can only be used for client peers"
+        ));
+    }
+    Ok(())
+}
+
+/// Servers that are reached via the Unix channel need to know which socket path to use.
+fn validate_server_socket_path(
+    is_server: Option<bool>,
+    input_type: InputChannel,
+    server_socket_path: Option<&PathBuf>,
+) -> Result<()> {
+    if is_server.unwrap_or(false)
+        && input_type == InputChannel::Unix
+        && server_socket_path.is_none()
+    {
+        return Err(anyhow!(
+            "server-socket-path must be set for servers using the Unix input channel"
+        ));
+    }
+    Ok(())
+}
+
+impl Validator for SessionConfig {
+    fn validate(&self) -> Result<()> {
+        if self.connections == 0 {
+            return Err(anyhow!("A session must consist of at least one connection"));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for TopologyConfig {
+    fn validate(&self) -> Result<()> {
+        let sources = self
+            .peers
+            .iter()
+            .filter(|p| matches!(p.peer, TopologyPeer::Source))
+            .count();
+        let sinks = self
+            .peers
+            .iter()
+            .filter(|p| matches!(p.peer, TopologyPeer::Sink))
+            .count();
+        if sources != 1 || sinks != 1 {
+            return Err(anyhow!(
+                "The topology must contain exactly one source and one sink peer"
+            ));
+        }
+
+        for peer in self.peers.iter() {
+            let helper = match &peer.peer {
+                TopologyPeer::Helper(helper) => helper,
+                _ => continue,
+            };
+            helper.validate()?;
+            match peer.ready_on {
+                None => (),
+                Some(ServerReadySignalKind::Bind(0) | ServerReadySignalKind::Listen(0)) => {
+                    if helper.server_addr().is_none() {
+                        return Err(anyhow!(
+                            "A helper with a ready-on event needs a server-port or server-address"
+                        ));
+                    }
+                }
+                Some(ready_on) => {
+                    return Err(anyhow!(
+                        "Helpers are not instrumented and can not be ready on {:?}",
+                        ready_on
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validator for HelperConfig {
+    fn validate(&self) -> Result<()> {
+        self.bin_path
+            .path_exists()
+            .context("Failed to validate bin_path")?;
+        validate_server_address(self.server_address, self.server_port.as_deref())
     }
 }
 
@@ -797,6 +1183,7 @@ impl TryFromYaml for InputChannel {
             "file" => Ok(Box::new(InputChannel::File)),
             "tcp" => Ok(Box::new(InputChannel::Tcp)),
             "udp" => Ok(Box::new(InputChannel::Udp)),
+            "unix" => Ok(Box::new(InputChannel::Unix)),
             _ => Err(ConfigError::InvalidValue(ret)),
         }
         .context("Must be one of None, Stdin, File or Network".to_owned())?;
@@ -810,27 +1197,41 @@ impl TryFromYaml for ServerReadySignalKind {
         let ret = String::try_from_yaml(_builder, yaml)?;
         let ret = ret.to_lowercase();
 
-        let r = Regex::new(r"(bind|listen)(\(([1-9]+[0-9]*)\))?").unwrap();
-        let matches = r.captures(&ret).unwrap();
+        let r = Regex::new(r"(bind|listen|accept|connect)(\(([1-9]+[0-9]*)\))?").unwrap();
+        let matches = r
+            .captures(&ret)
+            .ok_or_else(|| ConfigError::InvalidValue(ret.clone()))
+            .context("Must be one of Bind, Listen, Accept or Connect".to_owned())?;
 
-        if matches.get(3).is_none() {
-            let ret = match matches.get(1).unwrap().as_str() {
-                "bind" => Ok(Box::new(ServerReadySignalKind::Bind(0))),
-                "listen" => Ok(Box::new(ServerReadySignalKind::Listen(0))),
-                _ => Err(ConfigError::InvalidValue(ret)),
-            }
-            .context("Must be one of Bind or Listen".to_owned())?;
-            Ok(ret)
-        } else {
-            let ctr = matches.get(3).unwrap().as_str().parse().unwrap();
-            let ret = match matches.get(1).unwrap().as_str() {
-                "bind" => Ok(Box::new(ServerReadySignalKind::Bind(ctr))),
-                "listen" => Ok(Box::new(ServerReadySignalKind::Listen(ctr))),
-                _ => Err(ConfigError::InvalidValue(ret)),
-            }
-            .context("Must be one of Bind or Listen".to_owned())?;
-            Ok(ret)
+        let ctr = match matches.get(3) {
+            Some(ctr) => ctr.as_str().parse().unwrap(),
+            None => 0,
+        };
+        let ret = match matches.get(1).unwrap().as_str() {
+            "bind" => Ok(Box::new(ServerReadySignalKind::Bind(ctr))),
+            "listen" => Ok(Box::new(ServerReadySignalKind::Listen(ctr))),
+            "accept" => Ok(Box::new(ServerReadySignalKind::Accept(ctr))),
+            "connect" => Ok(Box::new(ServerReadySignalKind::Connect(ctr))),
+            _ => Err(ConfigError::InvalidValue(ret)),
+        }
+        .context("Must be one of Bind, Listen, Accept or Connect".to_owned())?;
+        Ok(ret)
+    }
+}
+
+/// Try to convert a yaml string attribute value to a SessionMode enum variant.
+impl TryFromYaml for SessionMode {
+    fn try_from_yaml(_builder: &ConfigBuilder, yaml: &Yaml) -> Result<Box<Self>> {
+        let ret = String::try_from_yaml(_builder, yaml)?;
+        let ret = ret.to_lowercase();
+        let ret = match &ret[..] {
+            "sequential" => Ok(Box::new(SessionMode::Sequential)),
+            "shared-timeout" => Ok(Box::new(SessionMode::SharedTimeout)),
+            "concurrent" => Ok(Box::new(SessionMode::Concurrent)),
+            _ => Err(ConfigError::InvalidValue(ret)),
         }
+        .context("Must be one of Sequential, Shared-Timeout or Concurrent".to_owned())?;
+        Ok(ret)
     }
 }
 
@@ -845,6 +1246,7 @@ impl TryFromYaml for OutputChannel {
             "file" => Ok(Box::new(OutputChannel::File)),
             "tcp" => Ok(Box::new(OutputChannel::Tcp)),
             "udp" => Ok(Box::new(OutputChannel::Udp)),
+            "unix" => Ok(Box::new(OutputChannel::Unix)),
             _ => Err(ConfigError::InvalidValue(ret)),
         }
         .context("Must be one of None, Stdout, File or Network".to_owned())?;
@@ -852,6 +1254,17 @@ impl TryFromYaml for OutputChannel {
     }
 }
 
+/// Try to convert a yaml string attribute value (e.g., `127.0.0.1:8080` or `[::1]:8080`) into a SocketAddr.
+impl TryFromYaml for SocketAddr {
+    fn try_from_yaml(_builder: &ConfigBuilder, yaml: &Yaml) -> Result<Box<Self>> {
+        let ret = String::try_from_yaml(_builder, yaml)?;
+        let addr = SocketAddr::from_str(&ret).map_err(|err| {
+            ConfigError::InvalidValue(format!("Invalid socket address {}. e={}", ret, err))
+        })?;
+        Ok(Box::new(addr))
+    }
+}
+
 impl TryFromYaml for Vec<(String, String)> {
     fn try_from_yaml(_builder: &ConfigBuilder, yaml: &Yaml) -> Result<Box<Vec<(String, String)>>> {
         let ret =
@@ -1032,6 +1445,8 @@ impl ConfigBuilder {
                 "sgfuzz",
                 "vanilla",
                 "phases",
+                "session",
+                "topology",
             ],
         )?;
 
@@ -1042,6 +1457,7 @@ impl ConfigBuilder {
             jail_uid,
             jail_gid,
             jail_drop_to_sudo_callee: jail_drop_to_sudo_callee.unwrap_or(true),
+            cerebrum_prior: None,
         })
     }
 
@@ -1057,11 +1473,14 @@ impl ConfigBuilder {
         let log_stderr = self.get_attribute(yaml, "log-stderr")?;
         let is_server: Option<bool> = self.get_attribute(yaml, "is-server")?;
         let server_port: Option<String> = self.get_attribute(yaml, "server-port")?;
+        let server_address: Option<SocketAddr> = self.get_attribute(yaml, "server-address")?;
+        let server_socket_path: Option<PathBuf> = self.get_attribute(yaml, "server-socket-path")?;
         let server_ready_on: Option<_> = self.get_attribute(yaml, "server-ready-on")?;
         let allowed_patch_points = self.get_attribute(yaml, "allowed-patch-points")?;
         let max_patch_points = self.get_attribute(yaml, "max-patch-points")?;
         let blocked_patchpoint_instructions =
             self.get_attribute(yaml, "blocked-patch-point-instructions")?;
+        let fault_callees = self.get_attribute(yaml, "fault-callees")?;
         let working_dir = self.get_attribute(yaml, "working-dir")?;
 
         ConfigBuilder::check_for_unparsed_keys(
@@ -1077,10 +1496,13 @@ impl ConfigBuilder {
                 "log-stderr",
                 "is-server",
                 "server-port",
+                "server-address",
+                "server-socket-path",
                 "server-ready-on",
                 "allowed-patch-points",
                 "max-patch-points",
                 "blocked-patch-point-instructions",
+                "fault-callees",
                 "working-dir",
             ],
         )?;
@@ -1096,10 +1518,13 @@ impl ConfigBuilder {
             log_stderr,
             is_server,
             server_port,
+            server_address,
+            server_socket_path,
             server_ready_on,
             allowed_patch_points,
             max_patch_points,
             blocked_patchpoint_instructions,
+            fault_callees,
             working_dir,
         })
     }
@@ -1141,12 +1566,28 @@ impl ConfigBuilder {
         let entry_cov_timeout = self
             .get_attribute::<Option<Duration>>(section, "entry-cov-timeout")?
             .unwrap_or_else(|| Duration::from_secs(60 * 10));
+        let adaptive_mutators = self
+            .get_attribute::<Option<bool>>(section, "adaptive-mutators")?
+            .unwrap_or(true);
+        let custom_mutators = self
+            .get_attribute::<Option<Vec<PathBuf>>>(section, "custom-mutators")?
+            .unwrap_or_default();
 
-        ConfigBuilder::check_for_unparsed_keys(section, &["weight", "entry-cov-timeout"])?;
+        ConfigBuilder::check_for_unparsed_keys(
+            section,
+            &[
+                "weight",
+                "entry-cov-timeout",
+                "adaptive-mutators",
+                "custom-mutators",
+            ],
+        )?;
 
         Ok(MutatePhaseConfig {
             weight,
             entry_cov_timeout,
+            adaptive_mutators,
+            custom_mutators,
         })
     }
 
@@ -1197,6 +1638,22 @@ impl ConfigBuilder {
         })
     }
 
+    fn parse_dictionary_phase_section(&self, section: &Yaml) -> Result<DictionaryPhaseConfig> {
+        let weight: u32 = self.get_attribute(section, "weight")?;
+        let entry_cov_timeout = self
+            .get_attribute::<Option<Duration>>(section, "entry-cov-timeout")?
+            .unwrap_or_else(|| Duration::from_secs(60 * 10));
+        let path = self.get_attribute(section, "path")?;
+
+        ConfigBuilder::check_for_unparsed_keys(section, &["weight", "entry-cov-timeout", "path"])?;
+
+        Ok(DictionaryPhaseConfig {
+            weight,
+            entry_cov_timeout,
+            path,
+        })
+    }
+
     fn parse_phases_section(&self, phases_section: &Yaml) -> Result<PhasesConfig> {
         let generation_ceiling: Option<u32> =
             self.get_attribute(phases_section, "generation-ceiling")?;
@@ -1232,6 +1689,14 @@ impl ConfigBuilder {
                 CombinePhaseConfig::default()
             };
 
+        let dictionary_config =
+            if let Some(section) = self.get_optional_section(phases_section, "dictionary")? {
+                self.parse_dictionary_phase_section(&section)
+                    .context("Failed to parse dictionary section")?
+            } else {
+                DictionaryPhaseConfig::default()
+            };
+
         ConfigBuilder::check_for_unparsed_keys(
             phases_section,
             &[
@@ -1240,6 +1705,7 @@ impl ConfigBuilder {
                 "mutate",
                 "add",
                 "combine",
+                "dictionary",
             ],
         )?;
 
@@ -1249,6 +1715,7 @@ impl ConfigBuilder {
             mutate: mutate_config,
             add: add_config,
             combine: combine_config,
+            dictionary: dictionary_config,
         })
     }
 
@@ -1363,11 +1830,14 @@ impl ConfigBuilder {
         let allow_unstable_sink = allow_unstable_sink.unwrap_or(true);
         let is_server: Option<bool> = self.get_attribute(yaml, "is-server")?;
         let server_port: Option<String> = self.get_attribute(yaml, "server-port")?;
+        let server_address: Option<SocketAddr> = self.get_attribute(yaml, "server-address")?;
+        let server_socket_path: Option<PathBuf> = self.get_attribute(yaml, "server-socket-path")?;
         let server_ready_on: Option<_> = self.get_attribute(yaml, "server-ready-on")?;
         let working_dir = self.get_attribute(yaml, "working-dir")?;
         let send_sigterm = self
             .get_attribute::<Option<bool>>(yaml, "send-sigterm")?
             .unwrap_or(false);
+        let idle_timeout = self.get_attribute(yaml, "idle-timeout")?;
 
         ConfigBuilder::check_for_unparsed_keys(
             yaml,
@@ -1383,9 +1853,12 @@ impl ConfigBuilder {
                 "allow-unstable-sink",
                 "is-server",
                 "server-port",
+                "server-address",
+                "server-socket-path",
                 "server-ready-on",
                 "working-dir",
                 "send-sigterm",
+                "idle-timeout",
             ],
         )
         .context("Sink")?;
@@ -1401,9 +1874,12 @@ impl ConfigBuilder {
             allow_unstable_sink,
             is_server,
             server_port,
+            server_address,
+            server_socket_path,
             server_ready_on,
             working_dir,
             send_sigterm,
+            idle_timeout,
         })
     }
 
@@ -1422,6 +1898,94 @@ impl ConfigBuilder {
         })
     }
 
+    fn parse_session_section(&self, yaml: &Yaml) -> Result<SessionConfig> {
+        let default = SessionConfig::default();
+        let connections = self
+            .get_attribute::<Option<usize>>(yaml, "connections")?
+            .unwrap_or(default.connections);
+        let mode = self
+            .get_attribute::<Option<SessionMode>>(yaml, "mode")?
+            .unwrap_or(default.mode);
+
+        ConfigBuilder::check_for_unparsed_keys(yaml, &["connections", "mode"])?;
+
+        Ok(SessionConfig { connections, mode })
+    }
+
+    fn parse_topology_section(&self, yaml: &Yaml) -> Result<TopologyConfig> {
+        let peers = yaml.as_vec().ok_or_else(|| ConfigError::ConvertionFailed {
+            value: format!("{:#?}", yaml),
+            expected_type: "Array".to_owned(),
+        })?;
+
+        let mut ret = Vec::new();
+        for (idx, peer) in peers.iter().enumerate() {
+            let peer = self
+                .parse_topology_peer(peer)
+                .context(format!("Failed to parse peer {}", idx))?;
+            ret.push(peer);
+        }
+
+        Ok(TopologyConfig { peers: ret })
+    }
+
+    fn parse_topology_peer(&self, yaml: &Yaml) -> Result<TopologyPeerConfig> {
+        let kind: String = self.get_attribute(yaml, "peer")?;
+        let ready_on = self.get_attribute(yaml, "ready-on")?;
+
+        let peer = match &kind.to_lowercase()[..] {
+            "source" => {
+                ConfigBuilder::check_for_unparsed_keys(yaml, &["peer", "ready-on"])?;
+                TopologyPeer::Source
+            }
+            "sink" => {
+                ConfigBuilder::check_for_unparsed_keys(yaml, &["peer", "ready-on"])?;
+                TopologyPeer::Sink
+            }
+            "helper" => TopologyPeer::Helper(self.parse_helper_peer(yaml)?),
+            _ => {
+                return Err(ConfigError::InvalidValue(kind))
+                    .context("Must be one of Source, Sink or Helper")
+            }
+        };
+
+        Ok(TopologyPeerConfig { peer, ready_on })
+    }
+
+    fn parse_helper_peer(&self, yaml: &Yaml) -> Result<HelperConfig> {
+        let env: Option<Vec<_>> = self.get_attribute(yaml, "env")?;
+        let env = env.unwrap_or_default();
+        let bin_path = self.get_attribute(yaml, "bin-path")?;
+        let arguments: Option<Vec<String>> = self.get_attribute(yaml, "arguments")?;
+        let arguments = arguments.unwrap_or_default();
+        let working_dir = self.get_attribute(yaml, "working-dir")?;
+        let server_port: Option<String> = self.get_attribute(yaml, "server-port")?;
+        let server_address: Option<SocketAddr> = self.get_attribute(yaml, "server-address")?;
+
+        ConfigBuilder::check_for_unparsed_keys(
+            yaml,
+            &[
+                "peer",
+                "ready-on",
+                "env",
+                "bin-path",
+                "arguments",
+                "working-dir",
+                "server-port",
+                "server-address",
+            ],
+        )?;
+
+        Ok(HelperConfig {
+            bin_path,
+            arguments,
+            env,
+            working_dir,
+            server_port,
+            server_address,
+        })
+    }
+
     fn parse_vanilla_section(&self, yaml: &Yaml, arguments: &[String]) -> Result<VanillaConfig> {
         let env: Option<Vec<_>> = self.get_attribute(yaml, "env")?;
         let env = env.unwrap_or_default();
@@ -1491,6 +2055,26 @@ impl ConfigBuilder {
             Some(self.parse_sgfuzz_section(sgfuzz_section)?)
         };
 
+        let session_section = &yaml["session"];
+        let session_config = if session_section.is_badvalue() {
+            None
+        } else {
+            Some(
+                self.parse_session_section(session_section)
+                    .context("Failed to parse session section")?,
+            )
+        };
+
+        let topology_section = &yaml["topology"];
+        let topology_config = if topology_section.is_badvalue() {
+            None
+        } else {
+            Some(
+                self.parse_topology_section(topology_section)
+                    .context("Failed to parse topology section")?,
+            )
+        };
+
         let vanilla_section = &yaml["vanilla"];
         if vanilla_section.is_badvalue() {
             return Err(ConfigError::MissingSection("vanilla".to_owned()).into());
@@ -1508,6 +2092,8 @@ impl ConfigBuilder {
             sgfuzz: sgfuzz_section,
             sink_cov: sink_cov_config,
             vanilla: vanilla_config,
+            session: session_config,
+            topology: topology_config,
         };
         config.validate()?;
         Ok(config)
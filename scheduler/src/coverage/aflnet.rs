@@ -9,7 +9,10 @@ use std::{
 };
 
 use crate::{
-    config::Config, coverage::write_cov_binary_info, networked::WaitForPeerResult, sink::AflSink,
+    config::{Config, TransportType},
+    coverage::write_cov_binary_info,
+    networked::WaitForPeerResult,
+    sink::AflSink,
 };
 use anyhow::Result;
 use glob::glob;
@@ -71,7 +74,11 @@ pub(super) fn compute_llvm_cov_afl_like_inputs(
 
     write_cov_binary_info(config, &llvm_cov_path);
 
-    let transport_type = config.aflnet.as_ref().unwrap().transport_type();
+    let transport_type = if config.target_uses_unix_socket() {
+        TransportType::UNIX
+    } else {
+        config.aflnet.as_ref().unwrap().transport_type()
+    };
     log::info!("transport_type: {transport_type:?}");
 
     let mut all_input_files = collect_input_file_paths(input_dirs);
@@ -167,6 +174,15 @@ pub(super) fn compute_llvm_cov_afl_like_inputs(
                         continue;
                     }
                 }
+                crate::config::TransportType::UNIX => {
+                    let packages = content.iter().map(|e| e.as_slice()).collect::<Vec<_>>();
+                    if let Err(err) = sink.write_unix(packages.as_slice()) {
+                        log::warn!("Error while writing input to unix socket: {:?}", err);
+                        // Kill the child and collect it.
+                        wait_for_child(&mut sink, timeout, true, None);
+                        continue;
+                    }
+                }
             }
 
             wait_for_child(&mut sink, timeout, false, Some(timeout / 2));
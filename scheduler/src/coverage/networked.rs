@@ -13,12 +13,15 @@ use crate::{
     config::Config,
     coverage::write_cov_binary_info,
     fuzzer::queue::{Queue, QueueEntry},
-    networked::{get_consumer, get_producer, Client, Server, WaitForPeerResult},
+    networked::{
+        get_consumer_and_producer, get_producer, wait_for_consumer_termination, Client, Server,
+        WaitForPeerResult,
+    },
     sink::AflSink,
     source::Source,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use fuzztruction_shared::mutation_cache::MutationCache;
 use itertools::Itertools;
 
@@ -46,6 +49,12 @@ pub fn compute_llvm_cov(
     timeout: Duration,
     overwrite_results: bool,
 ) -> Result<()> {
+    if config.topology.is_some() {
+        return Err(anyhow!(
+            "Computing coverage is not supported for targets with a topology configured"
+        ));
+    }
+
     let llvm_traces_folder = config.general.llvm_cov_directory();
     if llvm_traces_folder.exists() {
         if overwrite_results {
@@ -108,7 +117,7 @@ pub fn compute_llvm_cov(
         );
 
         client.spawn(timeout).unwrap();
-        let ret = client.wait_until_connect(timeout)?;
+        let ret = client.wait_until_session_established(config, timeout)?;
         match ret {
             WaitForPeerResult::Terminated(_)
             | WaitForPeerResult::Signalled(_)
@@ -120,9 +129,8 @@ pub fn compute_llvm_cov(
             WaitForPeerResult::Ready => (),
         }
 
-        let consumer = get_consumer(&mut client, &mut server);
-        let ret = consumer
-            .wait_for_child_termination(timeout, false, Some(timeout))
+        let (consumer, producer) = get_consumer_and_producer(&mut client, &mut server);
+        let ret = wait_for_consumer_termination(consumer, producer, config, timeout, Some(timeout))
             .unwrap();
         log::info!("consumer result: {ret:?}");
 
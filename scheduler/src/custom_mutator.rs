@@ -0,0 +1,396 @@
+//! Plugin interface for mutators that are not part of the scheduler. Plugins are
+//! shared libraries listed in the `custom-mutators` attribute of the mutate phase.
+//! Each plugin exports a function named [CUSTOM_MUTATOR_DESCRIPTOR_SYMBOL] that
+//! returns a [CustomMutatorDescriptor], see `scheduler/include/ft_custom_mutator.h`
+//! for the C declaration of the interface.
+//!
+//! For each mutation site that is fuzzed, a plugin is informed about the site,
+//! the queue entry, and the current mask via `begin`. Afterwards, `next` is called
+//! until it returns false, where each call must write a new mask. The mask passed
+//! to `next` always contains the mask that was passed to `begin`.
+//!
+//! Plugins written in Rust implement [CustomMutator] and generate the C interface
+//! via [export_custom_mutator](crate::export_custom_mutator).
+
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    fmt,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    slice,
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::fuzzer::queue::QueueEntry;
+
+/// Version of the plugin interface. Plugins using a different version are rejected.
+pub const CUSTOM_MUTATOR_API_VERSION: u32 = 1;
+/// The symbol of type [CustomMutatorDescriptorFn] every plugin must export.
+pub const CUSTOM_MUTATOR_DESCRIPTOR_SYMBOL: &str = "ft_custom_mutator_descriptor";
+
+/// Metadata of the mutation site that is mutated.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct SiteInfo {
+    /// The id of the mutation site.
+    pub site_id: u64,
+    /// The LLVM opcode of the instruction the site belongs to.
+    pub llvm_instruction: u32,
+    /// The width of the mutated value.
+    pub value_size_bits: u32,
+    /// Size of the chunks the mask is consumed in. Each hit consumes one chunk.
+    pub chunk_size_bits: u32,
+    /// Number of times the site was executed while tracing the queue entry, or
+    /// zero if unknown.
+    pub hits: u64,
+}
+
+/// The queue entry whose mutations are mutated.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct EntryInfo {
+    /// The id of the queue entry.
+    pub entry_id: u64,
+    /// The id of the entry's parent or `u64::MAX` if it has none.
+    pub parent_id: u64,
+    /// The number of ancestors of the entry.
+    pub generation: u64,
+    /// The input passed to the source. Only valid during the call it is passed to.
+    pub input: *const u8,
+    pub input_len: usize,
+}
+
+impl EntryInfo {
+    fn new(entry: &QueueEntry, input: &[u8]) -> EntryInfo {
+        EntryInfo {
+            entry_id: entry.id().0,
+            parent_id: entry.parent_id().map(|id| id.0).unwrap_or(u64::MAX),
+            generation: entry.generation() as u64,
+            input: input.as_ptr(),
+            input_len: input.len(),
+        }
+    }
+
+    /// The input passed to the source.
+    pub fn input(&self) -> &[u8] {
+        // Safety: `input` is valid while a reference to `self` is passed to a plugin.
+        unsafe { slice::from_raw_parts(self.input, self.input_len) }
+    }
+}
+
+/// The interface a plugin exposes. All functions are required.
+#[derive(Debug)]
+#[repr(C)]
+pub struct CustomMutatorDescriptor {
+    /// Must be [CUSTOM_MUTATOR_API_VERSION].
+    pub api_version: u32,
+    /// NUL terminated name of the mutator.
+    pub name: *const c_char,
+    /// Create a new instance of the mutator and return its state. Each worker
+    /// creates its own instance.
+    pub init: Option<unsafe extern "C" fn(seed: u64) -> *mut c_void>,
+    /// Destroy an instance created by `init`.
+    pub deinit: Option<unsafe extern "C" fn(state: *mut c_void)>,
+    /// Start mutating the mask `msk` of a mutation site and return the number of
+    /// masks the mutator wants to produce.
+    pub begin: Option<
+        unsafe extern "C" fn(
+            state: *mut c_void,
+            site: *const SiteInfo,
+            entry: *const EntryInfo,
+            msk: *const u8,
+            msk_len: usize,
+        ) -> usize,
+    >,
+    /// Write the next mask into `msk` or return false, if there are none left.
+    pub next:
+        Option<unsafe extern "C" fn(state: *mut c_void, msk: *mut u8, msk_len: usize) -> bool>,
+}
+
+// Safety: The descriptor is immutable and `name` points to static memory.
+unsafe impl Sync for CustomMutatorDescriptor {}
+
+pub type CustomMutatorDescriptorFn = unsafe extern "C" fn() -> *const CustomMutatorDescriptor;
+
+/// The Rust interface of a plugin. See [export_custom_mutator](crate::export_custom_mutator).
+pub trait CustomMutator {
+    /// Create a new instance that uses `seed` to seed its randomness.
+    fn new(seed: u64) -> Self;
+
+    /// Start mutating `msk` of `site` and return the number of masks that are produced.
+    fn begin(&mut self, site: &SiteInfo, entry: &EntryInfo, msk: &[u8]) -> usize;
+
+    /// Write the next mask into `msk` or return false, if there are none left.
+    fn next(&mut self, msk: &mut [u8]) -> bool;
+}
+
+/// Export the [CustomMutator] `$mutator` as plugin named `$name`, i.e., define the
+/// [CUSTOM_MUTATOR_DESCRIPTOR_SYMBOL] function of the C interface. Must be used at
+/// most once per shared library.
+#[macro_export]
+macro_rules! export_custom_mutator {
+    ($mutator:ty, $name:literal) => {
+        #[no_mangle]
+        pub extern "C" fn ft_custom_mutator_descriptor(
+        ) -> *const $crate::custom_mutator::CustomMutatorDescriptor {
+            use ::std::ffi::{c_char, c_void};
+            use $crate::custom_mutator::{
+                CustomMutator, CustomMutatorDescriptor, EntryInfo, SiteInfo,
+                CUSTOM_MUTATOR_API_VERSION,
+            };
+
+            unsafe extern "C" fn init(seed: u64) -> *mut c_void {
+                Box::into_raw(Box::new(<$mutator as CustomMutator>::new(seed))) as *mut c_void
+            }
+
+            unsafe extern "C" fn deinit(state: *mut c_void) {
+                drop(Box::from_raw(state as *mut $mutator));
+            }
+
+            unsafe extern "C" fn begin(
+                state: *mut c_void,
+                site: *const SiteInfo,
+                entry: *const EntryInfo,
+                msk: *const u8,
+                msk_len: usize,
+            ) -> usize {
+                let mutator = &mut *(state as *mut $mutator);
+                let msk = ::std::slice::from_raw_parts(msk, msk_len);
+                CustomMutator::begin(mutator, &*site, &*entry, msk)
+            }
+
+            unsafe extern "C" fn next(state: *mut c_void, msk: *mut u8, msk_len: usize) -> bool {
+                let mutator = &mut *(state as *mut $mutator);
+                let msk = ::std::slice::from_raw_parts_mut(msk, msk_len);
+                CustomMutator::next(mutator, msk)
+            }
+
+            static DESCRIPTOR: CustomMutatorDescriptor = CustomMutatorDescriptor {
+                api_version: CUSTOM_MUTATOR_API_VERSION,
+                name: concat!($name, "\0").as_ptr() as *const c_char,
+                init: Some(init),
+                deinit: Some(deinit),
+                begin: Some(begin),
+                next: Some(next),
+            };
+            &DESCRIPTOR
+        }
+    };
+}
+
+/// An instance of a plugin.
+pub struct CustomMutatorPlugin {
+    /// The handle returned by dlopen or null if the descriptor is not owned by a library.
+    handle: *mut c_void,
+    descriptor: &'static CustomMutatorDescriptor,
+    name: String,
+    /// The state returned by the descriptor's `init`.
+    state: *mut c_void,
+}
+
+// Safety: The plugin's state is only accessed by the owner of the instance.
+unsafe impl Send for CustomMutatorPlugin {}
+
+impl fmt::Debug for CustomMutatorPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomMutatorPlugin")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CustomMutatorPlugin {
+    /// Load the plugin at `path` and create a new instance of it.
+    pub fn load(path: &Path, seed: u64) -> Result<CustomMutatorPlugin> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(anyhow!("Failed to load {:?}: {}", path, dlerror()));
+        }
+
+        let symbol = CString::new(CUSTOM_MUTATOR_DESCRIPTOR_SYMBOL).unwrap();
+        let descriptor_fn = unsafe { libc::dlsym(handle, symbol.as_ptr()) };
+        let ret = if descriptor_fn.is_null() {
+            Err(anyhow!(
+                "{:?} does not export {}: {}",
+                path,
+                CUSTOM_MUTATOR_DESCRIPTOR_SYMBOL,
+                dlerror()
+            ))
+        } else {
+            let descriptor_fn = unsafe {
+                std::mem::transmute::<*mut c_void, CustomMutatorDescriptorFn>(descriptor_fn)
+            };
+            unsafe { CustomMutatorPlugin::from_descriptor(descriptor_fn(), handle, seed) }
+        };
+
+        if ret.is_err() {
+            unsafe { libc::dlclose(handle) };
+        }
+        ret.with_context(|| format!("Failed to load custom mutator {:?}", path))
+    }
+
+    /// # Safety
+    /// `descriptor` must be valid for the lifetime of `handle` or for the whole
+    /// runtime, if `handle` is null.
+    unsafe fn from_descriptor(
+        descriptor: *const CustomMutatorDescriptor,
+        handle: *mut c_void,
+        seed: u64,
+    ) -> Result<CustomMutatorPlugin> {
+        let descriptor = descriptor
+            .as_ref()
+            .ok_or_else(|| anyhow!("The descriptor is NULL"))?;
+        if descriptor.api_version != CUSTOM_MUTATOR_API_VERSION {
+            return Err(anyhow!(
+                "Unsupported API version {}, expected {}",
+                descriptor.api_version,
+                CUSTOM_MUTATOR_API_VERSION
+            ));
+        }
+        if descriptor.name.is_null()
+            || descriptor.init.is_none()
+            || descriptor.deinit.is_none()
+            || descriptor.begin.is_none()
+            || descriptor.next.is_none()
+        {
+            return Err(anyhow!("The descriptor is incomplete: {:#?}", descriptor));
+        }
+
+        let name = CStr::from_ptr(descriptor.name)
+            .to_str()
+            .context("The name is not valid UTF-8")?
+            .to_owned();
+        if name.is_empty() {
+            return Err(anyhow!("The name is empty"));
+        }
+
+        let state = (descriptor.init.unwrap())(seed);
+        Ok(CustomMutatorPlugin {
+            handle,
+            descriptor,
+            name,
+            state,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// See [CustomMutator::begin].
+    pub fn begin(&mut self, site: &SiteInfo, entry: &QueueEntry, msk: &[u8]) -> usize {
+        let input = entry.input();
+        let entry = EntryInfo::new(entry, input.data());
+        unsafe {
+            (self.descriptor.begin.unwrap())(self.state, site, &entry, msk.as_ptr(), msk.len())
+        }
+    }
+
+    /// See [CustomMutator::next].
+    pub fn next(&mut self, msk: &mut [u8]) -> bool {
+        unsafe { (self.descriptor.next.unwrap())(self.state, msk.as_mut_ptr(), msk.len()) }
+    }
+}
+
+impl Drop for CustomMutatorPlugin {
+    fn drop(&mut self) {
+        unsafe {
+            (self.descriptor.deinit.unwrap())(self.state);
+            if !self.handle.is_null() {
+                libc::dlclose(self.handle);
+            }
+        }
+    }
+}
+
+fn dlerror() -> String {
+    let msg = unsafe { libc::dlerror() };
+    if msg.is_null() {
+        return "Unknown error".to_owned();
+    }
+    unsafe { CStr::from_ptr(msg) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+
+    use super::{CustomMutator, CustomMutatorPlugin, EntryInfo, SiteInfo};
+
+    /// Increments each byte of the mask once.
+    struct IncrementMutator {
+        msk: Vec<u8>,
+        idx: usize,
+    }
+
+    impl CustomMutator for IncrementMutator {
+        fn new(_seed: u64) -> Self {
+            IncrementMutator {
+                msk: Vec::new(),
+                idx: 0,
+            }
+        }
+
+        fn begin(&mut self, site: &SiteInfo, entry: &EntryInfo, msk: &[u8]) -> usize {
+            assert_eq!(site.site_id, 7);
+            assert_eq!(entry.input(), b"input");
+            self.msk = msk.to_vec();
+            self.idx = 0;
+            msk.len()
+        }
+
+        fn next(&mut self, msk: &mut [u8]) -> bool {
+            assert_eq!(msk, self.msk);
+            if self.idx == msk.len() {
+                return false;
+            }
+            msk[self.idx] = msk[self.idx].wrapping_add(1);
+            self.idx += 1;
+            true
+        }
+    }
+
+    crate::export_custom_mutator!(IncrementMutator, "increment");
+
+    #[test]
+    fn exported_mutator_is_callable() {
+        let mut plugin = unsafe {
+            CustomMutatorPlugin::from_descriptor(ft_custom_mutator_descriptor(), ptr::null_mut(), 0)
+        }
+        .unwrap();
+        assert_eq!(plugin.name(), "increment");
+
+        let site = SiteInfo {
+            site_id: 7,
+            llvm_instruction: 0,
+            value_size_bits: 8,
+            chunk_size_bits: 8,
+            hits: 2,
+        };
+        let input = b"input";
+        let entry = EntryInfo {
+            entry_id: 0,
+            parent_id: u64::MAX,
+            generation: 0,
+            input: input.as_ptr(),
+            input_len: input.len(),
+        };
+        let mut msk = vec![0u8, 1];
+        let steps = unsafe {
+            (plugin.descriptor.begin.unwrap())(plugin.state, &site, &entry, msk.as_ptr(), msk.len())
+        };
+        assert_eq!(steps, 2);
+
+        assert!(plugin.next(&mut msk));
+        assert_eq!(msk, vec![1, 1]);
+        msk[0] = 0;
+        assert!(plugin.next(&mut msk));
+        assert_eq!(msk, vec![0, 2]);
+        msk[1] = 1;
+        assert!(!plugin.next(&mut msk));
+    }
+}
@@ -0,0 +1,192 @@
+//! Tokens that are overlaid onto the masks of mutation sites by the
+//! [Dictionary](crate::fuzzer::worker_impl::mutators::Dictionary) mutator.
+//! Tokens are taken from an AFL-style dictionary file, from the string constants
+//! of the source and sink binaries, and from the sink's AFL++ autodictionary.
+//!
+//! The autodictionary is sent by the forkserver during the handshake
+//! (FS_OPT_AUTODICT) and consists of the constant comparison operands AFL++
+//! extracted while compiling the sink. Operands the sink compares at runtime
+//! are not observed, thus comparisons against values that are computed, e.g.,
+//! read from a config file, do not contribute tokens.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::config::Config;
+
+/// Tokens shorter than this are not extracted from binaries.
+const MIN_STRING_LEN: usize = 4;
+/// Longer tokens are truncated.
+pub const MAX_TOKEN_LEN: usize = 32;
+/// Maximum number of tokens extracted from a single binary.
+const MAX_STRINGS_PER_BINARY: usize = 4096;
+
+#[derive(Debug, Default, Clone)]
+pub struct Dictionary {
+    tokens: Vec<Vec<u8>>,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl Dictionary {
+    /// Create a dictionary from the sources configured in `config`. `sink_tokens`
+    /// are the tokens of the sink's autodictionary.
+    pub fn from_config(config: &Config, sink_tokens: &[Vec<u8>]) -> Result<Dictionary> {
+        let mut ret = Dictionary::default();
+
+        if let Some(path) = config.phases.dictionary.path.as_ref() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read dictionary {:?}", path))?;
+            let tokens = parse_afl_dictionary(&content)
+                .with_context(|| format!("Failed to parse dictionary {:?}", path))?;
+            ret.extend(tokens);
+        }
+
+        for bin_path in [&config.source.bin_path, &config.sink.bin_path] {
+            let strings = elf_strings(bin_path)
+                .with_context(|| format!("Failed to extract strings from {:?}", bin_path))?;
+            ret.extend(strings);
+        }
+        ret.extend(sink_tokens.iter().cloned());
+
+        log::info!("Dictionary contains {} tokens", ret.len());
+        Ok(ret)
+    }
+
+    /// Add `token` if it is not empty and not already part of the dictionary.
+    pub fn add(&mut self, mut token: Vec<u8>) {
+        token.truncate(MAX_TOKEN_LEN);
+        if !token.is_empty() && self.seen.insert(token.clone()) {
+            self.tokens.push(token);
+        }
+    }
+
+    pub fn extend(&mut self, tokens: impl IntoIterator<Item = Vec<u8>>) {
+        tokens.into_iter().for_each(|token| self.add(token));
+    }
+
+    pub fn tokens(&self) -> &[Vec<u8>] {
+        &self.tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
+
+/// Parse a dictionary in the format used by AFL, i.e., one token per line that
+/// is either `"value"` or `name="value"`. Empty lines and lines starting with
+/// `#` are ignored. Values may contain `\\`, `\"`, and `\xNN` escapes.
+pub fn parse_afl_dictionary(content: &str) -> Result<Vec<Vec<u8>>> {
+    let mut tokens = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let start = line
+            .find('"')
+            .filter(|_| line.len() > 1 && line.ends_with('"'));
+        let Some(start) = start else {
+            return Err(anyhow!("Line {}: Token is not quoted: {}", idx + 1, line));
+        };
+        let name = line[..start].trim_end();
+        if !(name.is_empty() || name.ends_with('=')) || start == line.len() - 1 {
+            return Err(anyhow!("Line {}: Malformed token: {}", idx + 1, line));
+        }
+
+        let value = &line.as_bytes()[(start + 1)..(line.len() - 1)];
+        let token = unescape(value).ok_or_else(|| {
+            anyhow!(
+                "Line {}: Invalid escape sequence in token: {}",
+                idx + 1,
+                line
+            )
+        })?;
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn unescape(value: &[u8]) -> Option<Vec<u8>> {
+    let mut ret = Vec::with_capacity(value.len());
+    let mut iter = value.iter();
+
+    while let Some(c) = iter.next() {
+        if *c != b'\\' {
+            ret.push(*c);
+            continue;
+        }
+        match iter.next()? {
+            b'\\' => ret.push(b'\\'),
+            b'"' => ret.push(b'"'),
+            b'x' => {
+                let digits = [*iter.next()?, *iter.next()?];
+                let digits = std::str::from_utf8(&digits).ok()?;
+                ret.push(u8::from_str_radix(digits, 16).ok()?);
+            }
+            _ => return None,
+        }
+    }
+
+    Some(ret)
+}
+
+/// Extract the printable strings from the `.rodata` section of the ELF file at `path`.
+pub fn elf_strings(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let data = fs::read(path)?;
+    let file = elf::ElfBytes::<elf::endian::AnyEndian>::minimal_parse(&data)
+        .map_err(|e| anyhow!("Failed to parse ELF file: {}", e))?;
+    let Some(shdr) = file
+        .section_header_by_name(".rodata")
+        .map_err(|e| anyhow!("Failed to parse section headers: {}", e))?
+    else {
+        return Ok(Vec::new());
+    };
+    let (rodata, _) = file
+        .section_data(&shdr)
+        .map_err(|e| anyhow!("Failed to read .rodata: {}", e))?;
+
+    let strings = rodata
+        .split(|b| !(b.is_ascii_graphic() || *b == b' '))
+        .filter(|s| s.len() >= MIN_STRING_LEN)
+        .map(|s| s.to_vec())
+        .take(MAX_STRINGS_PER_BINARY)
+        .collect();
+    Ok(strings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_afl_dictionary;
+
+    #[test]
+    fn afl_dictionary_is_parsed() {
+        let content = r#"
+# Comment
+kw_get="GET"
+header_ct@2="Content-Type: \x22text\x22"
+"\\\"\x00"
+"#;
+        let tokens = parse_afl_dictionary(content).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                b"GET".to_vec(),
+                b"Content-Type: \"text\"".to_vec(),
+                b"\\\"\x00".to_vec(),
+            ]
+        );
+
+        assert!(parse_afl_dictionary("GET").is_err());
+        assert!(parse_afl_dictionary("kw \"GET\"").is_err());
+        assert!(parse_afl_dictionary("\"\\q\"").is_err());
+    }
+}
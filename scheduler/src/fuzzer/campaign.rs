@@ -10,10 +10,11 @@ use crate::{
     sink_bitmap::{Bitmap, BITMAP_DEFAULT_MAP_SIZE},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use log::*;
 
 use super::{
+    crash_triage::CrashTriage,
     queue::Queue,
     worker::{FuzzingWorker, WorkerProxy, WorkerUid},
     worker_impl::Cerebrum,
@@ -33,6 +34,7 @@ pub struct FuzzingCampaign {
     cerebrum: Arc<RwLock<Option<Cerebrum>>>,
     shared_virgin_map: Arc<Mutex<Bitmap>>,
     shared_crash_virgin_map: Arc<Mutex<Bitmap>>,
+    crash_triage: Arc<Mutex<CrashTriage>>,
     restarted_worker: Vec<WorkerUid>,
 }
 
@@ -59,6 +61,9 @@ impl FuzzingCampaign {
             BITMAP_DEFAULT_MAP_SIZE,
             0xff,
         )));
+        let crash_triage = Arc::new(Mutex::new(CrashTriage::new(
+            &config.general.crash_buckets_path(),
+        )?));
 
         Ok(FuzzingCampaign {
             config: config.clone(),
@@ -69,10 +74,30 @@ impl FuzzingCampaign {
             cerebrum,
             shared_virgin_map,
             shared_crash_virgin_map,
+            crash_triage,
             restarted_worker: Vec::new(),
         })
     }
 
+    /// Create a FuzzingCampaign that continues the campaign dumped into the workdir
+    /// of `config`. Instead of importing the seed files, the workers restore the
+    /// shared state from the dumped queue.
+    pub fn resume(config: &Config) -> Result<Self> {
+        let campaign = FuzzingCampaign::new(config)?;
+        let queue = Queue::resume(
+            &config.general.queue_path(),
+            &config.general.queue_stats_path(),
+        )?;
+        if queue.is_empty() {
+            return Err(anyhow!(
+                "There is no queue to resume in {:?}",
+                config.general.work_dir
+            ));
+        }
+        *campaign.queue.lock().unwrap() = queue;
+        Ok(campaign)
+    }
+
     /// Get the queue that is shared by all threads.
     pub fn queue(&self) -> Arc<Mutex<Queue>> {
         self.queue.clone()
@@ -96,6 +121,7 @@ impl FuzzingCampaign {
                 self.queue.clone(),
                 self.shared_virgin_map.clone(),
                 self.shared_crash_virgin_map.clone(),
+                self.crash_triage.clone(),
                 self.cerebrum.clone(),
                 init_shared_barrier.clone(),
             );
@@ -117,6 +143,7 @@ impl FuzzingCampaign {
             self.queue.clone(),
             self.shared_virgin_map.clone(),
             self.shared_crash_virgin_map.clone(),
+            self.crash_triage.clone(),
             self.cerebrum.clone(),
             init_shared_barrier.clone(),
         );
@@ -180,6 +207,12 @@ impl FuzzingCampaign {
             "Runtime             : {:?}",
             global_stats_sum.init_ts.unwrap().elapsed()
         );
+        if let Some(cerebrum) = self.cerebrum.read().unwrap().as_ref() {
+            info!(
+                "Mutator probabilities: {:#?}",
+                cerebrum.query().learned_mutator_probabilities()
+            );
+        }
 
         Ok(())
     }
@@ -188,6 +221,12 @@ impl FuzzingCampaign {
     pub fn dump(&self) -> Result<()> {
         let queue = self.queue.lock().unwrap();
         queue.dump(&self.config.general.queue_path())?;
+        queue.dump_stats(&self.config.general.queue_stats_path())?;
+        drop(queue);
+
+        if let Some(cerebrum) = self.cerebrum.read().unwrap().as_ref() {
+            cerebrum.dump_stats(&self.config.general.cerebrum_stats_path())?;
+        }
         Ok(())
     }
 
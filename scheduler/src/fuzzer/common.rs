@@ -1,13 +1,9 @@
 use std::{
     collections::HashSet,
-    fs::File,
     hash::Hasher,
-    io::{Read, Seek},
     path::PathBuf,
-    process::{Child, Command, Stdio},
     sync::{Arc, RwLock},
-    thread,
-    time::Instant,
+    time::{Instant, SystemTime},
 };
 
 use crate::{
@@ -16,7 +12,11 @@ use crate::{
         CALIBRATION_MEASURE_CYCLES, DEFAULT_CALIBRATION_TIMEOUT, EXECUTION_TIMEOUT_MULTIPLYER,
     },
     mutation_cache_ops::MutationCacheOpsEx,
-    networked::{get_consumer, get_producer, Client, Server, WaitForPeerResult},
+    networked::{
+        get_consumer, get_producer, spawn_topology, Client, Server, TopologySpawnResult,
+        WaitForPeerResult,
+    },
+    packet_capture::{CaptureFilter, PacketCapture},
     sink::{self, AflSink},
     sink_bitmap::Bitmap,
     source::{self, Source},
@@ -28,11 +28,6 @@ use anyhow::{Context, Result};
 use byte_unit::n_mib_bytes;
 use fuzztruction_shared::{mutation_cache::MutationCache, types::MutationSiteID};
 use lazy_static::lazy_static;
-use nix::{
-    sys::signal::{kill, Signal},
-    unistd::Pid,
-};
-use wait_timeout::ChildExt;
 
 use std::time::Duration;
 use thiserror::Error;
@@ -112,56 +107,22 @@ pub enum ExecError {
     /// The source execution was successfull, but we already saw the produced output (hash).
     #[error("Duplicated Output.")]
     DuplicatedOutput,
+    /// The server got ready, but the client never connected to it.
+    #[error("The client never connected to the server.")]
+    ClientNotConnected,
 }
 
-struct TcpDumpInstance {
-    pub pcap_file: File,
-    pub child: Option<Child>,
-}
-
-impl Drop for TcpDumpInstance {
-    fn drop(&mut self) {
-        // file is a tempfile, thus we do not need to take care of it.
-        if let Some(mut child) = self.child.take() {
-            let _ = child.kill();
-            child
-                .wait_timeout(Duration::from_secs(120))
-                .expect("Failed to kill tcpdump");
-        }
-    }
-}
-
-fn start_tcpdump(_config: &Config) -> Option<TcpDumpInstance> {
-    // let dst_port = config
-    //     .server_port()
-    //     .expect("Server port not set in the config");
-
-    let pcap_file = match tempfile::tempfile() {
-        Ok(path) => path,
-        Err(err) => {
-            log::warn!("Error while allocating temporary pcap file: {err:#?}");
-            return None;
-        }
-    };
-
-    let mut cmd = Command::new("/usr/bin/tcpdump");
-    cmd.stdout(pcap_file.try_clone().unwrap());
-    cmd.stderr(Stdio::null());
-
-    cmd.args(["-i", "lo", "-U"]);
-    cmd.args(["-w", "-"]);
-
-    let child = match cmd.spawn() {
-        Ok(child) => Some(child),
+/// Start recording the traffic of the calibration runs. Failing to start the
+/// recording is not fatal, the queue entry just does not get a pcap attached.
+fn start_packet_capture(config: &Config) -> Option<PacketCapture> {
+    let filter = CaptureFilter::from_config(config, true)?;
+    match PacketCapture::start(filter) {
+        Ok(capture) => Some(capture),
         Err(err) => {
-            log::warn!("Failed to run tcpdump: {err:#?}");
-            return None;
+            log::warn!("Failed to start packet capture: {err:#}");
+            None
         }
-    };
-
-    // Give tcpflow some time to spin up
-    thread::sleep(Duration::from_secs(1));
-    Some(TcpDumpInstance { pcap_file, child })
+    }
 }
 
 /// Produces a new QueueEntry from an input and mutations (that have been previously configured via the soruces mutation cache).
@@ -190,7 +151,7 @@ pub fn common_calibrate(
     let mut sink_input = Vec::<u8>::with_capacity(4096);
     let mut bitmaps = Vec::new();
 
-    let mut tcpdump_instance = start_tcpdump(config);
+    let packet_capture = start_packet_capture(config);
 
     // Get the default timeout value.
     let mut default_timeout = parent
@@ -201,14 +162,20 @@ pub fn common_calibrate(
         .unwrap_or(DEFAULT_CALIBRATION_TIMEOUT);
 
     let mut exec_durations = Vec::with_capacity(CALIBRATION_MEASURE_CYCLES.try_into().unwrap());
-    if config.sink.send_sigterm {
+    // Sessions are terminated as soon as they ended, thus they do not need the
+    // additional time a sink that is only terminated at the timeout needs.
+    let terminated_at_timeout = config.sink.send_sigterm && !config.detects_session_end();
+    if terminated_at_timeout {
         default_timeout *= 2;
     }
 
     let mut crash_did_not_crash_ctr = 0;
+    // The transcript only covers the last calibration run.
+    let mut last_cycle_start = SystemTime::now();
 
     for _ in 0..CALIBRATION_MEASURE_CYCLES {
         let cycle_start_ts = Instant::now();
+        last_cycle_start = SystemTime::now();
 
         // This will only return Ok(...) if we made it until execution of the sink.
         let sink_res =
@@ -228,7 +195,7 @@ pub fn common_calibrate(
             }
         }
         let calibration_round_duration = cycle_start_ts.elapsed();
-        if !config.sink.send_sigterm && calibration_round_duration > default_timeout {
+        if !terminated_at_timeout && calibration_round_duration > default_timeout {
             return Err(CalibrationError::ExecutionDurationVarianceTooHight.into());
         }
 
@@ -281,49 +248,22 @@ pub fn common_calibrate(
         mc.save_bytes()
     };
 
-    if let Some(tcpdump_instance) = tcpdump_instance.as_mut() {
-        let mut child = tcpdump_instance.child.take().unwrap();
-        // Give it some time to record all remaining packages :)
-        thread::sleep(Duration::from_secs(2));
-        let pidfd = Pid::from_raw(child.id().try_into().unwrap());
-        if let Err(err) = kill(pidfd, Signal::SIGTERM) {
-            log::warn!("Failed to kill tcpdump: {err:#?}");
-        } else {
-            match child.wait_timeout(Duration::from_secs(30)) {
-                Ok(Some(_)) => (),
-                Ok(None) => {
-                    // we hit the timeout
-                    log::warn!("tcpdump did not terminate in time");
-                    if let Err(err) = child.kill() {
-                        log::warn!("Failed to send sigkill: {err:#?}");
-                    }
-                    child
-                        .wait_timeout(Duration::from_secs(30))
-                        .expect("Failed to kill tcpdump");
-                }
-                Err(err) => log::warn!("Error while waiting for tcpdump: {err:#?}"),
-            }
-        }
-    }
-
-    let mut pcap = if let Some(tcpdump_instance) = &mut tcpdump_instance {
-        let file = &mut tcpdump_instance.pcap_file;
-        file.rewind().unwrap();
-
-        let mut buf = Vec::new();
-        match file.read_to_end(&mut buf) {
-            Ok(size) => {
-                log::info!("Recorded a PCAP of size {size} during calibration.");
-                Some(buf)
-            }
-            Err(err) => {
-                log::warn!("Failed to read pcap file: {err:#?}");
-                None
-            }
+    let recording = packet_capture.and_then(|capture| match capture.stop() {
+        Ok(recording) => Some(recording),
+        Err(err) => {
+            log::warn!("Failed to record pcap: {err:#}");
+            None
         }
-    } else {
-        None
-    };
+    });
+    let mut pcap = recording.as_ref().map(|recording| {
+        let pcap = recording.to_pcapng();
+        log::info!("Recorded a PCAP of size {} during calibration.", pcap.len());
+        pcap
+    });
+    let mut transcript = recording
+        .as_ref()
+        .map(|recording| recording.transcript(last_cycle_start))
+        .filter(|transcript| !transcript.is_empty());
 
     if pcap
         .as_ref()
@@ -333,6 +273,14 @@ pub fn common_calibrate(
         log::info!("Recorded pcap is too big, dropping");
         pcap.take();
     }
+    if transcript
+        .as_ref()
+        .map(|t| t.len_bytes() > n_mib_bytes!(4) as usize)
+        .unwrap_or(false)
+    {
+        log::info!("Recorded transcript is too big, dropping");
+        transcript.take();
+    }
 
     let mut qe = QueueEntry::new(
         qe_input,
@@ -347,6 +295,7 @@ pub fn common_calibrate(
         patch_point,
         is_crash,
         pcap,
+        transcript,
     );
 
     if is_crash && crash_did_not_crash_ctr > 0 {
@@ -367,7 +316,7 @@ pub fn common_calibrate(
 }
 
 #[inline]
-fn common_calibration_run(
+pub(crate) fn common_calibration_run(
     config: &Config,
     source: &mut Source,
     sink: &mut AflSink,
@@ -467,7 +416,31 @@ pub fn common_trace(
     }
 
     let trace_result =
-        if config.target_uses_network() {
+        if let Some(topology) = config.topology.as_ref() {
+            source.enable_tracing()?;
+            let _helpers = match spawn_topology(topology, source, sink, timeout)? {
+                TopologySpawnResult::Ready(helpers) => helpers,
+                TopologySpawnResult::PeerNotReady {
+                    is_last: true,
+                    result,
+                } => {
+                    return Err(
+                        CalibrationError::SinkExecutionFailed(result.try_into().unwrap()).into(),
+                    );
+                }
+                TopologySpawnResult::PeerNotReady { result, .. } => {
+                    return Err(CalibrationError::SourceExecutionFailed(
+                        result.try_into().unwrap(),
+                    )
+                    .into());
+                }
+            };
+
+            // Wait for the producer to terminate and kill the remaining peers.
+            let producer_result = source.wait_for_child_termination(timeout, false)?;
+            let _consumer_result = sink.wait_for_child_termination(timeout, true, None)?;
+            source.disable_tracing_and_process_result(producer_result)
+        } else if config.target_uses_network() {
             let (mut client, mut server) = if config.source.is_server.unwrap() {
                 (Client::AflSink(sink), Server::Source(source))
             } else {
@@ -5,7 +5,10 @@ use std::time::{Duration, Instant};
 use crate::{
     config::Config,
     fuzzer::common::CalibrationError,
-    networked::{get_consumer, get_producer, Client, Server, WaitForPeerResult},
+    networked::{
+        get_consumer_and_producer, get_producer, spawn_topology, wait_for_consumer_termination,
+        Client, HelperPeer, Server, TopologySpawnResult, WaitForPeerResult,
+    },
     sink::{self, AflSink},
     source::Source,
 };
@@ -19,6 +22,28 @@ pub fn networked_common_calibration_run(
     _data: &[u8],
     timeout: Duration,
 ) -> Result<sink::RunResult> {
+    if let Some(topology) = config.topology.as_ref() {
+        // Same mapping as below, failing to start the last peer corresponds to a
+        // failing client, every other peer to a failing server.
+        let helpers = match spawn_topology(topology, source, sink, timeout)? {
+            TopologySpawnResult::Ready(helpers) => helpers,
+            TopologySpawnResult::PeerNotReady {
+                is_last: true,
+                result,
+            } => {
+                return Err(
+                    CalibrationError::SinkExecutionFailed(result.try_into().unwrap()).into(),
+                )
+            }
+            TopologySpawnResult::PeerNotReady { result, .. } => {
+                return Err(
+                    CalibrationError::SourceExecutionFailed(result.try_into().unwrap()).into(),
+                )
+            }
+        };
+        return topology_wait_for_termination(config, source, sink, helpers, timeout);
+    }
+
     let (mut client, mut server) = if config.source.is_server.unwrap() {
         (Client::AflSink(sink), Server::Source(source))
     } else {
@@ -43,7 +68,7 @@ pub fn networked_common_calibration_run(
 
     client.spawn(timeout).context("Executing client")?;
 
-    let ret = client.wait_until_connect(timeout)?;
+    let ret = client.wait_until_session_established(config, timeout)?;
     match ret {
         WaitForPeerResult::Terminated(_)
         | WaitForPeerResult::Signalled(_)
@@ -60,9 +85,9 @@ pub fn networked_common_calibration_run(
     }
 
     let start_ts = Instant::now();
-    let consumer = get_consumer(&mut client, &mut server);
+    let (consumer, producer) = get_consumer_and_producer(&mut client, &mut server);
 
-    let consumer_result = consumer.wait_for_child_termination(timeout, false, None)?;
+    let consumer_result = wait_for_consumer_termination(consumer, producer, config, timeout, None)?;
     log::trace!(
         "wait_for_child_termination of consumer took {:?}",
         start_ts.elapsed()
@@ -80,6 +105,19 @@ pub fn networked_common_run(
     sink: &mut AflSink,
     timeout: Duration,
 ) -> Result<sink::RunResult> {
+    if let Some(topology) = config.topology.as_ref() {
+        let helpers = match spawn_topology(topology, source, sink, timeout)? {
+            TopologySpawnResult::Ready(helpers) => helpers,
+            TopologySpawnResult::PeerNotReady { is_last: true, .. } => {
+                return Err(ExecError::ClientNotConnected.into())
+            }
+            TopologySpawnResult::PeerNotReady { .. } => {
+                return Err(ExecError::NoSourceOutput.into())
+            }
+        };
+        return topology_wait_for_termination(config, source, sink, helpers, timeout);
+    }
+
     let (mut client, mut server) = if config.source.is_server.unwrap() {
         (Client::AflSink(sink), Server::Source(source))
     } else {
@@ -102,13 +140,13 @@ pub fn networked_common_run(
     }
 
     client.spawn(timeout).context("Executing client")?;
-    let ret = client.wait_until_connect(timeout)?;
+    let ret = client.wait_until_session_established(config, timeout)?;
     match ret {
         WaitForPeerResult::Terminated(_)
         | WaitForPeerResult::Signalled(_)
         | WaitForPeerResult::TimedOut => {
             log::trace!("Client failed to connect to server");
-            let err = Err(ExecError::NoSourceOutput);
+            let err = Err(ExecError::ClientNotConnected);
             // kill the server
             let _ = server.wait_for_child_termination(timeout, true)?;
             return err.context("Client failed to connect to server");
@@ -116,11 +154,26 @@ pub fn networked_common_run(
         WaitForPeerResult::Ready => (),
     }
 
-    let consumer = get_consumer(&mut client, &mut server);
-    let consumer_result = consumer.wait_for_child_termination(timeout, false, None)?;
+    let (consumer, producer) = get_consumer_and_producer(&mut client, &mut server);
+    let consumer_result = wait_for_consumer_termination(consumer, producer, config, timeout, None)?;
 
     // Consumer terminated. Now kill the producer (if still running) and wait for it to terminate.
     get_producer(&mut client, &mut server).wait_for_child_termination(timeout, true)?;
 
     Ok(consumer_result)
 }
+
+/// Wait for the consumer of a topology run to terminate and kill all other peers
+/// afterwards.
+fn topology_wait_for_termination(
+    config: &Config,
+    source: &mut Source,
+    sink: &mut AflSink,
+    helpers: Vec<HelperPeer>,
+    timeout: Duration,
+) -> Result<sink::RunResult> {
+    let consumer_result = wait_for_consumer_termination(sink, source, config, timeout, None)?;
+    source.wait_for_child_termination(timeout, true)?;
+    drop(helpers);
+    Ok(consumer_result)
+}
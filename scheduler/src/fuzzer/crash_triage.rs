@@ -0,0 +1,179 @@
+//! Deduplication of the crashes found during fuzzing. Crashes are sorted into
+//! buckets by the stack hash of their sanitizer report, similar to what
+//! [ValgrindManager](crate::valgrind::ValgrindManager) does for its reports.
+//! Each bucket is a directory in `crashes/` that contains a summary and a few
+//! samples of the crashes sorted into it.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::sanitizer::{Sanitizer, SanitizerReport, STACK_HASH_FRAMES};
+
+use super::queue::QueueEntryId;
+
+/// Name of the summary file in each bucket directory.
+const SUMMARY_FILE_NAME: &str = "summary.json";
+/// Number of samples (input and report) that are stored per bucket.
+const MAX_SAMPLES_PER_BUCKET: usize = 4;
+/// Number of queue entries that are recorded per bucket.
+const MAX_QUEUE_ENTRIES_PER_BUCKET: usize = 32;
+
+/// Summary of all crashes that were sorted into the same bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BucketSummary {
+    pub sanitizer: Sanitizer,
+    /// The bug type of the first crash, e.g., `heap-buffer-overflow`.
+    pub bug_type: String,
+    pub stack_hash: String,
+    /// The top frames of the crashing stack of the first crash.
+    pub frames: Vec<String>,
+    /// Number of crashes sorted into this bucket.
+    pub crashes: u64,
+    /// Time since the start of the campaign the first crash was found at.
+    pub first_seen_ms: u64,
+    /// Time since the start of the campaign the last crash was found at.
+    pub last_seen_ms: u64,
+    /// Queue entries whose crash was sorted into this bucket.
+    pub queue_entries: Vec<QueueEntryId>,
+    /// The file prefixes of the samples stored in the bucket directory.
+    pub samples: Vec<String>,
+}
+
+/// The buckets of all crashes found so far, shared by all workers.
+#[derive(Debug)]
+pub struct CrashTriage {
+    path: PathBuf,
+    buckets: HashMap<String, BucketSummary>,
+}
+
+impl CrashTriage {
+    /// Create a triage that stores its buckets in `path`. Buckets already stored
+    /// in `path`, e.g., by a campaign that is resumed, are loaded.
+    pub fn new(path: &Path) -> Result<CrashTriage> {
+        fs::create_dir_all(path)?;
+
+        let mut buckets = HashMap::new();
+        for entry in fs::read_dir(path)?.flatten() {
+            let summary_path = entry.path().join(SUMMARY_FILE_NAME);
+            if !summary_path.exists() {
+                continue;
+            }
+            let content = fs::read(&summary_path)?;
+            let summary: BucketSummary = serde_json::from_slice(&content)
+                .with_context(|| format!("Failed to parse {:?}", summary_path))?;
+            buckets.insert(entry.file_name().to_string_lossy().into_owned(), summary);
+        }
+
+        Ok(CrashTriage {
+            path: path.to_owned(),
+            buckets,
+        })
+    }
+
+    /// Sort a crash into the bucket of its `report`. If the bucket has room for
+    /// another sample, `input` and `raw_report` are stored using `prefix` as name.
+    /// Returns the name of the bucket and whether the bucket is new, i.e., the
+    /// crash is caused by a bug not seen before.
+    pub fn add(
+        &mut self,
+        report: &SanitizerReport,
+        raw_report: &str,
+        input: &[u8],
+        prefix: &str,
+        queue_entry: Option<QueueEntryId>,
+        ts_ms: u64,
+    ) -> Result<(String, bool)> {
+        let bucket = report.bucket();
+        let bucket_path = self.path.join(&bucket);
+        let is_new = !self.buckets.contains_key(&bucket);
+
+        let summary = self
+            .buckets
+            .entry(bucket.clone())
+            .or_insert_with(|| BucketSummary {
+                sanitizer: report.sanitizer,
+                bug_type: report.bug_type.clone(),
+                stack_hash: report.stack_hash(STACK_HASH_FRAMES),
+                frames: report
+                    .frames
+                    .iter()
+                    .take(STACK_HASH_FRAMES)
+                    .map(|frame| frame.to_string())
+                    .collect(),
+                crashes: 0,
+                first_seen_ms: ts_ms,
+                last_seen_ms: ts_ms,
+                queue_entries: Vec::new(),
+                samples: Vec::new(),
+            });
+        summary.crashes += 1;
+        summary.last_seen_ms = summary.last_seen_ms.max(ts_ms);
+        if let Some(queue_entry) = queue_entry {
+            if summary.queue_entries.len() < MAX_QUEUE_ENTRIES_PER_BUCKET {
+                summary.queue_entries.push(queue_entry);
+            }
+        }
+
+        fs::create_dir_all(&bucket_path)?;
+        if summary.samples.len() < MAX_SAMPLES_PER_BUCKET {
+            fs::write(bucket_path.join(format!("{}.input", prefix)), input)?;
+            fs::write(bucket_path.join(format!("{}.report", prefix)), raw_report)?;
+            summary.samples.push(prefix.to_owned());
+        }
+
+        let summary_path = bucket_path.join(SUMMARY_FILE_NAME);
+        let tmp_path = summary_path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(summary)?)?;
+        fs::rename(&tmp_path, &summary_path)?;
+
+        Ok((bucket, is_new))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fuzzer::queue::QueueEntryId, sanitizer::SanitizerReport};
+
+    use super::CrashTriage;
+
+    const REPORT: &str = "\
+==42==ERROR: AddressSanitizer: stack-buffer-overflow on address 0x7ffd5c2a1b30
+    #0 0x55d0c3a1b2c4 in parse_record /src/parser.c:42:7
+    #1 0x55d0c3a1b3d5 in main /src/main.c:7:3
+";
+
+    #[test]
+    fn duplicates_share_a_bucket() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut triage = CrashTriage::new(dir.path()).unwrap();
+
+        let report = SanitizerReport::parse(REPORT).unwrap();
+        let (bucket, is_new) = triage
+            .add(&report, REPORT, b"a", "first", Some(QueueEntryId(1)), 10)
+            .unwrap();
+        assert!(is_new);
+        let relocated = REPORT.replace("0x55d0c3a1", "0x56aa0000");
+        let report = SanitizerReport::parse(&relocated).unwrap();
+        let (other, is_new) = triage
+            .add(&report, &relocated, b"b", "second", None, 20)
+            .unwrap();
+        assert!(!is_new);
+        assert_eq!(other, bucket);
+
+        // The buckets are restored from disk.
+        let triage = CrashTriage::new(dir.path()).unwrap();
+        assert_eq!(triage.buckets.len(), 1);
+        let summary = &triage.buckets[&bucket];
+        assert_eq!(summary.crashes, 2);
+        assert_eq!(summary.bug_type, "stack-buffer-overflow");
+        assert_eq!(summary.queue_entries, vec![QueueEntryId(1)]);
+        assert_eq!((summary.first_seen_ms, summary.last_seen_ms), (10, 20));
+        assert!(dir.path().join(&bucket).join("second.input").exists());
+    }
+}
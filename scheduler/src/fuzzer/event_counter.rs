@@ -34,12 +34,17 @@ pub struct FuzzerEventCounter {
     pub source_no_output: u64,
     /// The source produced output already seen.
     pub source_duplicated_output: u64,
+    /// Number of executions where the client never connected to the server.
+    pub client_not_connected: u64,
     /// Number of executions that caused the sink to timeout.
     pub sink_timeout: u64,
     /// Number of execution that caused the sink to crash .
     pub sink_crashes: u64,
     /// Number of execution with unique coverage map that caused the sink to crash .
     pub sink_unique_crashes: u64,
+    /// Number of crashes of the sink whose sanitizer report has a stack hash not
+    /// seen before, i.e., the number of unique bugs.
+    pub sink_unique_bugs: u64,
 }
 
 impl std::fmt::Debug for FuzzerEventCounter {
@@ -76,8 +81,13 @@ impl std::fmt::Debug for FuzzerEventCounter {
                 "source_duplicated_output",
                 &rel_to_execs(self.source_duplicated_output),
             )
+            .field(
+                "client_not_connected",
+                &rel_to_execs(self.client_not_connected),
+            )
             .field("sink_timeout", &rel_to_execs(self.sink_timeout))
             .field("sink_crashes", &rel_to_execs(self.sink_crashes))
+            .field("sink_unique_bugs", &self.sink_unique_bugs)
             .finish()
     }
 }
@@ -139,8 +149,10 @@ where
         self.source_timeout += rhs.source_timeout;
         self.source_no_output += rhs.source_no_output;
         self.source_duplicated_output += rhs.source_duplicated_output;
+        self.client_not_connected += rhs.client_not_connected;
         self.sink_timeout += rhs.sink_timeout;
         self.sink_crashes += rhs.sink_crashes;
+        self.sink_unique_bugs += rhs.sink_unique_bugs;
     }
 }
 
@@ -160,8 +172,10 @@ where
         self.source_timeout += rhs.source_timeout;
         self.source_no_output += rhs.source_no_output;
         self.source_duplicated_output += rhs.source_duplicated_output;
+        self.client_not_connected += rhs.client_not_connected;
         self.sink_timeout += rhs.sink_timeout;
         self.sink_crashes += rhs.sink_crashes;
+        self.sink_unique_bugs += rhs.sink_unique_bugs;
         self
     }
 }
@@ -182,8 +196,10 @@ where
         self.source_timeout -= rhs.source_timeout;
         self.source_no_output -= rhs.source_no_output;
         self.source_duplicated_output -= rhs.source_duplicated_output;
+        self.client_not_connected -= rhs.client_not_connected;
         self.sink_timeout -= rhs.sink_timeout;
         self.sink_crashes -= rhs.sink_crashes;
+        self.sink_unique_bugs -= rhs.sink_unique_bugs;
         self
     }
 }
@@ -202,8 +218,10 @@ where
         self.source_timeout -= rhs.source_timeout;
         self.source_no_output -= rhs.source_no_output;
         self.source_duplicated_output -= rhs.source_duplicated_output;
+        self.client_not_connected -= rhs.client_not_connected;
         self.sink_timeout -= rhs.sink_timeout;
         self.sink_crashes -= rhs.sink_crashes;
+        self.sink_unique_bugs -= rhs.sink_unique_bugs;
     }
 }
 
@@ -1,8 +1,9 @@
 pub mod campaign;
 pub mod queue;
 
-mod common;
+pub(crate) mod common;
 mod common_networked;
+mod crash_triage;
 
 mod event_counter;
 mod worker;
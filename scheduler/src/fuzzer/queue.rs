@@ -28,6 +28,7 @@ use crate::{
     finite_integer_set::FiniteIntegerSet,
     sink_bitmap::Bitmap,
     trace::Trace,
+    transcript::Transcript,
 };
 
 use super::{
@@ -184,6 +185,25 @@ pub struct QueueEntryStats {
     favoured_weight: Option<NonZeroU32>,
 }
 
+/// The parts of [QueueEntryStats] that change after an entry was dumped and
+/// are required to resume a campaign, see [Queue::dump_stats].
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedEntryStats {
+    id: QueueEntryId,
+    blacklisted: bool,
+    phases_done: HashSet<FuzzingPhase>,
+    mutators_done: HashSet<MutatorType>,
+    favoured_weight: Option<NonZeroU32>,
+}
+
+/// The content of the file written by [Queue::dump_stats].
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedQueueStats {
+    /// Milliseconds since the start of the campaign.
+    runtime_ms: i64,
+    entries: Vec<PersistedEntryStats>,
+}
+
 /// See [GlobalStats] for the "counterpart" of the methods below.
 impl QueueEntryStats {
     /// Attach a trace the the `QueueEntryStats`. This must be called before
@@ -312,6 +332,9 @@ pub struct QueueEntry {
     /// Whether this is a queue entry that causes a crash of the consumer.
     is_crash: bool,
     pcap: Option<Vec<u8>>,
+    /// The messages exchanged by the peers during the last calibration run.
+    #[serde(default)]
+    transcript: Option<Transcript>,
 }
 
 impl PartialEq for QueueEntry {
@@ -365,6 +388,7 @@ impl QueueEntry {
         patch_point: Option<MutationSiteID>,
         is_crash: bool,
         pcap: Option<Vec<u8>>,
+        transcript: Option<Transcript>,
     ) -> Self {
         let next_id = QUEUE_ENTRY_NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
         let stats = QueueEntryStats {
@@ -397,6 +421,7 @@ impl QueueEntry {
             patch_point,
             is_crash,
             pcap,
+            transcript,
         }
     }
 
@@ -496,6 +521,10 @@ impl QueueEntry {
     pub fn pcap(&self) -> &Option<Vec<u8>> {
         &self.pcap
     }
+
+    pub fn transcript(&self) -> Option<&Transcript> {
+        self.transcript.as_ref()
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize)]
@@ -623,15 +652,20 @@ impl Queue {
             if entry_path.exists() {
                 // Was already dumped.
             } else {
+                // Write to a temporary file first, thus an interrupted dump does
+                // not leave a truncated entry behind.
+                let tmp_path = entry_path.with_extension("tmp");
                 let mut file = OpenOptions::new()
                     .create(true)
+                    .truncate(true)
                     .write(true)
-                    .open(entry_path)
+                    .open(&tmp_path)
                     .unwrap();
                 let mut compressor = ZlibEncoder::new(Vec::new(), Compression::default());
                 serde_json::to_writer_pretty(&mut compressor, &entry).unwrap();
                 let compressed_bytes = compressor.finish().unwrap();
                 file.write_all(&compressed_bytes).unwrap();
+                fs::rename(&tmp_path, &entry_path).unwrap();
             }
         });
         //});
@@ -646,8 +680,19 @@ impl Queue {
             .into_iter()
             .flatten()
             .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "zlib"))
             .collect_vec();
 
+        // Entries created from now on must not reuse the IDs of dumped entries,
+        // even of those that are not loaded due to `id_whitelist`.
+        let next_id = files
+            .iter()
+            .filter_map(|path| path.file_prefix()?.to_str()?.parse::<u64>().ok())
+            .map(|id| id + 1)
+            .max()
+            .unwrap_or(0);
+        QUEUE_ENTRY_NEXT_ID.fetch_max(next_id, std::sync::atomic::Ordering::SeqCst);
+
         if let Some(id_whitelist) = id_whitelist {
             files.retain(|path| {
                 id_whitelist.contains(
@@ -683,6 +728,117 @@ impl Queue {
         Ok(ret)
     }
 
+    /// Read the data of the inputs of all entries from their origin path, since
+    /// it is not part of a dumped queue. Entries without an origin path keep an
+    /// empty input.
+    pub fn reload_inputs(&mut self) -> Result<()> {
+        let mut inputs: HashMap<PathBuf, Arc<Input>> = HashMap::new();
+        for entry in self.entries.iter_mut() {
+            let Some(origin_path) = entry.input.origin_path.clone() else {
+                continue;
+            };
+            let input = match inputs.get(&origin_path) {
+                Some(input) => input.clone(),
+                None => {
+                    let input = Input::from_path(&origin_path)?;
+                    inputs.insert(origin_path, input.clone());
+                    input
+                }
+            };
+            Arc::make_mut(entry).input = input;
+        }
+        Ok(())
+    }
+
+    /// Dump the stats of all entries into the file `path`. In contrast to the
+    /// entries, which are dumped only once by [Queue::dump], the file is
+    /// overwritten on each call.
+    pub fn dump_stats(&self, path: &Path) -> Result<()> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let stats = entry.stats_ro();
+                PersistedEntryStats {
+                    id: entry.id(),
+                    blacklisted: stats.blacklisted,
+                    phases_done: stats.phases_done.clone(),
+                    mutators_done: stats.mutators_done.clone(),
+                    favoured_weight: stats.favoured_weight,
+                }
+            })
+            .collect();
+        let stats = PersistedQueueStats {
+            runtime_ms: (Utc::now() - self.start_ts).num_milliseconds(),
+            entries,
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&stats)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Load the queue dumped into `path` and the stats dumped into `stats_path`
+    /// in order to resume a campaign. The IDs of new entries and the creation
+    /// timestamps continue where the dumped campaign stopped.
+    pub fn resume(path: &Path, stats_path: &Path) -> Result<Queue> {
+        let mut ret = Queue::load(path, None)
+            .with_context(|| format!("Failed to load the queue from {:?}", path))?;
+        ret.entries.sort_by_key(|entry| entry.id());
+        ret.reload_inputs()?;
+
+        let mut runtime_ms = ret
+            .entries
+            .iter()
+            .filter_map(|entry| entry.creation_ts())
+            .max()
+            .unwrap_or(0);
+        if stats_path.exists() {
+            let content =
+                fs::read(stats_path).with_context(|| format!("Failed to read {:?}", stats_path))?;
+            let stats: PersistedQueueStats = serde_json::from_slice(&content)
+                .with_context(|| format!("Failed to parse {:?}", stats_path))?;
+            runtime_ms = runtime_ms.max(stats.runtime_ms);
+
+            let entries = ret
+                .entries
+                .iter()
+                .map(|entry| (entry.id(), entry))
+                .collect::<HashMap<_, _>>();
+            for persisted in stats.entries {
+                let Some(entry) = entries.get(&persisted.id) else {
+                    // Entries created after the last dump are lost.
+                    continue;
+                };
+                let mut stats = entry.stats_rw();
+                stats.blacklisted = persisted.blacklisted;
+                stats.phases_done = persisted.phases_done;
+                stats.mutators_done = persisted.mutators_done;
+                stats.favoured_weight = persisted.favoured_weight;
+            }
+        } else {
+            log::warn!(
+                "{:?} does not exist, the stats of the queue entries are not restored",
+                stats_path
+            );
+        }
+
+        for entry in ret.entries.iter() {
+            // Entries that were traced while being dumped are traced again.
+            entry.stats_rw().tracing_in_progress = false;
+        }
+
+        ret.start_ts = Utc::now() - chrono::Duration::milliseconds(runtime_ms);
+
+        log::info!(
+            "Resumed queue with {} entries after {:?} of fuzzing",
+            ret.len(),
+            Duration::from_millis(runtime_ms.max(0) as u64)
+        );
+        Ok(ret)
+    }
+
     pub fn print_queue_stats(&self) {
         let mut stage_yield_frequency = HashMap::new();
         let mut mutator_yield_frequency = HashMap::new();
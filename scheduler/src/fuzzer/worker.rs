@@ -13,6 +13,8 @@ use std::{
 
 use crate::{
     config::Config,
+    custom_mutator::CustomMutatorPlugin,
+    dictionary::Dictionary,
     sink::AflSink,
     sink_bitmap::{Bitmap, BITMAP_DEFAULT_MAP_SIZE},
     source::Source,
@@ -28,6 +30,7 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 use super::{
+    crash_triage::CrashTriage,
     event_counter::FuzzerEventCounter,
     queue::{Queue, QueueEntry},
     worker_impl::{Cerebrum, FuzzingPhase, MutatorType},
@@ -180,6 +183,10 @@ pub struct FuzzingWorker {
     pub source: Option<Source>,
     /// The sink that is used during fuzzing.
     pub sink: Option<AflSink>,
+    /// Tokens used by the dictionary phase.
+    pub dictionary: Arc<Dictionary>,
+    /// The instances of the custom mutators used by the mutate phase.
+    pub custom_mutators: Vec<Arc<Mutex<CustomMutatorPlugin>>>,
     /// Path to the directory in which inputs that yielded new coverage are stored.
     pub interesting_inputs: PathBuf,
     /// Path to the directory in which inputs that caused crashes are stored.
@@ -191,6 +198,8 @@ pub struct FuzzingWorker {
     /// A local cache that is queried to avoid locking `shared_crash_virgin_map`
     /// each time.
     pub crash_virgin_map: Bitmap,
+    /// Buckets of the crashes found by all workers, used to count unique bugs.
+    pub crash_triage: Arc<Mutex<CrashTriage>>,
     /// Whether the worker was requested to terminate.
     pub stop_requested: bool,
     /// Average execution duration over all executions that is used for runtime estimations.
@@ -301,6 +310,7 @@ impl FuzzingWorker {
         queue: Arc<Mutex<Queue>>,
         shared_virgin_map: Arc<Mutex<Bitmap>>,
         shared_crash_virgin_map: Arc<Mutex<Bitmap>>,
+        crash_triage: Arc<Mutex<CrashTriage>>,
         cerebrum: Arc<RwLock<Option<Cerebrum>>>,
         init_shared_barrier: Arc<Barrier>,
     ) -> FuzzingWorker {
@@ -333,11 +343,14 @@ impl FuzzingWorker {
             alive_channel: (Some(alive_channel_receive), alive_channel_send),
             source: None,
             sink: None,
+            dictionary: Default::default(),
+            custom_mutators: Vec::new(),
             interesting_inputs,
             crashing_inputs,
             asan_reports,
             crash_virgin_map,
             shared_crash_virgin_map,
+            crash_triage,
             stop_requested: false,
             state: Default::default(),
             avg_execution_duration: Duration::from_millis(50),
@@ -0,0 +1,104 @@
+//! Multi-armed bandit policy that is used to decide how much of the fuzzing budget
+//! is spent on each [MutatorType](super::MutatorType). Each arm is scored by its
+//! normalized yield per execution plus an UCB1 exploration bonus, and the scores
+//! are turned into selection probabilities. Similar to MOpt, every arm keeps a
+//! minimum probability, thus mutators that did not yield for some time are still
+//! executed occasionally.
+
+/// Weight of the exploration bonus relative to the normalized yield rate.
+const EXPLORATION_WEIGHT: f64 = 0.5;
+/// Share of the probability mass that is distributed uniformly across all arms.
+const UNIFORM_SHARE: f64 = 0.1;
+/// Bounds of the factor the iterations of an arm are scaled with.
+const MIN_ITERATIONS_FACTOR: f64 = 0.25;
+const MAX_ITERATIONS_FACTOR: f64 = 4.0;
+
+/// The observations made for a single arm.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArmStats {
+    /// Number of coverage yields.
+    pub yields: u64,
+    /// Number of executions.
+    pub execs: u64,
+}
+
+/// Get the selection probability of each arm in `arms`. The returned probabilities
+/// are in the same order as `arms` and sum up to one. Arms that were not executed
+/// yet are treated as if they are the best arm with the largest exploration bonus.
+pub fn probabilities(arms: &[ArmStats]) -> Vec<f64> {
+    if arms.is_empty() {
+        return Vec::new();
+    }
+
+    let total_execs = arms.iter().map(|arm| arm.execs).sum::<u64>().max(1) as f64;
+    let rate = |arm: &ArmStats| arm.yields as f64 / arm.execs.max(1) as f64;
+    let max_rate = arms.iter().map(rate).fold(0.0, f64::max);
+
+    let scores = arms
+        .iter()
+        .map(|arm| {
+            let executed = arm.execs.max(1) as f64;
+            let bonus = EXPLORATION_WEIGHT * (total_execs.ln().max(1.0) / executed).sqrt();
+            match arm.execs {
+                0 => 1.0 + bonus,
+                _ if max_rate > 0.0 => rate(arm) / max_rate + bonus,
+                _ => bonus,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let uniform = 1.0 / arms.len() as f64;
+    let scores_sum = scores.iter().sum::<f64>();
+    scores
+        .into_iter()
+        .map(|score| match scores_sum {
+            sum if sum > 0.0 => (1.0 - UNIFORM_SHARE) * score / sum + UNIFORM_SHARE * uniform,
+            _ => uniform,
+        })
+        .collect()
+}
+
+/// Scale `iterations` according to `probability`, where the iterations are kept as
+/// they are if the arm's probability equals the uniform probability of `arms_cnt` arms.
+pub fn scale_iterations(iterations: usize, probability: f64, arms_cnt: usize) -> usize {
+    let factor =
+        (probability * arms_cnt as f64).clamp(MIN_ITERATIONS_FACTOR, MAX_ITERATIONS_FACTOR);
+    ((iterations as f64 * factor).round() as usize).max(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{probabilities, scale_iterations, ArmStats};
+
+    #[test]
+    fn yielding_arms_are_preferred() {
+        let arms = [
+            ArmStats {
+                yields: 50,
+                execs: 10_000,
+            },
+            ArmStats {
+                yields: 1,
+                execs: 10_000,
+            },
+            ArmStats {
+                yields: 0,
+                execs: 10_000,
+            },
+        ];
+        let probs = probabilities(&arms);
+        assert!((probs.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(probs[0] > probs[1] && probs[1] > probs[2]);
+        assert!(probs[2] > 0.0);
+
+        // Arms that were never executed are explored.
+        let probs = probabilities(&[arms[0], ArmStats::default()]);
+        assert!(probs[1] >= probs[0]);
+
+        let probs = probabilities(&[ArmStats::default(); 4]);
+        assert!(probs.iter().all(|p| (p - 0.25).abs() < 1e-9));
+        assert_eq!(scale_iterations(100, 0.25, 4), 100);
+        assert_eq!(scale_iterations(100, 1.0, 4), 400);
+        assert_eq!(scale_iterations(100, 0.0, 4), 25);
+    }
+}
@@ -1,12 +1,15 @@
 #![allow(unused)]
 
+use anyhow::{anyhow, Context, Result};
 use fuzztruction_shared::{mutation_cache::MutationCache, types::MutationSiteID};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
-    default, fmt,
+    default, fmt, fs,
     iter::Sum,
     mem,
     ops::SubAssign,
+    path::Path,
     sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard},
     time::Instant,
 };
@@ -16,7 +19,7 @@ use crate::{
         event_counter::FuzzerEventCounter,
         queue::{Queue, QueueEntry, QueueEntryId},
     },
-    mutation_site::MutationSite,
+    mutation_site::{self, MutationSite, StableSiteId},
     trace::Trace,
 };
 
@@ -26,7 +29,7 @@ use super::{
     phases::FuzzingPhase,
 };
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub(super) struct PatchPointStatsEntry {
     ///Number of coverage yields.
     pub(super) yield_cnt: u64,
@@ -37,10 +40,19 @@ pub(super) struct PatchPointStatsEntry {
     ///Number of times the source timed out.
     pub(super) source_timeout_cnt: u64,
     /// All [QueueEntryId]s of the [QueueEntry]s that are using this [PatchPointID].
+    #[serde(skip)]
     pub(super) used_by: HashSet<QueueEntryId>,
 }
 
 impl PatchPointStatsEntry {
+    /// Add the counters of `other` to the counters of `self`.
+    fn add(&mut self, other: &PatchPointStatsEntry) {
+        self.yield_cnt += other.yield_cnt;
+        self.source_crash_cnt += other.source_crash_cnt;
+        self.mutation_cnt += other.mutation_cnt;
+        self.source_timeout_cnt += other.source_timeout_cnt;
+    }
+
     pub fn merge(&mut self, into: &mut PatchPointStatsEntry) {
         // into.yield_cnt += self.yield_cnt;
         // into.crash_cnt += self.crash_cnt;
@@ -52,6 +64,59 @@ impl PatchPointStatsEntry {
     }
 }
 
+/// Statistics of all configurations that used a specific [MutatorType].
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(super) struct MutatorStatsEntry {
+    /// Number of coverage yields.
+    pub(super) yield_cnt: u64,
+    /// Number of source crashes.
+    pub(super) source_crash_cnt: u64,
+    /// Total number of mutations applied.
+    pub(super) mutation_cnt: u64,
+    /// Number of times the source timed out.
+    pub(super) source_timeout_cnt: u64,
+}
+
+impl MutatorStatsEntry {
+    /// Add the counters of `other` to the counters of `self`.
+    fn add(&mut self, other: &MutatorStatsEntry) {
+        self.yield_cnt += other.yield_cnt;
+        self.source_crash_cnt += other.source_crash_cnt;
+        self.mutation_cnt += other.mutation_cnt;
+        self.source_timeout_cnt += other.source_timeout_cnt;
+    }
+}
+
+/// Masks imported via [Cerebrum::import_prior] belong to queue entries of another
+/// campaign. Their [QueueEntryId]s are moved into this range, such that they do not
+/// collide with the IDs of this campaign's queue entries.
+const IMPORTED_QUEUE_ENTRY_ID_OFFSET: u64 = 1 << 63;
+
+/// The statistics of a [Cerebrum] that are dumped while fuzzing. They are restored
+/// if a campaign is resumed, and can be imported into a fresh campaign against
+/// another build of the source, see [Cerebrum::import_prior]. Which entries use a
+/// patch point is not part of it, since it is derived from the queue via
+/// [Cerebrum::report_new_qe].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CerebrumStats {
+    patch_point_stats: Vec<(MutationSiteID, PatchPointStatsEntry)>,
+    #[serde(default)]
+    patch_point_msks: Vec<(MutationSiteID, Vec<(QueueEntryId, Arc<[u8]>)>)>,
+    mutator_stats: Vec<(MutatorType, MutatorStatsEntry)>,
+    phase_mutator_stats: Vec<(FuzzingPhase, MutatorType, MutatorStatsEntry)>,
+    /// The identity of the patch points that allows to match them with the patch
+    /// points of another build.
+    #[serde(default)]
+    sites: Vec<(MutationSiteID, StableSiteId)>,
+}
+
+impl CerebrumStats {
+    fn load(path: &Path) -> Result<CerebrumStats> {
+        let content = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        serde_json::from_slice(&content).with_context(|| format!("Failed to parse {:?}", path))
+    }
+}
+
 pub struct FuzzerConfiguration {
     entry: Arc<QueueEntry>,
     phase: FuzzingPhase,
@@ -101,14 +166,18 @@ pub struct Cerebrum {
     pub(super) patch_points: HashSet<Arc<MutationSite>>,
     pub(super) patch_point_stats: HashMap<MutationSiteID, PatchPointStatsEntry>,
     pub(super) patch_point_msks: HashMap<MutationSiteID, Vec<(QueueEntryId, Arc<[u8]>)>>,
+    pub(super) mutator_stats: HashMap<MutatorType, MutatorStatsEntry>,
     pub(super) active_configuration: Option<FuzzerConfiguration>,
     pub(super) queue: Arc<Mutex<Queue>>,
-    // replace usize with struct?
-    //pub(super) phase_mutator_yields: HashMap<(FuzzingPhase, MutatorType), usize>,
+    /// Same as `mutator_stats`, but distinguishes the phase the mutator was used in.
+    pub(super) phase_mutator_stats: HashMap<(FuzzingPhase, MutatorType), MutatorStatsEntry>,
+    /// The identity of each patch point across builds, see [StableSiteId].
+    pub(super) stable_ids: HashMap<MutationSiteID, StableSiteId>,
 }
 
 impl Cerebrum {
     pub fn new(patch_points: &[MutationSite], queue: Arc<Mutex<Queue>>) -> Cerebrum {
+        let stable_ids = mutation_site::stable_site_ids(patch_points);
         let patch_points = patch_points
             .iter()
             .cloned()
@@ -123,8 +192,11 @@ impl Cerebrum {
             patch_points,
             patch_point_stats,
             patch_point_msks: HashMap::new(),
+            mutator_stats: HashMap::new(),
             active_configuration: Default::default(),
             queue,
+            phase_mutator_stats: HashMap::new(),
+            stable_ids,
         }
     }
 
@@ -153,16 +225,146 @@ impl Cerebrum {
             for mc_entry in mc.entries() {
                 let value = self.patch_point_stats.get_mut(&mc_entry.id()).unwrap();
                 value.used_by.insert(qe.id());
-                let key = self.patch_point_msks.entry(mc_entry.id()).or_default();
-                if key
-                    .iter()
-                    .all(|entry| entry.1.as_ref() != mc_entry.get_msk_as_slice())
-                {
-                    let msk = mc_entry.get_msk_as_slice().to_owned();
-                    key.push((qe.id(), msk.into()));
+                self.add_msk(mc_entry.id(), qe.id(), mc_entry.get_msk_as_slice());
+            }
+        }
+    }
+
+    /// Add `msk` to the masks of the patch point `id`, if it is not known yet.
+    fn add_msk(&mut self, id: MutationSiteID, qe_id: QueueEntryId, msk: &[u8]) {
+        let msks = self.patch_point_msks.entry(id).or_default();
+        if msks.iter().all(|entry| entry.1.as_ref() != msk) {
+            msks.push((qe_id, msk.into()));
+        }
+    }
+
+    /// Dump the statistics into the file `path`, see [Cerebrum::restore_stats].
+    pub fn dump_stats(&self, path: &Path) -> Result<()> {
+        let stats = CerebrumStats {
+            patch_point_stats: self
+                .patch_point_stats
+                .iter()
+                .map(|(id, stats)| (*id, stats.clone()))
+                .collect(),
+            patch_point_msks: self
+                .patch_point_msks
+                .iter()
+                .map(|(id, msks)| (*id, msks.clone()))
+                .collect(),
+            mutator_stats: self
+                .mutator_stats
+                .iter()
+                .map(|(mutator, stats)| (*mutator, stats.clone()))
+                .collect(),
+            phase_mutator_stats: self
+                .phase_mutator_stats
+                .iter()
+                .map(|((phase, mutator), stats)| (*phase, *mutator, stats.clone()))
+                .collect(),
+            sites: self
+                .stable_ids
+                .iter()
+                .map(|(id, stable_id)| (*id, stable_id.clone()))
+                .collect(),
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&stats)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Restore the statistics dumped via [Cerebrum::dump_stats] into `path`.
+    /// Statistics of patch points that are unknown to this cerebrum are ignored.
+    pub fn restore_stats(&mut self, path: &Path) -> Result<()> {
+        let stats = CerebrumStats::load(path)?;
+
+        for (id, restored) in stats.patch_point_stats {
+            if let Some(entry) = self.patch_point_stats.get_mut(&id) {
+                let used_by = mem::take(&mut entry.used_by);
+                *entry = PatchPointStatsEntry {
+                    used_by,
+                    ..restored
+                };
+            } else {
+                log::warn!("Ignoring stats of unknown patch point {:?}", id);
+            }
+        }
+        for (id, msks) in stats.patch_point_msks {
+            if self.patch_point_stats.contains_key(&id) {
+                for (qe_id, msk) in msks {
+                    self.add_msk(id, qe_id, &msk);
                 }
             }
         }
+        self.mutator_stats.extend(stats.mutator_stats);
+        self.phase_mutator_stats.extend(
+            stats
+                .phase_mutator_stats
+                .into_iter()
+                .map(|(phase, mutator, stats)| ((phase, mutator), stats)),
+        );
+        Ok(())
+    }
+
+    /// Import the statistics dumped via [Cerebrum::dump_stats] into `path` by a
+    /// campaign against another build of the source as prior knowledge. Its
+    /// patch points are matched with ours via their [StableSiteId]. The stats of
+    /// patch points that can not be matched are ignored, all other stats are added
+    /// to ours.
+    pub fn import_prior(&mut self, path: &Path) -> Result<()> {
+        let stats = CerebrumStats::load(path)?;
+        if stats.sites.is_empty() {
+            return Err(anyhow!(
+                "{:?} does not contain the identities of its patch points",
+                path
+            ));
+        }
+
+        let local_ids = self
+            .stable_ids
+            .iter()
+            .map(|(id, stable_id)| (stable_id, *id))
+            .collect::<HashMap<_, _>>();
+        let id_map = stats
+            .sites
+            .iter()
+            .filter_map(|(id, stable_id)| Some((*id, *local_ids.get(stable_id)?)))
+            .collect::<HashMap<_, _>>();
+        log::info!(
+            "Matched {} of the {} patch points of the prior with the {} patch points of this build",
+            id_map.len(),
+            stats.sites.len(),
+            self.patch_points.len()
+        );
+
+        for (id, prior) in stats.patch_point_stats.iter() {
+            if let Some(entry) = id_map
+                .get(id)
+                .and_then(|id| self.patch_point_stats.get_mut(id))
+            {
+                entry.add(prior);
+            }
+        }
+        for (id, msks) in stats.patch_point_msks {
+            let Some(id) = id_map.get(&id) else {
+                continue;
+            };
+            for (qe_id, msk) in msks {
+                let qe_id = QueueEntryId(IMPORTED_QUEUE_ENTRY_ID_OFFSET | qe_id.0);
+                self.add_msk(*id, qe_id, &msk);
+            }
+        }
+        for (mutator, prior) in stats.mutator_stats.iter() {
+            self.mutator_stats.entry(*mutator).or_default().add(prior);
+        }
+        for (phase, mutator, prior) in stats.phase_mutator_stats.iter() {
+            self.phase_mutator_stats
+                .entry((*phase, *mutator))
+                .or_default()
+                .add(prior);
+        }
+        Ok(())
     }
 
     pub fn report_crash(&mut self) {
@@ -176,7 +378,21 @@ impl Cerebrum {
         p.mutation_cnt += counter.execs;
         p.source_timeout_cnt += counter.source_timeout;
         p.yield_cnt += counter.edges_found + counter.hits_found;
-        // //TODO: Process phase/mutator/... for, e.g., mutator statstics
+
+        let m = self.mutator_stats.entry(cfg.mutator).or_default();
+        m.source_crash_cnt += counter.source_crashes;
+        m.mutation_cnt += counter.execs;
+        m.source_timeout_cnt += counter.source_timeout;
+        m.yield_cnt += counter.edges_found + counter.hits_found;
+
+        let m = self
+            .phase_mutator_stats
+            .entry((cfg.phase, cfg.mutator))
+            .or_default();
+        m.source_crash_cnt += counter.source_crashes;
+        m.mutation_cnt += counter.execs;
+        m.source_timeout_cnt += counter.source_timeout;
+        m.yield_cnt += counter.edges_found + counter.hits_found;
     }
 }
 
@@ -1,6 +1,11 @@
 #![allow(unused)]
 
-use super::cerebrum::Cerebrum;
+use super::{
+    bandit::{self, ArmStats},
+    cerebrum::Cerebrum,
+    mutators::MutatorType,
+    phases::FuzzingPhase,
+};
 use crate::{fuzzer::queue::QueueEntry, mutation_site::MutationSite, trace::Trace};
 use fuzztruction_shared::{
     mutation_cache::MutationCache, mutation_cache_entry::MutationCacheEntry, types::MutationSiteID,
@@ -43,6 +48,63 @@ impl CerebrumQuery<'_> {
             .collect()
     }
 
+    /// Get the number of yields and mutations of each [MutatorType] that was used so far.
+    pub fn mutator_yields(&self) -> HashMap<MutatorType, (u64, u64)> {
+        self.cerebrum
+            .mutator_stats
+            .iter()
+            .map(|(mutator, stats)| (*mutator, (stats.yield_cnt, stats.mutation_cnt)))
+            .collect()
+    }
+
+    /// Get the number of yields and mutations of each [MutatorType] per [FuzzingPhase].
+    pub fn phase_mutator_yields(&self) -> HashMap<(FuzzingPhase, MutatorType), (u64, u64)> {
+        self.cerebrum
+            .phase_mutator_stats
+            .iter()
+            .map(|(key, stats)| (*key, (stats.yield_cnt, stats.mutation_cnt)))
+            .collect()
+    }
+
+    /// Get the probability of each mutator in `mutators` to be selected during
+    /// `phase`, according to the yields observed so far. The probabilities are
+    /// in the same order as `mutators`.
+    pub fn mutator_probabilities(&self, phase: FuzzingPhase, mutators: &[MutatorType]) -> Vec<f64> {
+        let arms = mutators
+            .iter()
+            .map(|mutator| {
+                self.cerebrum
+                    .phase_mutator_stats
+                    .get(&(phase, *mutator))
+                    .map(|stats| ArmStats {
+                        yields: stats.yield_cnt,
+                        execs: stats.mutation_cnt,
+                    })
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        bandit::probabilities(&arms)
+    }
+
+    /// Get the probabilities learned for all mutators that were used so far,
+    /// grouped by the phase they were used in.
+    pub fn learned_mutator_probabilities(&self) -> HashMap<FuzzingPhase, Vec<(MutatorType, f64)>> {
+        let mut mutators_per_phase = HashMap::<_, Vec<_>>::new();
+        for (phase, mutator) in self.cerebrum.phase_mutator_stats.keys() {
+            mutators_per_phase.entry(*phase).or_default().push(*mutator);
+        }
+
+        mutators_per_phase
+            .into_iter()
+            .map(|(phase, mutators)| {
+                let probabilities = self.mutator_probabilities(phase, &mutators);
+                let mut ret = mutators.into_iter().zip(probabilities).collect::<Vec<_>>();
+                ret.sort_by(|a, b| b.1.total_cmp(&a.1));
+                (phase, ret)
+            })
+            .collect()
+    }
+
     pub fn patch_point_yield_prop(&self) -> HashMap<MutationSiteID, f64> {
         todo!();
     }
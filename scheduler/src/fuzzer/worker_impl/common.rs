@@ -16,6 +16,7 @@ use sha2::{Digest, Sha256};
 use crate::{
     constants::AVG_EXECUTION_TIME_STABILIZATION_VALUE,
     fuzzer::{common::common_trace, queue::QueueEntry, worker::FuzzingWorker},
+    sanitizer::SanitizerReport,
     sink_bitmap::{Bitmap, BitmapStatus},
     trace::Trace,
 };
@@ -78,28 +79,31 @@ impl FuzzingWorker {
     }
 
     /// Store `sink_input` in the `crashing` directory using its SHA256
-    /// hash and the signal name as filename.
+    /// hash and the signal name as filename. If the sink produced a sanitizer
+    /// report, the crash is sorted into its bucket. Returns whether the crash
+    /// is a new bug, i.e., opened a new bucket.
     pub(super) fn save_crashing_input_and_asan_ubsan_report(
         &mut self,
         sink_input: &[u8],
         signal: Signal,
         qe: Option<Arc<QueueEntry>>,
-    ) {
-        let queue_entry_id = qe
-            .map(|q| q.id().0.to_string())
-            .unwrap_or("none".to_owned());
+    ) -> bool {
+        let queue_entry_id = qe.map(|q| q.id());
         let sha256_digest = get_slice_digest(sink_input);
 
         let stats_lock = self.stats.lock().unwrap();
         let ts = stats_lock.init_ts;
         mem::drop(stats_lock);
 
+        let ts_ms = ts.unwrap().elapsed().as_millis();
         let mut path = self.crashing_inputs.clone();
         let prefix = format!(
             "ts:{}+hash:{}+queue_entry:{}+sig:{}",
-            ts.unwrap().elapsed().as_millis(),
+            ts_ms,
             sha256_digest,
-            queue_entry_id,
+            queue_entry_id
+                .map(|id| id.0.to_string())
+                .unwrap_or("none".to_owned()),
             signal
         );
         let name = format!("{}.input", prefix);
@@ -107,18 +111,45 @@ impl FuzzingWorker {
         fs::write(&path, sink_input).unwrap();
 
         let sink = self.sink.as_mut().unwrap();
+        let mut is_new_bug = false;
         if let Some(report_content) = sink.get_latest_asan_report() {
             let mut report_path = self.asan_reports.clone();
             let name = format!("{}.asan", prefix);
             report_path.push(name);
             fs::write(report_path, &report_content).unwrap();
 
-            let symbolized_report = symbolize_report(report_content);
+            let symbolized_report = symbolize_report(&report_content);
 
             let report_symbolized = format!("{}.asan_symbolized", prefix);
             let mut path = self.asan_reports.clone();
             path.push(report_symbolized);
-            fs::write(path, symbolized_report).unwrap();
+            fs::write(path, &symbolized_report).unwrap();
+
+            // Prefer the symbolized report, since its frames are more likely to
+            // carry function names.
+            let report = [&symbolized_report, &report_content]
+                .into_iter()
+                .find_map(|report| Some((SanitizerReport::parse(report)?, report)));
+            if let Some((report, raw_report)) = report {
+                let mut crash_triage = self.crash_triage.lock().unwrap();
+                match crash_triage.add(
+                    &report,
+                    raw_report,
+                    sink_input,
+                    &prefix,
+                    queue_entry_id,
+                    ts_ms as u64,
+                ) {
+                    Ok((bucket, true)) => {
+                        log::info!("Crash is a new bug: bucket={bucket}");
+                        is_new_bug = true;
+                    }
+                    Ok((bucket, false)) => log::info!("Crash is a duplicate: bucket={bucket}"),
+                    Err(err) => log::error!("Failed to triage crash: {err:#}"),
+                }
+            } else {
+                log::warn!("Failed to parse the sanitizer report of crash {prefix}");
+            }
         }
         // if let Some(report_content) = sink.get_latest_ubsan_report() {
         //     let mut path = self.ubsan_reports.clone();
@@ -133,6 +164,8 @@ impl FuzzingWorker {
         //     path.push(name);
         //     fs::write(path, symbolized_report).unwrap();
         // }
+
+        is_new_bug
     }
 
     /// Trace the given `QueueEntry` if it does not contain a trace.
@@ -214,7 +247,7 @@ impl FuzzingWorker {
     }
 }
 
-fn symbolize_report(report: String) -> String {
+fn symbolize_report(report: &str) -> String {
     let mut cmd = Command::new("python3");
     cmd.args([
         "/home/user/fuzztruction/lib/asan_symbolize.py",
@@ -1,9 +1,15 @@
 use anyhow::Result;
 use fuzztruction_shared::util::ExpectNone;
-use std::{cell::RefCell, fs, process};
+use std::{
+    cell::RefCell,
+    fs, process,
+    sync::{Arc, Mutex},
+};
 
 use crate::{
     constants::AVG_EXECUTION_TIME_STABILIZATION_VALUE,
+    custom_mutator::CustomMutatorPlugin,
+    dictionary::Dictionary,
     fuzzer::{
         common::{common_calibrate, InputType},
         queue::{Input, QueueEntry},
@@ -50,6 +56,17 @@ impl FuzzingWorker {
         self.sink.as_mut().unwrap().start()?;
         self.resize_bitmaps();
 
+        if self.config.phases.dictionary.weight > 0 {
+            let sink_tokens = self.sink.as_ref().unwrap().dictionary_tokens();
+            self.dictionary = Arc::new(Dictionary::from_config(&self.config, sink_tokens)?);
+        }
+
+        for path in self.config.phases.mutate.custom_mutators.iter() {
+            let plugin = CustomMutatorPlugin::load(path, rand::random())?;
+            log::info!("Loaded custom mutator {} from {:?}", plugin.name(), path);
+            self.custom_mutators.push(Arc::new(Mutex::new(plugin)));
+        }
+
         Ok(())
     }
 
@@ -111,6 +128,27 @@ impl FuzzingWorker {
             let _ = cerebrum_guard.insert(Cerebrum::new(&patch_points, self.queue.clone()));
             drop(cerebrum_guard);
 
+            // The queue is only populated at this point if the campaign was resumed.
+            if !self.queue.lock().unwrap().is_empty() {
+                if let Err(e) = self.restore_shared_state() {
+                    self.queue_init_failed_flag
+                        .store(true, std::sync::atomic::Ordering::SeqCst);
+                    error = Some(e);
+                }
+                return;
+            }
+
+            if let Some(prior) = self.config.general.cerebrum_prior.clone() {
+                let mut cerebrum_guard = self.cerebrum.write().unwrap();
+                let cerebrum = cerebrum_guard.as_mut().unwrap();
+                if let Err(e) = cerebrum.import_prior(&prior) {
+                    self.queue_init_failed_flag
+                        .store(true, std::sync::atomic::Ordering::SeqCst);
+                    error = Some(e.context("Failed to import the cerebrum prior"));
+                    return;
+                }
+            }
+
             let success = self.calibrate_seed_files();
             if let Err(e) = success {
                 // Notify the other threads that the initialization failed.
@@ -145,6 +183,43 @@ impl FuzzingWorker {
         Ok(())
     }
 
+    /// Restore the shared state of a resumed campaign, i.e., the virgin maps and
+    /// the cerebrum, from the queue and the statistics dumped by the previous run.
+    fn restore_shared_state(&mut self) -> Result<()> {
+        let entries = self.queue.lock().unwrap().entries();
+        log::info!("Resuming campaign with {} queue entries", entries.len());
+
+        let mut virgin_map = self.shared_virgin_map.lock().unwrap();
+        let mut crash_virgin_map = self.shared_crash_virgin_map.lock().unwrap();
+        for entry in entries.iter() {
+            let map = if entry.is_crash() {
+                &mut crash_virgin_map
+            } else {
+                &mut virgin_map
+            };
+            map.mark_edges_seen(&entry.covered_edges());
+        }
+        drop(virgin_map);
+        drop(crash_virgin_map);
+
+        let mut cerebrum_guard = self.cerebrum.write().unwrap();
+        let cerebrum = cerebrum_guard.as_mut().unwrap();
+        for entry in entries {
+            cerebrum.report_new_qe(entry);
+        }
+        let stats_path = self.config.general.cerebrum_stats_path();
+        if stats_path.exists() {
+            cerebrum.restore_stats(&stats_path)?;
+        } else {
+            log::warn!(
+                "{:?} does not exist, the cerebrum statistics are not restored",
+                stats_path
+            );
+        }
+
+        Ok(())
+    }
+
     /// Run the calibration for all seed files in the input directory and
     /// return a vector of QueueEntries.
     ///
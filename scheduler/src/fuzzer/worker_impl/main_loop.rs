@@ -160,6 +160,9 @@ impl FuzzingWorker {
             Some((.., FuzzingPhase::Combine)) => {
                 self.do_combine_phase()?;
             }
+            Some((.., FuzzingPhase::Dictionary)) => {
+                self.do_dictionary_phase()?;
+            }
             Some(phase) => panic!("Unknown phase: {:?}", phase),
             None => {
                 log::info!("No FuzzingPhases to choose from");
@@ -183,6 +186,13 @@ impl FuzzingWorker {
             let choice = (self.config.phases.combine.weight, FuzzingPhase::Combine);
             choices.push(choice);
         }
+        if !self.is_phase_done(FuzzingPhase::Dictionary) {
+            let choice = (
+                self.config.phases.dictionary.weight,
+                FuzzingPhase::Dictionary,
+            );
+            choices.push(choice);
+        }
         choices
     }
 }
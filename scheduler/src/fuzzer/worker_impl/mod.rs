@@ -1,3 +1,4 @@
+mod bandit;
 mod cerebrum;
 pub use cerebrum::Cerebrum;
 mod cerebrum_query;
@@ -1,11 +1,23 @@
-use rand::{self, Rng};
+use fuzztruction_shared::mutation_cache_entry::{MutationCacheEntry, MutationOperator};
+use itertools::Itertools;
+use rand::{self, seq::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 use std::{
-    cmp, fmt,
-    sync::Arc,
+    cmp,
+    collections::BTreeSet,
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
+use crate::{
+    custom_mutator::{CustomMutatorPlugin, SiteInfo},
+    dictionary,
+    fuzzer::queue::QueueEntry,
+    mutation_site::CallFaultKind,
+};
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum MutatorType {
     Nop,
@@ -17,6 +29,56 @@ pub enum MutatorType {
     U8Counter,
     Havoc,
     Combine,
+    RandomChunk(MutationOperator),
+    HitSelect,
+    BranchForce,
+    CallFault,
+    Dictionary,
+    InterestingValue,
+    Arithmetic,
+    Custom(CustomMutatorName),
+}
+
+/// The name of a [CustomMutatorPlugin]. Names are interned, thus [MutatorType]
+/// stays [Copy] even though the names are only known at runtime.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CustomMutatorName(&'static str);
+
+impl CustomMutatorName {
+    pub fn new(name: &str) -> CustomMutatorName {
+        static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+        let mut names = NAMES.lock().unwrap();
+        if let Some(name) = names.get(name) {
+            return CustomMutatorName(name);
+        }
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        names.insert(name);
+        CustomMutatorName(name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Debug for CustomMutatorName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl Serialize for CustomMutatorName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for CustomMutatorName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CustomMutatorName::new(&name))
+    }
 }
 
 /// A Mutator is a collection of multiple mutations that are consecutively
@@ -39,6 +101,12 @@ pub trait Mutator: Iterator + fmt::Debug {
         false
     }
 
+    /// The operator the mutated [MutationCacheEntry] must use while this
+    /// [Mutator] is executed. If None, the operator of the entry is not changed.
+    fn operator(&self) -> Option<MutationOperator> {
+        None
+    }
+
     fn estimate_runtime(&self, avg_exec_time: Duration) -> Duration {
         avg_exec_time.saturating_mul(self.steps_total().try_into().unwrap_or(u32::MAX))
     }
@@ -90,90 +158,898 @@ where
             self.start_ts = Some(Instant::now());
         }
 
-        if self.start_ts.unwrap().elapsed() > self.timeout {
-            log::debug!(
-                "Mutator {:?} exceeded timeout of {:?}",
-                self.mutator.mutator_type(),
-                self.timeout
-            );
-            return None;
+        if self.start_ts.unwrap().elapsed() > self.timeout {
+            log::debug!(
+                "Mutator {:?} exceeded timeout of {:?}",
+                self.mutator.mutator_type(),
+                self.timeout
+            );
+            return None;
+        }
+
+        self.mutator.next()
+    }
+}
+
+impl<T: Mutator> Mutator for WithTimeout<T> {
+    fn mutator_type(&self) -> MutatorType {
+        self.mutator.mutator_type()
+    }
+
+    fn steps_total(&self) -> usize {
+        self.mutator.steps_total()
+    }
+
+    fn steps_done(&self) -> usize {
+        self.mutator.steps_done()
+    }
+
+    fn needs_sync(&self) -> bool {
+        self.mutator.needs_sync()
+    }
+
+    fn one_shot(&self) -> bool {
+        self.mutator.one_shot()
+    }
+
+    fn operator(&self) -> Option<MutationOperator> {
+        self.mutator.operator()
+    }
+
+    fn estimate_runtime(&self, avg_exec_time: Duration) -> Duration {
+        self.mutator.estimate_runtime(avg_exec_time)
+    }
+
+    fn steps_left(&self) -> usize {
+        self.mutator.steps_left()
+    }
+}
+
+pub struct RandomBit<'a, const N: usize> {
+    /// The buffer that is mutated.
+    buffer: &'a mut [u8],
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// Index of the last byte we mutated.
+    last_idx: Option<usize>,
+    /// The original value of the byte that we mutated.
+    saved_byte: u8,
+}
+
+impl<const N: usize> RandomBit<'_, N> {
+    /// Create a new mutator. Returns None, if the backing buffer is smaller than N.
+    #[allow(unused)]
+    pub fn new(buffer: &mut [u8], steps: usize) -> RandomBit<N> {
+        assert!(N == 1, "Currently only 1 is supported for N");
+        RandomBit {
+            buffer,
+            current_step: 0,
+            max_step: steps,
+            last_idx: None,
+            saved_byte: 0,
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for RandomBit<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("RandomBit{}", N))
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("last_idx", &self.last_idx)
+            .field("saved_byte", &self.saved_byte)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const N: usize> Mutator for RandomBit<'_, N> {
+    fn steps_total(&self) -> usize {
+        self.max_step
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::RandomBit(N)
+    }
+}
+
+impl<const N: usize> Iterator for RandomBit<'_, N> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx] = self.saved_byte;
+        }
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        // Mutate
+        let mut rng = rand::thread_rng();
+        let target_idx = rng.gen_range(0..(self.buffer.len()));
+        self.saved_byte = self.buffer[target_idx];
+        self.buffer[target_idx] ^= 1 << (rng.gen::<u8>() % 8);
+        self.last_idx = Some(target_idx);
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl<const N: usize> Drop for RandomBit<'_, N> {
+    fn drop(&mut self) {
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx] = self.saved_byte;
+        }
+    }
+}
+
+/// A Mutator that replaces a byte at a random index with a random value.
+pub struct RandomByte<'a, const N: usize> {
+    /// The buffer that is mutated.
+    buffer: &'a mut [u8],
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// Index of the last byte we mutated.
+    last_idx: Option<usize>,
+    /// The original value of the byte that we mutated.
+    saved_bytes: [u8; N],
+}
+
+impl<const N: usize> RandomByte<'_, N> {
+    /// Create a new mutator. Returns None, if the backing buffer is smaller than N.
+    pub fn new(buffer: &mut [u8], steps: usize) -> Option<RandomByte<N>> {
+        if buffer.len() < N {
+            return None;
+        }
+
+        Some(RandomByte {
+            buffer,
+            current_step: 0,
+            max_step: steps,
+            last_idx: None,
+            saved_bytes: [0; N],
+        })
+    }
+}
+
+impl<const N: usize> fmt::Debug for RandomByte<'_, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("RandomByte{}", N))
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("last_idx", &self.last_idx)
+            .field("saved_bytes", &self.saved_bytes)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<const N: usize> Mutator for RandomByte<'_, N> {
+    fn steps_total(&self) -> usize {
+        self.max_step
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::RandomByte(N)
+    }
+}
+
+impl<const N: usize> Iterator for RandomByte<'_, N> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx..(idx + N)].copy_from_slice(&self.saved_bytes);
+        }
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        // Mutate
+        let mut rng = rand::thread_rng();
+        let last_idx = rng.gen_range(0..=(self.buffer.len() - N));
+        self.saved_bytes
+            .copy_from_slice(&self.buffer[last_idx..(last_idx + N)]);
+
+        self.buffer[last_idx..(last_idx + N)]
+            .iter_mut()
+            .for_each(|e| *e ^= rng.gen::<u8>());
+        self.last_idx = Some(last_idx);
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl<const N: usize> Drop for RandomByte<'_, N> {
+    fn drop(&mut self) {
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx..(idx + N)].copy_from_slice(&self.saved_bytes);
+        }
+    }
+}
+
+pub type RandomByte1<'a> = RandomByte<'a, 1>;
+#[allow(unused)]
+pub type RandomByte2<'a> = RandomByte<'a, 2>;
+pub type RandomByte4<'a> = RandomByte<'a, 4>;
+
+/// Largest delta used by [RandomChunk] for [MutationOperator::Add] and [MutationOperator::Sub]
+/// and by [TracedValue::arithmetic].
+const ARITH_MAX: u64 = 35;
+
+/// A Mutator that writes a value into a random chunk of the mask that is applied
+/// via `operator`, e.g., a small delta in case of [MutationOperator::Add] or a
+/// boundary value in case of [MutationOperator::Set].
+pub struct RandomChunk<'a> {
+    /// The buffer that is mutated.
+    buffer: &'a mut [u8],
+    /// Size of the chunks the mask is applied in.
+    chunk_size: usize,
+    operator: MutationOperator,
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// Index of the last chunk we mutated.
+    last_idx: Option<usize>,
+    /// The original value of the chunk that we mutated.
+    saved_chunk: [u8; 8],
+}
+
+impl RandomChunk<'_> {
+    /// Create a new mutator. Returns None, if the chunk size is not 1, 2, 4, or 8 byte
+    /// or the buffer does not contain at least one chunk.
+    pub fn new(
+        buffer: &mut [u8],
+        chunk_size: usize,
+        operator: MutationOperator,
+        steps: usize,
+    ) -> Option<RandomChunk> {
+        if ![1, 2, 4, 8].contains(&chunk_size) || buffer.len() < chunk_size {
+            return None;
+        }
+
+        Some(RandomChunk {
+            buffer,
+            chunk_size,
+            operator,
+            current_step: 0,
+            max_step: steps,
+            last_idx: None,
+            saved_chunk: [0; 8],
+        })
+    }
+
+    fn restore(&mut self) {
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx..(idx + self.chunk_size)]
+                .copy_from_slice(&self.saved_chunk[..self.chunk_size]);
+        }
+    }
+
+    /// A non zero value (zero chunks are not applied) that is meaningful for `self.operator`.
+    fn gen_value(&self, rng: &mut impl Rng) -> u64 {
+        let bits = self.chunk_size as u32 * 8;
+        let all_ones = u64::MAX >> (64 - bits);
+        match self.operator {
+            MutationOperator::Add | MutationOperator::Sub => rng.gen_range(1..=ARITH_MAX),
+            MutationOperator::Or | MutationOperator::AndNot => 1u64 << rng.gen_range(0..bits),
+            MutationOperator::Set => match rng.gen_range(0..5) {
+                0 => 1,
+                1 => all_ones,
+                2 => all_ones >> 1,
+                3 => 1 << (bits - 1),
+                _ => rng.gen_range(1..=all_ones),
+            },
+            MutationOperator::Xor | MutationOperator::Force => rng.gen_range(1..=all_ones),
+        }
+    }
+}
+
+impl fmt::Debug for RandomChunk<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomChunk")
+            .field("chunk_size", &self.chunk_size)
+            .field("operator", &self.operator)
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("last_idx", &self.last_idx)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mutator for RandomChunk<'_> {
+    fn steps_total(&self) -> usize {
+        self.max_step
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::RandomChunk(self.operator)
+    }
+
+    fn operator(&self) -> Option<MutationOperator> {
+        Some(self.operator)
+    }
+}
+
+impl Iterator for RandomChunk<'_> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        self.restore();
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        // Mutate
+        let mut rng = rand::thread_rng();
+        let chunk_size = self.chunk_size;
+        let idx = rng.gen_range(0..(self.buffer.len() / chunk_size)) * chunk_size;
+        self.saved_chunk[..chunk_size].copy_from_slice(&self.buffer[idx..(idx + chunk_size)]);
+
+        let value = self.gen_value(&mut rng);
+        self.buffer[idx..(idx + chunk_size)].copy_from_slice(&value.to_le_bytes()[..chunk_size]);
+        self.last_idx = Some(idx);
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl Drop for RandomChunk<'_> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// A Mutator that only mutates a single, randomly selected execution of a patch
+/// point. For this, the hit filter of the entry is set to the selected hit and
+/// a random value is written into the first chunk of the mask, which is the
+/// only chunk consumed while the filter is set.
+pub struct HitSelect<'a> {
+    entry: &'a mut MutationCacheEntry,
+    /// Number of times the patch point of `entry` is executed.
+    hits: u32,
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// The filter of `entry` before this mutator was created.
+    saved_filter: Option<Range<u32>>,
+    /// The original value of the first chunk.
+    saved_chunk: Vec<u8>,
+}
+
+impl HitSelect<'_> {
+    /// Create a new mutator for `entry`, whose patch point is executed `hits` times.
+    /// Returns None, if there is only a single hit to choose from or the mask
+    /// does not contain at least one chunk.
+    pub fn new(entry: &mut MutationCacheEntry, hits: u64, steps: usize) -> Option<HitSelect> {
+        let chunk_size = entry.chunk_size_bytes() as usize;
+        let hits = hits.try_into().unwrap_or(u32::MAX);
+        if hits < 2 || entry.get_msk_as_slice().len() < chunk_size {
+            return None;
+        }
+
+        let saved_filter = entry.hit_filter();
+        let saved_chunk = entry.get_msk_as_slice()[..chunk_size].to_vec();
+        Some(HitSelect {
+            entry,
+            hits,
+            current_step: 0,
+            max_step: steps,
+            saved_filter,
+            saved_chunk,
+        })
+    }
+
+    fn restore(&mut self) {
+        self.entry.set_hit_filter(self.saved_filter.clone());
+        let chunk_size = self.saved_chunk.len();
+        self.entry.get_msk_as_slice()[..chunk_size].copy_from_slice(&self.saved_chunk);
+    }
+}
+
+impl fmt::Debug for HitSelect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HitSelect")
+            .field("entry.id()", &self.entry.id())
+            .field("hits", &self.hits)
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("saved_filter", &self.saved_filter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mutator for HitSelect<'_> {
+    fn steps_total(&self) -> usize {
+        self.max_step
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::HitSelect
+    }
+}
+
+impl Iterator for HitSelect<'_> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        self.restore();
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let hit = rng.gen_range(0..self.hits);
+        self.entry.set_hit_filter(Some(hit..(hit + 1)));
+
+        // The bits covered by the chunk must not be all zero, since applying
+        // the chunk would not change anything otherwise.
+        let bits = self.entry.chunk_size_bits().min(64) as u32;
+        let value: u64 = rng.gen_range(1..=(u64::MAX >> (64 - bits)));
+        let chunk = &mut self.entry.get_msk_as_slice()[..self.saved_chunk.len()];
+        rng.fill(&mut *chunk);
+        let len = chunk.len().min(8);
+        chunk[..len].copy_from_slice(&value.to_le_bytes()[..len]);
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl Drop for HitSelect<'_> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// A Mutator that forces a `Br`, `Select`, or `Switch` instruction to take a
+/// specific successor. It must be used with [MutationOperator::Force], where each
+/// chunk of the mask selects the successor of one hit. Each step either forces
+/// a single, random hit or all hits to a random successor. The mask is zeroed
+/// before each step, since chunks of an XOR mask would be misinterpreted as
+/// successors, and it is restored afterwards.
+pub struct BranchForce<'a> {
+    /// The buffer that is mutated.
+    buffer: &'a mut [u8],
+    /// Size of the chunks the mask is applied in.
+    chunk_size: usize,
+    /// The chunk values that select each successor.
+    chunks: Vec<u64>,
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// The range of the buffer we mutated.
+    last_range: Option<Range<usize>>,
+    /// The original content of the buffer.
+    saved_buffer: Vec<u8>,
+}
+
+impl BranchForce<'_> {
+    /// Create a new mutator. `successor_values` are the condition values that select
+    /// the successors of the instruction (see [crate::mutation_site::MutationSite]).
+    /// Returns None, if there are no successors that can be forced, the chunk size is
+    /// not 1, 2, 4, or 8 byte, or the buffer does not contain at least one chunk.
+    pub fn new<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        successor_values: &[u64],
+        steps: usize,
+    ) -> Option<BranchForce<'a>> {
+        if !chunk_size.is_power_of_two() || chunk_size > 8 || buffer.len() < chunk_size {
+            return None;
+        }
+
+        // A chunk is applied as `chunk - 1`, thus values that would overflow the
+        // chunk can not be forced.
+        let max_chunk = u64::MAX >> (64 - chunk_size * 8);
+        let chunks = successor_values
+            .iter()
+            .filter(|value| **value < max_chunk)
+            .map(|value| value + 1)
+            .collect::<Vec<_>>();
+        if chunks.is_empty() {
+            return None;
+        }
+
+        let saved_buffer = buffer.to_vec();
+        Some(BranchForce {
+            buffer,
+            chunk_size,
+            chunks,
+            current_step: 0,
+            max_step: steps,
+            last_range: None,
+            saved_buffer,
+        })
+    }
+
+    fn restore(&mut self) {
+        if let Some(range) = self.last_range.take() {
+            self.buffer[range.clone()].copy_from_slice(&self.saved_buffer[range]);
+        }
+    }
+}
+
+impl fmt::Debug for BranchForce<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BranchForce")
+            .field("chunk_size", &self.chunk_size)
+            .field("chunks", &self.chunks)
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("last_range", &self.last_range)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Mutator for BranchForce<'_> {
+    fn steps_total(&self) -> usize {
+        self.max_step
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::BranchForce
+    }
+
+    fn operator(&self) -> Option<MutationOperator> {
+        Some(MutationOperator::Force)
+    }
+}
+
+impl Iterator for BranchForce<'_> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        self.restore();
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let chunk_size = self.chunk_size;
+        let hits = self.buffer.len() / chunk_size;
+        let chunk = self.chunks[rng.gen_range(0..self.chunks.len())].to_le_bytes();
+
+        let range = if rng.gen_bool(0.5) {
+            let idx = rng.gen_range(0..hits) * chunk_size;
+            idx..(idx + chunk_size)
+        } else {
+            0..(hits * chunk_size)
+        };
+        // Zero chunks leave the other hits untouched.
+        self.buffer.fill(0);
+        self.buffer[range]
+            .chunks_exact_mut(chunk_size)
+            .for_each(|c| c.copy_from_slice(&chunk[..chunk_size]));
+        self.last_range = Some(0..self.buffer.len());
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl Drop for BranchForce<'_> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// The values errno is set to if a call is faulted. These are errors callers of
+/// allocators, I/O, and networking functions are expected to handle.
+const FAULT_ERRNOS: [i32; 10] = [
+    libc::EINTR,
+    libc::EAGAIN,
+    libc::EIO,
+    libc::ENOMEM,
+    libc::ECONNRESET,
+    libc::EPIPE,
+    libc::ENOSPC,
+    libc::EBADF,
+    libc::EINVAL,
+    libc::ETIMEDOUT,
+];
+
+/// A Mutator that lets calls fail by replacing their return value, i.e.,
+/// pointers are set to NULL via [MutationOperator::Force] and integers are set
+/// to -1 or a short count via [MutationOperator::Set]. If the return value is
+/// -1, errno is set via the mask of the errno site of the same call, if any.
+/// Each step either faults a single, random hit or all hits of the call.
+pub struct CallFault<'a> {
+    /// The buffer of the return value site that is mutated.
+    buffer: &'a mut [u8],
+    /// Size of the chunks the mask is applied in.
+    chunk_size: usize,
+    kind: CallFaultKind,
+    /// The buffer of the errno site, which is applied via XOR on a zero value.
+    errno_buffer: Option<&'a mut [u8]>,
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// Maximum number of iterations.
+    max_step: usize,
+    /// The range of hits we mutated.
+    last_hits: Option<Range<usize>>,
+    /// The original content of the buffers.
+    saved_buffer: Vec<u8>,
+    saved_errno_buffer: Vec<u8>,
+}
+
+impl CallFault<'_> {
+    /// Size of the chunks of an errno site, i.e., the size of an int.
+    const ERRNO_CHUNK_SIZE: usize = 4;
+
+    /// Create a new mutator for the return value site `buffer` of the given `kind`.
+    /// Returns None, if `kind` is not a return value, the chunk size is not 1, 2, 4,
+    /// or 8 byte, or the buffer does not contain at least one chunk.
+    pub fn new<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        kind: CallFaultKind,
+        errno_buffer: Option<&'a mut [u8]>,
+        steps: usize,
+    ) -> Option<CallFault<'a>> {
+        if kind == CallFaultKind::Errno
+            || ![1, 2, 4, 8].contains(&chunk_size)
+            || buffer.len() < chunk_size
+        {
+            return None;
+        }
+
+        let errno_buffer = errno_buffer.filter(|b| b.len() >= Self::ERRNO_CHUNK_SIZE);
+        let saved_buffer = buffer.to_vec();
+        let saved_errno_buffer = errno_buffer
+            .as_ref()
+            .map(|b| b.to_vec())
+            .unwrap_or_default();
+        Some(CallFault {
+            buffer,
+            chunk_size,
+            kind,
+            errno_buffer,
+            current_step: 0,
+            max_step: steps,
+            last_hits: None,
+            saved_buffer,
+            saved_errno_buffer,
+        })
+    }
+
+    fn restore(&mut self) {
+        if let Some(hits) = self.last_hits.take() {
+            let range = (hits.start * self.chunk_size)..(hits.end * self.chunk_size);
+            self.buffer[range.clone()].copy_from_slice(&self.saved_buffer[range]);
+            if let Some(errno_buffer) = self.errno_buffer.as_mut() {
+                errno_buffer.copy_from_slice(&self.saved_errno_buffer);
+            }
         }
+    }
 
-        self.mutator.next()
+    /// The chunk of the faulted return value and the errno that is set.
+    fn gen_fault(&self, rng: &mut impl Rng) -> (u64, i32) {
+        let all_ones = u64::MAX >> (64 - self.chunk_size * 8);
+        match self.kind {
+            // Force applies `chunk - 1`, i.e., NULL.
+            CallFaultKind::ReturnPointer => (1, 0),
+            CallFaultKind::ReturnInteger if rng.gen_bool(0.5) => {
+                (all_ones, *FAULT_ERRNOS.choose(rng).unwrap())
+            }
+            // A short count, e.g., of a read or write that only processed a few bytes.
+            CallFaultKind::ReturnInteger => (rng.gen_range(1..=ARITH_MAX.min(all_ones)), 0),
+            CallFaultKind::Errno => unreachable!(),
+        }
     }
 }
 
-impl<T: Mutator> Mutator for WithTimeout<T> {
-    fn mutator_type(&self) -> MutatorType {
-        self.mutator.mutator_type()
+impl fmt::Debug for CallFault<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CallFault")
+            .field("chunk_size", &self.chunk_size)
+            .field("kind", &self.kind)
+            .field("has_errno", &self.errno_buffer.is_some())
+            .field("current_step", &self.current_step)
+            .field("max_step", &self.max_step)
+            .field("last_hits", &self.last_hits)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
     }
+}
 
+impl Mutator for CallFault<'_> {
     fn steps_total(&self) -> usize {
-        self.mutator.steps_total()
+        self.max_step
     }
 
     fn steps_done(&self) -> usize {
-        self.mutator.steps_done()
+        self.current_step
     }
 
-    fn needs_sync(&self) -> bool {
-        self.mutator.needs_sync()
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::CallFault
     }
 
-    fn one_shot(&self) -> bool {
-        self.mutator.one_shot()
+    fn operator(&self) -> Option<MutationOperator> {
+        match self.kind {
+            CallFaultKind::ReturnPointer => Some(MutationOperator::Force),
+            _ => Some(MutationOperator::Set),
+        }
     }
+}
 
-    fn estimate_runtime(&self, avg_exec_time: Duration) -> Duration {
-        self.mutator.estimate_runtime(avg_exec_time)
+impl Iterator for CallFault<'_> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Revert previous mutation.
+        self.restore();
+
+        if self.current_step == self.max_step {
+            // No steps left
+            return None;
+        }
+
+        let mut rng = rand::thread_rng();
+        let chunk_size = self.chunk_size;
+        let hits = self.buffer.len() / chunk_size;
+        let (chunk, errno) = self.gen_fault(&mut rng);
+
+        let hit_range = if rng.gen_bool(0.5) {
+            let hit = rng.gen_range(0..hits);
+            hit..(hit + 1)
+        } else {
+            0..hits
+        };
+        self.buffer[(hit_range.start * chunk_size)..(hit_range.end * chunk_size)]
+            .chunks_exact_mut(chunk_size)
+            .for_each(|c| c.copy_from_slice(&chunk.to_le_bytes()[..chunk_size]));
+
+        // The errno site is executed right after the return value site, thus
+        // both are hit the same number of times.
+        if let Some(errno_buffer) = self.errno_buffer.as_mut() {
+            let errno_chunks = errno_buffer.len() / Self::ERRNO_CHUNK_SIZE;
+            let start = hit_range.start.min(errno_chunks) * Self::ERRNO_CHUNK_SIZE;
+            let end = hit_range.end.min(errno_chunks) * Self::ERRNO_CHUNK_SIZE;
+            errno_buffer[start..end]
+                .chunks_exact_mut(Self::ERRNO_CHUNK_SIZE)
+                .for_each(|c| c.copy_from_slice(&errno.to_le_bytes()));
+        }
+        self.last_hits = Some(hit_range);
+
+        self.current_step += 1;
+        Some(())
     }
+}
 
-    fn steps_left(&self) -> usize {
-        self.mutator.steps_left()
+impl Drop for CallFault<'_> {
+    fn drop(&mut self) {
+        self.restore();
     }
 }
 
-pub struct RandomBit<'a, const N: usize> {
+/// A Mutator that overlays a token onto consecutive hits of a patch point. The token
+/// is split into chunks of the patched value's size, and each chunk is XORed with
+/// the value observed during tracing. Applied via [MutationOperator::Xor], the
+/// patched values thus spell out the token in memory. Only hits whose value was
+/// traced are mutated, and bytes behind a token's last, partial chunk keep their
+/// original value.
+pub struct Dictionary<'a> {
     /// The buffer that is mutated.
     buffer: &'a mut [u8],
+    /// Size of the chunks the mask is applied in.
+    chunk_size: usize,
+    /// The values observed during the hits that consume the chunks of `buffer`.
+    values: Vec<u64>,
+    tokens: Arc<dictionary::Dictionary>,
     /// Number of iterations we already performed.
     current_step: usize,
     /// Maximum number of iterations.
     max_step: usize,
-    /// Index of the last byte we mutated.
-    last_idx: Option<usize>,
-    /// The original value of the byte that we mutated.
-    saved_byte: u8,
+    /// The range of the buffer we mutated.
+    last_range: Option<Range<usize>>,
+    /// The original content of the buffer.
+    saved_buffer: Vec<u8>,
 }
 
-impl<const N: usize> RandomBit<'_, N> {
-    /// Create a new mutator. Returns None, if the backing buffer is smaller than N.
-    #[allow(unused)]
-    pub fn new(buffer: &mut [u8], steps: usize) -> RandomBit<N> {
-        assert!(N == 1, "Currently only 1 is supported for N");
-        RandomBit {
+impl Dictionary<'_> {
+    /// Create a new mutator. `values` are the values observed during the hits that
+    /// consume the chunks of `buffer`. Returns None, if there are no tokens, the chunk
+    /// size is not 1, 2, 4, or 8 byte, or no hit with a traced value is covered by
+    /// the buffer.
+    pub fn new<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        values: &[u64],
+        tokens: Arc<dictionary::Dictionary>,
+        steps: usize,
+    ) -> Option<Dictionary<'a>> {
+        if tokens.is_empty() || ![1, 2, 4, 8].contains(&chunk_size) {
+            return None;
+        }
+        let hits = values.len().min(buffer.len() / chunk_size);
+        if hits == 0 {
+            return None;
+        }
+
+        let saved_buffer = buffer.to_vec();
+        Some(Dictionary {
             buffer,
+            chunk_size,
+            values: values[..hits].to_vec(),
+            tokens,
             current_step: 0,
             max_step: steps,
-            last_idx: None,
-            saved_byte: 0,
+            last_range: None,
+            saved_buffer,
+        })
+    }
+
+    fn restore(&mut self) {
+        if let Some(range) = self.last_range.take() {
+            self.buffer[range.clone()].copy_from_slice(&self.saved_buffer[range]);
         }
     }
 }
 
-impl<const N: usize> fmt::Debug for RandomBit<'_, N> {
+impl fmt::Debug for Dictionary<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct(&format!("RandomBit{}", N))
+        f.debug_struct("Dictionary")
+            .field("chunk_size", &self.chunk_size)
+            .field("tokens.len()", &self.tokens.len())
+            .field("values.len()", &self.values.len())
             .field("current_step", &self.current_step)
             .field("max_step", &self.max_step)
-            .field("last_idx", &self.last_idx)
-            .field("saved_byte", &self.saved_byte)
+            .field("last_range", &self.last_range)
             .field("buffer.len()", &self.buffer.len())
             .finish_non_exhaustive()
     }
 }
 
-impl<const N: usize> Mutator for RandomBit<'_, N> {
+impl Mutator for Dictionary<'_> {
     fn steps_total(&self) -> usize {
         self.max_step
     }
@@ -183,90 +1059,202 @@ impl<const N: usize> Mutator for RandomBit<'_, N> {
     }
 
     fn mutator_type(&self) -> MutatorType {
-        MutatorType::RandomBit(N)
+        MutatorType::Dictionary
+    }
+
+    fn operator(&self) -> Option<MutationOperator> {
+        Some(MutationOperator::Xor)
     }
 }
 
-impl<const N: usize> Iterator for RandomBit<'_, N> {
+impl Iterator for Dictionary<'_> {
     type Item = ();
 
     fn next(&mut self) -> Option<Self::Item> {
         // Revert previous mutation.
-        if let Some(idx) = self.last_idx.take() {
-            self.buffer[idx] = self.saved_byte;
-        }
+        self.restore();
 
         if self.current_step == self.max_step {
             // No steps left
             return None;
         }
 
-        // Mutate
         let mut rng = rand::thread_rng();
-        let target_idx = rng.gen_range(0..(self.buffer.len()));
-        self.saved_byte = self.buffer[target_idx];
-        self.buffer[target_idx] ^= 1 << (rng.gen::<u8>() % 8);
-        self.last_idx = Some(target_idx);
+        let chunk_size = self.chunk_size;
+        let hits = self.values.len();
+        let token = self.tokens.tokens().choose(&mut rng).unwrap();
+        // Tokens that span more hits than available are truncated.
+        let token = &token[..token.len().min(hits * chunk_size)];
+        let token_chunks = token.len().div_ceil(chunk_size);
+
+        let first_hit = rng.gen_range(0..=(hits - token_chunks));
+        for (idx, token_chunk) in token.chunks(chunk_size).enumerate() {
+            let hit = first_hit + idx;
+            let observed = self.values[hit].to_le_bytes();
+            let chunk = &mut self.buffer[(hit * chunk_size)..((hit + 1) * chunk_size)];
+            chunk.fill(0);
+            chunk
+                .iter_mut()
+                .zip(observed.iter().zip(token_chunk))
+                .for_each(|(c, (o, t))| *c = o ^ t);
+        }
+        let start = first_hit * chunk_size;
+        self.last_range = Some(start..(start + token_chunks * chunk_size));
 
         self.current_step += 1;
         Some(())
     }
 }
 
-impl<const N: usize> Drop for RandomBit<'_, N> {
+impl Drop for Dictionary<'_> {
     fn drop(&mut self) {
-        if let Some(idx) = self.last_idx.take() {
-            self.buffer[idx] = self.saved_byte;
-        }
+        self.restore();
     }
 }
 
-/// A Mutator that replaces a byte at a random index with a random value.
-pub struct RandomByte<'a, const N: usize> {
+/// A Mutator that turns the values observed during tracing into boundary values
+/// ([TracedValue::interesting_values]) or into values with a small delta
+/// ([TracedValue::arithmetic]). Each step writes `observed ^ target` into the chunk
+/// of a single hit, which is applied via [MutationOperator::Xor] and thus yields
+/// `target`. Values and chunks are little endian, i.e., the mask's bytes are in the
+/// order the value is stored in memory.
+pub struct TracedValue<'a> {
     /// The buffer that is mutated.
     buffer: &'a mut [u8],
+    /// Size of the chunks the mask is applied in, i.e., the width of the value.
+    chunk_size: usize,
+    mutator_type: MutatorType,
+    /// The offset of the chunk in `buffer` and the mask written into it for each step.
+    masks: Vec<(usize, u64)>,
     /// Number of iterations we already performed.
     current_step: usize,
-    /// Maximum number of iterations.
-    max_step: usize,
-    /// Index of the last byte we mutated.
+    /// Index of the last chunk we mutated.
     last_idx: Option<usize>,
-    /// The original value of the byte that we mutated.
-    saved_bytes: [u8; N],
+    /// The original value of the chunk that we mutated.
+    saved_chunk: [u8; 8],
 }
 
-impl<const N: usize> RandomByte<'_, N> {
-    /// Create a new mutator. Returns None, if the backing buffer is smaller than N.
-    pub fn new(buffer: &mut [u8], steps: usize) -> Option<RandomByte<N>> {
-        if buffer.len() < N {
+impl TracedValue<'_> {
+    /// Create a mutator that sets the value of each hit to 0, 1, -1, the signed and
+    /// unsigned extremes, and the powers of two of the value's width. `values` are
+    /// the values observed during the hits that consume the chunks of `buffer`.
+    /// Returns None, if the chunk size is not 1, 2, 4, or 8 byte or there is nothing
+    /// to mutate.
+    pub fn interesting_values<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        values: &[u64],
+        steps: usize,
+    ) -> Option<TracedValue<'a>> {
+        TracedValue::new(
+            buffer,
+            chunk_size,
+            values,
+            steps,
+            MutatorType::InterestingValue,
+            |_, bits| {
+                let all_ones = u64::MAX >> (64 - bits);
+                [0, all_ones, all_ones >> 1, 1 << (bits - 1)]
+                    .into_iter()
+                    .chain((0..bits).map(|shift| 1 << shift))
+                    .collect()
+            },
+        )
+    }
+
+    /// Create a mutator that adds and subtracts deltas of up to [ARITH_MAX] to the
+    /// value of each hit. Results wrap around at the value's width. See
+    /// [TracedValue::interesting_values] for the arguments.
+    pub fn arithmetic<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        values: &[u64],
+        steps: usize,
+    ) -> Option<TracedValue<'a>> {
+        TracedValue::new(
+            buffer,
+            chunk_size,
+            values,
+            steps,
+            MutatorType::Arithmetic,
+            |observed, bits| {
+                let all_ones = u64::MAX >> (64 - bits);
+                (1..=ARITH_MAX)
+                    .flat_map(|delta| [observed.wrapping_add(delta), observed.wrapping_sub(delta)])
+                    .map(|value| value & all_ones)
+                    .collect()
+            },
+        )
+    }
+
+    /// `targets` maps an observed value and the value's width in bits to the values
+    /// it should be turned into.
+    fn new<'a>(
+        buffer: &'a mut [u8],
+        chunk_size: usize,
+        values: &[u64],
+        steps: usize,
+        mutator_type: MutatorType,
+        targets: impl Fn(u64, u32) -> Vec<u64>,
+    ) -> Option<TracedValue<'a>> {
+        if ![1, 2, 4, 8].contains(&chunk_size) {
             return None;
         }
 
-        Some(RandomByte {
+        let bits = chunk_size as u32 * 8;
+        let all_ones = u64::MAX >> (64 - bits);
+        let mut masks = Vec::new();
+        for (hit, observed) in values.iter().take(buffer.len() / chunk_size).enumerate() {
+            // The agent reports zero extended values that are not masked to the width.
+            let observed = observed & all_ones;
+            let hit_masks = targets(observed, bits)
+                .into_iter()
+                .unique()
+                .filter(|target| *target != observed)
+                .map(|target| (hit * chunk_size, observed ^ target));
+            masks.extend(hit_masks);
+        }
+        masks.truncate(steps);
+
+        if masks.is_empty() {
+            return None;
+        }
+
+        Some(TracedValue {
             buffer,
+            chunk_size,
+            mutator_type,
+            masks,
             current_step: 0,
-            max_step: steps,
             last_idx: None,
-            saved_bytes: [0; N],
+            saved_chunk: [0; 8],
         })
     }
+
+    fn restore(&mut self) {
+        if let Some(idx) = self.last_idx.take() {
+            self.buffer[idx..(idx + self.chunk_size)]
+                .copy_from_slice(&self.saved_chunk[..self.chunk_size]);
+        }
+    }
 }
 
-impl<const N: usize> fmt::Debug for RandomByte<'_, N> {
+impl fmt::Debug for TracedValue<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct(&format!("RandomByte{}", N))
+        f.debug_struct("TracedValue")
+            .field("mutator_type", &self.mutator_type)
+            .field("chunk_size", &self.chunk_size)
             .field("current_step", &self.current_step)
-            .field("max_step", &self.max_step)
+            .field("masks.len()", &self.masks.len())
             .field("last_idx", &self.last_idx)
-            .field("saved_bytes", &self.saved_bytes)
             .field("buffer.len()", &self.buffer.len())
             .finish_non_exhaustive()
     }
 }
 
-impl<const N: usize> Mutator for RandomByte<'_, N> {
+impl Mutator for TracedValue<'_> {
     fn steps_total(&self) -> usize {
-        self.max_step
+        self.masks.len()
     }
 
     fn steps_done(&self) -> usize {
@@ -274,53 +1262,148 @@ impl<const N: usize> Mutator for RandomByte<'_, N> {
     }
 
     fn mutator_type(&self) -> MutatorType {
-        MutatorType::RandomByte(N)
+        self.mutator_type
+    }
+
+    fn operator(&self) -> Option<MutationOperator> {
+        Some(MutationOperator::Xor)
     }
 }
 
-impl<const N: usize> Iterator for RandomByte<'_, N> {
+impl Iterator for TracedValue<'_> {
     type Item = ();
 
     fn next(&mut self) -> Option<Self::Item> {
         // Revert previous mutation.
-        if let Some(idx) = self.last_idx.take() {
-            self.buffer[idx..(idx + N)].copy_from_slice(&self.saved_bytes);
-        }
+        self.restore();
 
-        if self.current_step == self.max_step {
+        let Some((idx, mask)) = self.masks.get(self.current_step).copied() else {
             // No steps left
             return None;
+        };
+
+        let chunk_size = self.chunk_size;
+        self.saved_chunk[..chunk_size].copy_from_slice(&self.buffer[idx..(idx + chunk_size)]);
+        self.buffer[idx..(idx + chunk_size)].copy_from_slice(&mask.to_le_bytes()[..chunk_size]);
+        self.last_idx = Some(idx);
+
+        self.current_step += 1;
+        Some(())
+    }
+}
+
+impl Drop for TracedValue<'_> {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// A Mutator that lets a [CustomMutatorPlugin] craft the masks. The plugin is
+/// informed about the mutated site when the first mask is requested, since
+/// the plugin can only work on a single site at a time and the mutators of
+/// all candidates are created upfront.
+pub struct Custom<'a> {
+    /// The buffer that is mutated.
+    buffer: &'a mut [u8],
+    plugin: Arc<Mutex<CustomMutatorPlugin>>,
+    name: CustomMutatorName,
+    site: SiteInfo,
+    entry: Arc<QueueEntry>,
+    /// Number of iterations we already performed.
+    current_step: usize,
+    /// The maximum number of iterations. Lowered to the number of masks the
+    /// plugin announced once it was started.
+    max_steps: usize,
+    started: bool,
+    /// The content of `buffer` before the plugin was started.
+    saved_buffer: Vec<u8>,
+}
+
+impl Custom<'_> {
+    pub fn new<'a>(
+        buffer: &'a mut [u8],
+        plugin: Arc<Mutex<CustomMutatorPlugin>>,
+        site: SiteInfo,
+        entry: Arc<QueueEntry>,
+        max_steps: usize,
+    ) -> Custom<'a> {
+        let name = CustomMutatorName::new(plugin.lock().unwrap().name());
+        Custom {
+            buffer,
+            plugin,
+            name,
+            site,
+            entry,
+            current_step: 0,
+            max_steps,
+            started: false,
+            saved_buffer: Vec::new(),
         }
+    }
+}
 
-        // Mutate
-        let mut rng = rand::thread_rng();
-        let last_idx = rng.gen_range(0..=(self.buffer.len() - N));
-        self.saved_bytes
-            .copy_from_slice(&self.buffer[last_idx..(last_idx + N)]);
+impl fmt::Debug for Custom<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Custom")
+            .field("name", &self.name)
+            .field("site", &self.site)
+            .field("current_step", &self.current_step)
+            .field("max_steps", &self.max_steps)
+            .field("started", &self.started)
+            .field("buffer.len()", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
 
-        self.buffer[last_idx..(last_idx + N)]
-            .iter_mut()
-            .for_each(|e| *e ^= rng.gen::<u8>());
-        self.last_idx = Some(last_idx);
+impl Mutator for Custom<'_> {
+    fn steps_total(&self) -> usize {
+        self.max_steps
+    }
+
+    fn steps_done(&self) -> usize {
+        self.current_step
+    }
+
+    fn mutator_type(&self) -> MutatorType {
+        MutatorType::Custom(self.name)
+    }
+}
+
+impl Iterator for Custom<'_> {
+    type Item = ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut plugin = self.plugin.lock().unwrap();
+        if self.started {
+            // Revert previous mutation.
+            self.buffer.copy_from_slice(&self.saved_buffer);
+        } else {
+            self.started = true;
+            self.saved_buffer = self.buffer.to_vec();
+            let steps = plugin.begin(&self.site, &self.entry, self.buffer);
+            self.max_steps = self.max_steps.min(steps);
+        }
+
+        if self.current_step >= self.max_steps || !plugin.next(self.buffer) {
+            // Make sure the plugin's mask does not outlive the mutator.
+            self.buffer.copy_from_slice(&self.saved_buffer);
+            self.max_steps = self.current_step;
+            return None;
+        }
 
         self.current_step += 1;
         Some(())
     }
 }
 
-impl<const N: usize> Drop for RandomByte<'_, N> {
+impl Drop for Custom<'_> {
     fn drop(&mut self) {
-        if let Some(idx) = self.last_idx.take() {
-            self.buffer[idx..(idx + N)].copy_from_slice(&self.saved_bytes);
+        if self.started {
+            self.buffer.copy_from_slice(&self.saved_buffer);
         }
     }
 }
 
-pub type RandomByte1<'a> = RandomByte<'a, 1>;
-#[allow(unused)]
-pub type RandomByte2<'a> = RandomByte<'a, 2>;
-pub type RandomByte4<'a> = RandomByte<'a, 4>;
-
 /// A Mutator that consecutively flips each byte.
 pub struct FlipByte<'a> {
     /// The mutated buffer.
@@ -926,11 +2009,20 @@ impl Drop for FlipOnce<'_> {
 
 #[cfg(test)]
 mod test {
-    use std::mem;
+    use std::{mem, sync::Arc};
+
+    use fuzztruction_shared::{
+        mutation_cache_entry::{MutationCacheEntry, MutationOperator},
+        types::MutationSiteID,
+    };
+    use llvm_stackmap::LLVMInstruction;
 
-    use crate::fuzzer::worker_impl::mutators::Havoc;
+    use crate::{dictionary, fuzzer::worker_impl::mutators::Havoc, mutation_site::CallFaultKind};
 
-    use super::RandomByte1;
+    use super::{
+        BranchForce, CallFault, CustomMutatorName, Dictionary, HitSelect, Mutator, MutatorType,
+        RandomByte1, RandomChunk, TracedValue,
+    };
 
     #[test]
     fn havoc_mutator_drop() {
@@ -969,4 +2061,229 @@ mod test {
         mem::drop(mutator);
         assert_eq!(buffer, vec![11u8; 7]);
     }
+
+    #[test]
+    fn random_chunk_mutator_writes_whole_chunks() {
+        let mut buffer = vec![0u8; 16];
+        {
+            let mut mutator = RandomChunk::new(&mut buffer, 4, MutationOperator::Add, 100).unwrap();
+            for _ in 0..100 {
+                mutator.next().unwrap();
+                let chunks = mutator.buffer.chunks(4);
+                let values = chunks
+                    .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                    .filter(|v| *v != 0)
+                    .collect::<Vec<_>>();
+                assert_eq!(values.len(), 1);
+                assert!(values[0] <= super::ARITH_MAX as u32);
+            }
+            assert!(mutator.next().is_none());
+        }
+        assert_eq!(buffer, vec![0u8; 16]);
+        assert!(RandomChunk::new(&mut buffer, 3, MutationOperator::Set, 1).is_none());
+    }
+
+    #[test]
+    fn hit_select_mutator_restores_filter() {
+        let mut entry = MutationCacheEntry::new(
+            MutationSiteID::get(0, 0, 0),
+            LLVMInstruction::Load,
+            0,
+            0,
+            unsafe { mem::zeroed() },
+            16,
+            8,
+        );
+        entry.set_hit_filter(Some(1..3));
+        {
+            let mut mutator = HitSelect::new(&mut entry, 4, 50).unwrap();
+            for _ in 0..50 {
+                mutator.next().unwrap();
+                let filter = mutator.entry.hit_filter().unwrap();
+                assert_eq!(filter.len(), 1);
+                assert!(filter.start < 4);
+                assert_ne!(mutator.entry.get_msk_as_slice()[..2], [0, 0]);
+                assert!(mutator.entry.get_msk_as_slice()[2..]
+                    .iter()
+                    .all(|b| *b == 0));
+            }
+            assert!(mutator.next().is_none());
+        }
+        assert_eq!(entry.hit_filter(), Some(1..3));
+        assert!(entry.is_nop());
+        assert!(HitSelect::new(&mut entry, 1, 50).is_none());
+    }
+
+    #[test]
+    fn branch_force_mutator_selects_successors() {
+        // An XOR mask inherited from an earlier mutation.
+        let mut buffer = vec![0x5u8; 16];
+        {
+            // Successor values of a switch on an i16, the last one can not be forced.
+            let mut mutator = BranchForce::new(&mut buffer, 2, &[3, 0, 0xffff], 100).unwrap();
+            for _ in 0..100 {
+                mutator.next().unwrap();
+                let chunks = mutator
+                    .buffer
+                    .chunks(2)
+                    .map(|c| u16::from_le_bytes(c.try_into().unwrap()))
+                    .filter(|v| *v != 0)
+                    .collect::<Vec<_>>();
+                assert!(chunks.len() == 1 || chunks.len() == 8);
+                assert!(chunks.iter().all(|v| *v == chunks[0]));
+                assert!(chunks[0] == 4 || chunks[0] == 1);
+            }
+            assert!(mutator.next().is_none());
+        }
+        assert_eq!(buffer, vec![0x5u8; 16]);
+        assert!(BranchForce::new(&mut buffer, 1, &[], 1).is_none());
+        assert!(BranchForce::new(&mut buffer, 3, &[1, 0], 1).is_none());
+    }
+
+    #[test]
+    fn call_fault_mutator_sets_errno_on_failure() {
+        let mut buffer = vec![0u8; 16];
+        let mut errno_buffer = vec![0u8; 16];
+        {
+            let mut mutator = CallFault::new(
+                &mut buffer,
+                4,
+                CallFaultKind::ReturnInteger,
+                Some(&mut errno_buffer),
+                100,
+            )
+            .unwrap();
+            assert_eq!(mutator.operator(), Some(MutationOperator::Set));
+            for _ in 0..100 {
+                mutator.next().unwrap();
+                let values = mutator
+                    .buffer
+                    .chunks(4)
+                    .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                    .collect::<Vec<_>>();
+                let errnos = mutator
+                    .errno_buffer
+                    .as_ref()
+                    .unwrap()
+                    .chunks(4)
+                    .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+                    .collect::<Vec<_>>();
+                assert!(values.iter().any(|v| *v != 0));
+                for (value, errno) in values.iter().zip(errnos) {
+                    assert_eq!(*value == u32::MAX, errno != 0);
+                }
+            }
+            assert!(mutator.next().is_none());
+        }
+        assert_eq!(buffer, vec![0u8; 16]);
+        assert_eq!(errno_buffer, vec![0u8; 16]);
+
+        let mutator = CallFault::new(&mut buffer, 8, CallFaultKind::ReturnPointer, None, 1);
+        assert_eq!(mutator.unwrap().operator(), Some(MutationOperator::Force));
+        assert!(CallFault::new(&mut buffer, 4, CallFaultKind::Errno, None, 1).is_none());
+    }
+
+    #[test]
+    fn dictionary_mutator_overlays_tokens() {
+        let mut tokens = dictionary::Dictionary::default();
+        tokens.extend([b"GET".to_vec(), b"HTTP/1.1".to_vec()]);
+        let tokens = Arc::new(tokens);
+
+        // The values observed during the four hits, stored little endian.
+        let values = [0x1122_3344u64, 0, 0xffff_ffff, 0x4142_4344];
+        let mut buffer = vec![0x5u8; 16];
+        {
+            let mut mutator =
+                Dictionary::new(&mut buffer, 4, &values, tokens.clone(), 100).unwrap();
+            assert_eq!(mutator.operator(), Some(MutationOperator::Xor));
+            for _ in 0..100 {
+                mutator.next().unwrap();
+                let range = mutator.last_range.clone().unwrap();
+                assert_eq!(range.start % 4, 0);
+                // Applying the mask via XOR yields the token.
+                let patched = mutator.buffer[range.clone()]
+                    .chunks(4)
+                    .zip(&values[(range.start / 4)..])
+                    .flat_map(|(chunk, value)| {
+                        let chunk = u32::from_le_bytes(chunk.try_into().unwrap());
+                        (chunk ^ *value as u32).to_le_bytes()
+                    })
+                    .collect::<Vec<_>>();
+                let original_last_byte = (values[range.start / 4] >> 24) as u8;
+                assert!(
+                    patched == [b'G', b'E', b'T', original_last_byte] || patched == b"HTTP/1.1"
+                );
+            }
+            assert!(mutator.next().is_none());
+        }
+        assert_eq!(buffer, vec![0x5u8; 16]);
+
+        // Tokens are truncated if the patch point is not hit often enough.
+        let mut buffer = vec![0u8; 2];
+        let mut mutator = Dictionary::new(&mut buffer, 2, &[0], tokens.clone(), 10).unwrap();
+        mutator.next().unwrap();
+        assert!(mutator.buffer == b"GE" || mutator.buffer == b"HT");
+        drop(mutator);
+
+        // Only hits with a traced value are mutated.
+        assert!(Dictionary::new(&mut buffer, 2, &[], tokens.clone(), 1).is_none());
+        let empty = Arc::new(dictionary::Dictionary::default());
+        assert!(Dictionary::new(&mut buffer, 1, &[0], empty, 1).is_none());
+        assert!(Dictionary::new(&mut buffer, 3, &[0], tokens, 1).is_none());
+    }
+
+    #[test]
+    fn traced_value_mutators_reach_targets() {
+        // Two 16 bit values, stored little endian. The second value carries bits
+        // beyond its width that must be ignored.
+        let values = [0x1234u64, 0xdead_ffff];
+        let mut buffer = vec![0u8; 4];
+        let mut seen = vec![Vec::new(), Vec::new()];
+        {
+            let mut mutator =
+                TracedValue::interesting_values(&mut buffer, 2, &values, usize::MAX).unwrap();
+            assert_eq!(mutator.mutator_type(), MutatorType::InterestingValue);
+            assert_eq!(mutator.operator(), Some(MutationOperator::Xor));
+            while mutator.next().is_some() {
+                let idx = mutator.last_idx.unwrap();
+                let mask = u16::from_le_bytes([mutator.buffer[idx], mutator.buffer[idx + 1]]);
+                seen[idx / 2].push(values[idx / 2] as u16 ^ mask);
+            }
+        }
+        assert_eq!(buffer, vec![0u8; 4]);
+        for targets in [0, 1, 0xffff, 0x7fff, 0x8000, 0x100] {
+            assert!(seen[0].contains(&targets));
+        }
+        assert!(!seen[1].contains(&0xffff));
+        assert!(seen[1].contains(&0x0));
+
+        // Deltas wrap around at the width of the value.
+        let mut mutator = TracedValue::arithmetic(&mut buffer[2..], 2, &values[1..], 2).unwrap();
+        assert_eq!(mutator.steps_total(), 2);
+        mutator.next().unwrap();
+        assert_eq!(mutator.buffer, (0xffffu16 ^ 0x0).to_le_bytes());
+        mutator.next().unwrap();
+        assert_eq!(mutator.buffer, (0xffffu16 ^ 0xfffe).to_le_bytes());
+        assert!(mutator.next().is_none());
+        drop(mutator);
+
+        assert!(TracedValue::arithmetic(&mut buffer, 3, &values, 10).is_none());
+        assert!(TracedValue::arithmetic(&mut buffer, 2, &[], 10).is_none());
+    }
+
+    #[test]
+    fn custom_mutator_names_are_interned() {
+        let name = CustomMutatorName::new("grammar");
+        assert_eq!(name, CustomMutatorName::new(&String::from("grammar")));
+        assert!(std::ptr::eq(
+            name.as_str(),
+            CustomMutatorName::new("grammar").as_str()
+        ));
+        assert_ne!(name, CustomMutatorName::new("radamsa"));
+
+        let mutator = MutatorType::Custom(name);
+        let json = serde_json::to_string(&mutator).unwrap();
+        assert_eq!(json, r#"{"Custom":"grammar"}"#);
+        assert_eq!(serde_json::from_str::<MutatorType>(&json).unwrap(), mutator);
+    }
 }
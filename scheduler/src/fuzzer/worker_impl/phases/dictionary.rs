@@ -0,0 +1,74 @@
+use super::{inject_debug_mutator, FuzzingPhase};
+use crate::fuzzer::{worker::FuzzingWorker, worker_impl::mutators};
+
+use anyhow::Result;
+use fuzztruction_shared::mutation_cache_entry::MutationCacheEntry;
+use rand::{seq::SliceRandom, thread_rng};
+
+const PHASE: FuzzingPhase = FuzzingPhase::Dictionary;
+
+/// Maximum number of tokens tried for each entry.
+const MAX_STEPS_PER_ENTRY: usize = 512;
+
+impl FuzzingWorker {
+    pub fn do_dictionary_phase(&mut self) -> Result<()> {
+        self.state.set_phase(PHASE);
+        if self.dictionary.is_empty() {
+            log::info!("The dictionary is empty, skipping phase");
+            return Ok(());
+        }
+
+        let entry = self.state.entry();
+        let trace = entry.stats_ro().trace();
+        let source = self.source.as_mut().unwrap();
+        let mut candidates = source.mutation_cache().borrow_mut().entries_mut_static();
+        candidates.shuffle(&mut thread_rng());
+        let steps = self.dictionary.len().min(MAX_STEPS_PER_ENTRY);
+
+        let mut mutations = Vec::<(
+            &mut MutationCacheEntry,
+            Vec<Box<dyn mutators::Mutator<Item = ()>>>,
+        )>::new();
+
+        for candidate in candidates.into_iter() {
+            if candidate.chunk_size_bits() % 8 != 0 {
+                continue;
+            }
+            // The token is XORed with the values observed during tracing. If a hit
+            // filter is set, the first chunk is consumed by the first hit that passes
+            // the filter.
+            let first_hit = candidate
+                .hit_filter()
+                .map(|f| f.start as usize)
+                .unwrap_or(0);
+            let values = trace
+                .as_ref()
+                .and_then(|trace| trace.values(candidate.id()))
+                .and_then(|values| values.get(first_hit..))
+                .unwrap_or_default();
+            let mutator = mutators::Dictionary::new(
+                candidate.get_msk_as_slice(),
+                candidate.chunk_size_bytes() as usize,
+                values,
+                self.dictionary.clone(),
+                steps,
+            );
+            let Some(mutator) = mutator else {
+                continue;
+            };
+
+            let mut mutators = vec![Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>];
+            inject_debug_mutator(&mut mutators);
+            let entry = (unsafe { candidate.alias_mut() }, mutators);
+            mutations.push(entry);
+        }
+
+        self.fuzz_candidates(
+            mutations,
+            Some(self.config.phases.dictionary.entry_cov_timeout),
+            false,
+        )?;
+
+        Ok(())
+    }
+}
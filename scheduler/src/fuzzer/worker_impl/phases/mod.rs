@@ -9,6 +9,7 @@ mod run_phase;
 //phases
 mod add;
 mod combine;
+mod dictionary;
 mod discovery;
 mod mutate;
 
@@ -1,12 +1,25 @@
 use super::{inject_debug_mutator, FuzzingPhase};
-use crate::fuzzer::{
-    worker::FuzzingWorker,
-    worker_impl::mutators::{self, Mutator},
+use crate::{
+    custom_mutator::SiteInfo,
+    fuzzer::{
+        worker::FuzzingWorker,
+        worker_impl::{
+            bandit,
+            mutators::{self, CustomMutatorName, Mutator, MutatorType},
+        },
+    },
+    mutation_cache_ops::MutationCacheOpsEx,
+    mutation_site::{CallFaultKind, MutationSite},
 };
 
 use anyhow::Result;
-use fuzztruction_shared::mutation_cache_entry::MutationCacheEntry;
-use rand::{seq::SliceRandom, thread_rng};
+use fuzztruction_shared::mutation_cache_entry::{MutationCacheEntry, MutationOperator};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    thread_rng,
+};
+use std::collections::HashMap;
 
 const PHASE: FuzzingPhase = FuzzingPhase::Mutate;
 
@@ -14,9 +27,112 @@ impl FuzzingWorker {
     pub fn do_mutate_phase(&mut self) -> Result<()> {
         self.state.set_phase(PHASE);
         let entry = self.state.entry();
+        let trace = entry.stats_ro().trace();
 
         let source = self.source.as_mut().unwrap();
+        let candidate_ids = source
+            .mutation_cache()
+            .borrow()
+            .iter()
+            .map(|c| c.id())
+            .collect::<Vec<_>>();
+        let mutation_sites = {
+            let cerebrum_guard = self.cerebrum.read().unwrap();
+            let query = cerebrum_guard.as_ref().unwrap().query();
+            query
+                .patch_point_ids_to_patch_point(&candidate_ids)
+                .into_iter()
+                .map(|site| (site.id(), site))
+                .collect::<HashMap<_, _>>()
+        };
+
+        // Masks of the entry were sized according to the trace they were created
+        // with. [mutators::BranchForce] selects a successor per hit, thus the
+        // masks of forceable sites must cover all hits of this entry's trace.
+        if let Some(trace) = trace.as_ref() {
+            let mut mc = source.mutation_cache().borrow().try_clone()?;
+            unsafe {
+                // Safety: There are no references into the mutation cache yet.
+                mc.grow_covered_entries(trace, |e| {
+                    mutation_sites
+                        .get(&e.id())
+                        .is_some_and(|site| !site.successor_values().is_empty())
+                });
+                source.mutation_cache_replace(&mc)?;
+            }
+        }
         let candidates = source.mutation_cache().borrow_mut().entries_mut_static();
+        // Errno sites are only mutated together with the return value site of their
+        // call, which records the same call ID.
+        let is_errno_site = |site: &MutationSite| {
+            site.call_fault()
+                .is_some_and(|fault| fault.kind == CallFaultKind::Errno)
+        };
+        let mut errno_entries = source
+            .mutation_cache()
+            .borrow_mut()
+            .entries_mut_static()
+            .into_iter()
+            .filter_map(|e| {
+                let site = mutation_sites
+                    .get(&e.id())
+                    .filter(|site| is_errno_site(site))?;
+                let call_id = site.call_fault().unwrap().call_id;
+                Some(((site.function_address(), call_id), e))
+            })
+            .collect::<HashMap<_, _>>();
+        // Force is only meaningful for masks crafted by [mutators::BranchForce].
+        let chunk_operators = MutationOperator::ALL
+            .into_iter()
+            .filter(|op| !matches!(op, MutationOperator::Xor | MutationOperator::Force))
+            .collect::<Vec<_>>();
+
+        // The iterations of the non-deterministic mutators are scaled according
+        // to the probabilities learned from their yields so far.
+        let mut adaptive_mutators = vec![
+            MutatorType::Havoc,
+            MutatorType::RandomByte(1),
+            MutatorType::RandomByte(4),
+            MutatorType::HitSelect,
+            MutatorType::BranchForce,
+            MutatorType::CallFault,
+        ];
+        adaptive_mutators.extend(
+            chunk_operators
+                .iter()
+                .map(|op| MutatorType::RandomChunk(*op)),
+        );
+        let custom_mutators = self
+            .custom_mutators
+            .iter()
+            .map(|plugin| {
+                let name = CustomMutatorName::new(plugin.lock().unwrap().name());
+                (plugin.clone(), MutatorType::Custom(name))
+            })
+            .collect::<Vec<_>>();
+        adaptive_mutators.extend(custom_mutators.iter().map(|(_, mutator)| *mutator));
+        let probabilities = if self.config.phases.mutate.adaptive_mutators {
+            let cerebrum_guard = self.cerebrum.read().unwrap();
+            let query = cerebrum_guard.as_ref().unwrap().query();
+            query.mutator_probabilities(PHASE, &adaptive_mutators)
+        } else {
+            vec![1.0 / adaptive_mutators.len() as f64; adaptive_mutators.len()]
+        };
+        let probabilities = adaptive_mutators
+            .iter()
+            .copied()
+            .zip(probabilities)
+            .collect::<HashMap<_, _>>();
+        log::debug!("Mutator probabilities: {:#?}", probabilities);
+        let scaled_iterations = |mutator: MutatorType, iterations: usize| {
+            bandit::scale_iterations(iterations, probabilities[&mutator], adaptive_mutators.len())
+        };
+        let chunk_operator_dist = WeightedIndex::new(
+            chunk_operators
+                .iter()
+                .map(|op| probabilities[&MutatorType::RandomChunk(*op)]),
+        )
+        .unwrap();
 
         let mut mutations = Vec::<(
             &mut MutationCacheEntry,
@@ -24,6 +140,12 @@ impl FuzzingWorker {
         )>::new();
 
         for candidate in candidates.into_iter() {
+            if mutation_sites
+                .get(&candidate.id())
+                .is_some_and(|site| is_errno_site(site))
+            {
+                continue;
+            }
             let mut mutators = Vec::new();
             let msk_len = candidate.get_msk_as_slice().len();
 
@@ -41,17 +163,27 @@ impl FuzzingWorker {
                 }
             }
 
-            let mutator = mutators::Havoc::new(candidate.get_msk_as_slice(), 16, iterations);
+            let mutator = mutators::Havoc::new(
+                candidate.get_msk_as_slice(),
+                16,
+                scaled_iterations(MutatorType::Havoc, iterations),
+            );
             mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
             inject_debug_mutator(&mut mutators);
 
-            let mutator = mutators::RandomByte1::new(candidate.get_msk_as_slice(), iterations);
+            let mutator = mutators::RandomByte1::new(
+                candidate.get_msk_as_slice(),
+                scaled_iterations(MutatorType::RandomByte(1), iterations),
+            );
             if let Some(mutator) = mutator {
                 mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
                 inject_debug_mutator(&mut mutators);
             }
 
-            let mutator = mutators::RandomByte4::new(candidate.get_msk_as_slice(), iterations);
+            let mutator = mutators::RandomByte4::new(
+                candidate.get_msk_as_slice(),
+                scaled_iterations(MutatorType::RandomByte(4), iterations),
+            );
             if let Some(mutator) = mutator {
                 mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
                 inject_debug_mutator(&mut mutators);
@@ -63,11 +195,141 @@ impl FuzzingWorker {
                 inject_debug_mutator(&mut mutators);
             }
 
+            // Apply the chunks via an operator other than XOR, which allows to, e.g.,
+            // add small deltas or to set values to a boundary.
+            let operator = chunk_operators[chunk_operator_dist.sample(&mut thread_rng())];
+            let mutator = mutators::RandomChunk::new(
+                candidate.get_msk_as_slice(),
+                candidate.chunk_size_bytes() as usize,
+                operator,
+                scaled_iterations(MutatorType::RandomChunk(operator), iterations),
+            );
+            if let Some(mutator) = mutator.filter(|_| candidate.chunk_size_bits() % 8 == 0) {
+                mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                inject_debug_mutator(&mut mutators);
+            }
+
+            // Patch points that are executed multiple times, e.g., once per record
+            // of a message, allow to mutate a single record without touching the others.
+            let hits = trace
+                .as_ref()
+                .and_then(|trace| trace.hits_mapping().get(&candidate.id()).copied());
+            if let Some(hits) = hits {
+                let mce = unsafe { candidate.alias_mut() };
+                let iterations = scaled_iterations(MutatorType::HitSelect, iterations);
+                if let Some(mutator) = mutators::HitSelect::new(mce, hits, iterations) {
+                    mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                    inject_debug_mutator(&mut mutators);
+                }
+            }
+
+            // Turn the values observed during tracing into boundary values or
+            // change them by small deltas. If a hit filter is set, the first chunk
+            // is consumed by the first hit that passes the filter.
+            let first_hit = candidate
+                .hit_filter()
+                .map(|f| f.start as usize)
+                .unwrap_or(0);
+            let values = trace
+                .as_ref()
+                .and_then(|trace| trace.values(candidate.id()))
+                .and_then(|values| values.get(first_hit..))
+                .unwrap_or_default();
+            let chunk_size = candidate.chunk_size_bytes() as usize;
+            if candidate.chunk_size_bits() % 8 == 0 {
+                let value_mutators = [
+                    mutators::TracedValue::interesting_values(
+                        candidate.get_msk_as_slice(),
+                        chunk_size,
+                        values,
+                        iterations,
+                    ),
+                    mutators::TracedValue::arithmetic(
+                        candidate.get_msk_as_slice(),
+                        chunk_size,
+                        values,
+                        iterations,
+                    ),
+                ];
+                // These are deterministic, thus they are applied to all candidates
+                // once per entry.
+                for mutator in value_mutators.into_iter().flatten() {
+                    if !entry.stats_ro().is_mutator_done(mutator.mutator_type()) {
+                        mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                        inject_debug_mutator(&mut mutators);
+                    }
+                }
+            }
+
+            // Force branches, selects, and switches to take a specific successor,
+            // e.g., the error path or a different switch case.
+            let successor_values = mutation_sites
+                .get(&candidate.id())
+                .map(|site| site.successor_values())
+                .unwrap_or_default();
+            let mutator = mutators::BranchForce::new(
+                candidate.get_msk_as_slice(),
+                candidate.chunk_size_bytes() as usize,
+                successor_values,
+                scaled_iterations(MutatorType::BranchForce, iterations),
+            );
+            if let Some(mutator) = mutator {
+                mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                inject_debug_mutator(&mut mutators);
+            }
+
+            // Let calls fail, e.g., allocators return NULL or I/O functions -1 and
+            // set errno.
+            let site = mutation_sites.get(&candidate.id());
+            if let Some((site, fault)) = site.and_then(|site| Some((site, site.call_fault()?))) {
+                let errno_buffer = errno_entries
+                    .remove(&(site.function_address(), fault.call_id))
+                    .map(|errno_entry| errno_entry.get_msk_as_slice());
+                let mutator = mutators::CallFault::new(
+                    candidate.get_msk_as_slice(),
+                    candidate.chunk_size_bytes() as usize,
+                    fault.kind,
+                    errno_buffer,
+                    scaled_iterations(MutatorType::CallFault, iterations),
+                );
+                if let Some(mutator) = mutator.filter(|_| candidate.chunk_size_bits() % 8 == 0) {
+                    mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                    inject_debug_mutator(&mut mutators);
+                }
+            }
+
+            // Mutators provided by plugins, see [crate::custom_mutator].
+            for (plugin, mutator_type) in custom_mutators.iter() {
+                let site = SiteInfo {
+                    site_id: candidate.id().0,
+                    llvm_instruction: candidate.llvm_instruction() as u32,
+                    value_size_bits: candidate.target_value_size_bit(),
+                    chunk_size_bits: candidate.chunk_size_bits().into(),
+                    hits: hits.unwrap_or(0),
+                };
+                let mutator = mutators::Custom::new(
+                    candidate.get_msk_as_slice(),
+                    plugin.clone(),
+                    site,
+                    entry.clone(),
+                    scaled_iterations(*mutator_type, iterations),
+                );
+                mutators.push(Box::new(mutator) as Box<dyn mutators::Mutator<Item = ()>>);
+                inject_debug_mutator(&mut mutators);
+            }
+
             mutators.shuffle(&mut thread_rng());
             let entry = (unsafe { candidate.alias_mut() }, mutators);
             mutations.push(entry);
         }
 
+        entry
+            .stats_rw()
+            .mark_mutator_done(mutators::MutatorType::InterestingValue);
+        entry
+            .stats_rw()
+            .mark_mutator_done(mutators::MutatorType::Arithmetic);
+
         let cov_timeout = self.config.phases.mutate.entry_cov_timeout;
         self.fuzz_candidates(mutations, Some(cov_timeout), false)?;
 
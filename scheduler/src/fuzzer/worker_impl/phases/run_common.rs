@@ -72,10 +72,14 @@ impl FuzzingWorker {
                 drop(queue);
 
                 let mut cerebrum_guard = self.cerebrum.write().unwrap();
-                cerebrum_guard
-                    .as_mut()
-                    .unwrap()
-                    .report_new_qe(Arc::clone(&new_entry));
+                let cerebrum = cerebrum_guard.as_mut().unwrap();
+                cerebrum.report_new_qe(Arc::clone(&new_entry));
+                let mutator_probabilities = log::log_enabled!(log::Level::Debug)
+                    .then(|| cerebrum.query().learned_mutator_probabilities());
+                drop(cerebrum_guard);
+                if let Some(mutator_probabilities) = mutator_probabilities {
+                    log::debug!("mutator_probabilities={:?}", mutator_probabilities);
+                }
 
                 // Clear new bits from our local map.
                 virgin_map.not();
@@ -123,6 +127,9 @@ impl FuzzingWorker {
             ExecError::DuplicatedOutput => {
                 stats.source_duplicated_output += 1;
             }
+            ExecError::ClientNotConnected => {
+                stats.client_not_connected += 1;
+            }
         }
     }
 
@@ -239,7 +246,9 @@ impl FuzzingWorker {
                 stats.sink_unique_crashes += 1;
                 stats.last_crash_ts = Some(Instant::now());
                 let qe = self.create_new_queue_entry(new_bits, true)?;
-                self.save_crashing_input_and_asan_ubsan_report(sink_input, signal, qe);
+                if self.save_crashing_input_and_asan_ubsan_report(sink_input, signal, qe) {
+                    stats.sink_unique_bugs += 1;
+                }
             }
             BitmapStatus::NewHit => {
                 log::info!(
@@ -250,7 +259,9 @@ impl FuzzingWorker {
                 stats.sink_unique_crashes += 1;
                 stats.last_crash_ts = Some(Instant::now());
                 let qe = self.create_new_queue_entry(new_bits, true)?;
-                self.save_crashing_input_and_asan_ubsan_report(sink_input, signal, qe);
+                if self.save_crashing_input_and_asan_ubsan_report(sink_input, signal, qe) {
+                    stats.sink_unique_bugs += 1;
+                }
             }
             BitmapStatus::NoChange => (),
         }
@@ -99,6 +99,8 @@ impl FuzzingWorker {
             // copy mask for comparing it after fuzzing is done
             let msk_copy = target_mce.get_msk_as_slice().to_vec();
             debug_assert_eq!(msk_copy, target_mce.get_msk_as_slice(),);
+            // Mutators might change the operator, which is restored after each mutator.
+            let original_operator = target_mce.operator();
 
             // Apply all mutators to the current `mce` (MutationCacheEntry)
             'mce: for mutator in mutators {
@@ -117,10 +119,13 @@ impl FuzzingWorker {
                 ));
 
                 let one_shot = mutator.one_shot();
-                let sync_needed = mutator.needs_sync();
+                let operator = mutator.operator().unwrap_or(original_operator);
+                // Changing the operator requires the mutation stub to be regenerated.
+                let sync_needed = mutator.needs_sync() || operator != original_operator;
 
                 // Enable once for this fuzzing round.
                 one_shot.then(|| target_mce.enable());
+                target_mce.set_operator(operator);
 
                 if sync_needed {
                     self.source.as_mut().unwrap().sync_mutations()?;
@@ -139,6 +144,7 @@ impl FuzzingWorker {
                         && mce_start_paths_cnt != iteration_stats.paths()
                     {
                         one_shot.then(|| target_mce.disable());
+                        target_mce.set_operator(original_operator);
                         if sync_needed {
                             self.source.as_mut().unwrap().sync_mutations()?;
                         }
@@ -169,6 +175,7 @@ impl FuzzingWorker {
                         last_update_ts = Instant::now();
                         if self.should_stop() {
                             one_shot.then(|| target_mce.disable());
+                            target_mce.set_operator(original_operator);
                             // No sync check needed since we exit the loop.
                             break 'exit;
                         }
@@ -177,6 +184,7 @@ impl FuzzingWorker {
                             iteration_no_new_coverage_timeout,
                             &iteration_stats,
                         ) {
+                            target_mce.set_operator(original_operator);
                             // No sync check needed since we exit the loop.
                             break 'exit;
                         }
@@ -192,6 +200,7 @@ impl FuzzingWorker {
                                 no_successful_exec_time_limit
                             );
                             one_shot.then(|| target_mce.disable());
+                            target_mce.set_operator(original_operator);
                             if sync_needed {
                                 self.source.as_mut().unwrap().sync_mutations()?;
                             }
@@ -210,6 +219,7 @@ impl FuzzingWorker {
                 }
 
                 one_shot.then(|| target_mce.disable());
+                target_mce.set_operator(original_operator);
                 if sync_needed {
                     // We need to sync here, since the next entry might not have set sync_needed.
                     self.source.as_mut().unwrap().sync_mutations()?;
@@ -12,6 +12,8 @@ pub enum FuzzingPhase {
     Mutate,
     Add,
     Combine,
+    /// Overlay tokens of the dictionary onto the masks of all [QueueEntry]s.
+    Dictionary,
     /// Currently there is no phase enabled. This happens to be the state
     /// during scheduling.
     None,
@@ -15,6 +15,9 @@ pub enum InputChannel {
     /// The applications uses network primitives (e.g., sockets for communication)
     Tcp,
     Udp,
+    /// The application communicates via an AF_UNIX socket that is bound to
+    /// a path in the filesystem.
+    Unix,
 }
 
 /// The kind of output a program produces.
@@ -30,4 +33,7 @@ pub enum OutputChannel {
     /// The applications uses network primitives (e.g., sockets for communication)
     Tcp,
     Udp,
+    /// The application communicates via an AF_UNIX socket that is bound to
+    /// a path in the filesystem.
+    Unix,
 }
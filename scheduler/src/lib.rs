@@ -21,12 +21,15 @@
 
 extern crate lazy_static;
 
+pub use custom_mutator::CustomMutator;
 pub use fuzztruction_shared::dwarf;
 pub use fuzztruction_shared::mutation_cache;
 pub use llvm_stackmap;
 
 //pub mod mutation;
 pub mod checks;
+pub mod custom_mutator;
+pub mod dictionary;
 pub mod io_channels;
 pub mod mutation_cache_ops;
 pub mod mutation_site;
@@ -45,6 +48,9 @@ pub mod constants;
 pub mod aflpp;
 pub mod competitors;
 pub mod networked;
+pub mod packet_capture;
+pub mod sanitizer;
+pub mod transcript;
 pub mod valgrind;
 
 pub mod coverage;
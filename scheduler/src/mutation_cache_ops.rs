@@ -33,6 +33,17 @@ pub trait MutationCacheOpsEx {
     /// This function is only safe if no references into the [MutationCache]
     /// cross this function call.
     unsafe fn resize_covered_entries_wo_msk(&mut self, trace: &Trace) -> &mut Self;
+
+    /// Grow the masks of the `MutationCacheEntry`s for which `filter` returns true,
+    /// such that they cover all hits in `trace`. Masks are extended with zeros, and
+    /// masks that already cover all hits are left untouched.
+    ///
+    /// # Safety
+    /// This function is only safe if no references into the [MutationCache]
+    /// cross this function call.
+    unsafe fn grow_covered_entries<F>(&mut self, trace: &Trace, filter: F) -> &mut Self
+    where
+        F: Fn(&MutationCacheEntry) -> bool;
 }
 
 impl MutationCacheOpsEx for MutationCache {
@@ -99,4 +110,31 @@ impl MutationCacheOpsEx for MutationCache {
 
         self
     }
+
+    unsafe fn grow_covered_entries<F>(&mut self, trace: &Trace, filter: F) -> &mut Self
+    where
+        F: Fn(&MutationCacheEntry) -> bool,
+    {
+        let map = trace.hits_mapping();
+
+        let mut resized_entries = Vec::new();
+        for entry in self.iter_mut() {
+            if let Some(v) = map.get(&entry.id()) {
+                let msk_len = (*v) as u32 * u32::from(entry.chunk_size_bytes());
+                if msk_len > entry.msk_len() && filter(entry) {
+                    resized_entries.push(entry.clone_with_new_msk(msk_len));
+                }
+            }
+        }
+
+        for e in resized_entries.iter() {
+            self.remove(e.id());
+        }
+
+        for e in resized_entries.iter() {
+            self.push(e);
+        }
+
+        self
+    }
 }
@@ -1,5 +1,6 @@
 use std::{
     assert_matches::assert_matches,
+    collections::HashMap,
     convert::TryInto,
     fs::{self, OpenOptions},
     ops::Range,
@@ -29,6 +30,51 @@ pub enum LLVMIns {
     Select = 57,
 }
 
+/// The value of a call site that can be faulted. The values must match the
+/// FT_CALL_FAULT_* constants of the compiler pass.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CallFaultKind {
+    /// The integer returned by the callee.
+    ReturnInteger = 1,
+    /// The pointer returned by the callee.
+    ReturnPointer = 2,
+    /// The value errno is set to after the callee returned. Zero leaves errno untouched.
+    Errno = 3,
+}
+
+impl TryFrom<u64> for CallFaultKind {
+    type Error = ();
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(CallFaultKind::ReturnInteger),
+            2 => Ok(CallFaultKind::ReturnPointer),
+            3 => Ok(CallFaultKind::Errno),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A call site whose result can be replaced by an error value.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CallFault {
+    pub kind: CallFaultKind,
+    /// The FNV-1a hash of the callee's name, see [callee_hash].
+    pub callee_hash: u64,
+    /// Identifies the call within its function. The return value site and the
+    /// errno site of the same call share this ID.
+    pub call_id: u64,
+}
+
+/// The 64 bit FNV-1a hash of `name`. This is the hash the compiler pass records
+/// for the callee of a [CallFault] site. It is also used to identify the function
+/// a site belongs to, see [MutationSite::function_hash].
+pub fn callee_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct MutationSite {
     /// A unique ID that identifies this MutationSite.
@@ -44,23 +90,43 @@ pub struct MutationSite {
     llvm_instruction: LLVMInstruction,
     /// The stack slot the to be mutated value is spilled into.
     spill_slot: Location,
+    /// The large constant referred to by `spill_slot` if it is of type ConstIndex.
+    #[serde(default)]
+    spill_slot_constant: u64,
     /// The location of the value that was spilled into the `spill_slot`.
     /// This is used to determine the values size, because the spill slot is
     /// located on the stack and therefore has a size that is a multiple of 8 (on 64bit).
     target_value_size_in_bit: u32,
+    /// The values of the mutated condition that select the successors of a `Br`,
+    /// `Select`, or `Switch` instruction in successor order. Empty, if the
+    /// successor can not be forced.
+    #[serde(default)]
+    successor_values: Vec<u64>,
+    /// Set if this site records the result of a call that can be faulted.
+    #[serde(default)]
+    call_fault: Option<CallFault>,
+    /// The [callee_hash] of the name of the function that contains this site.
+    /// `None`, if the binary has no symbol for the function.
+    #[serde(default)]
+    function_hash: Option<u64>,
     /// The memory mapping this mutation site belongs to.
     mapping: MapRange,
 }
 
 impl MutationSite {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         base: u64,
         address: u64,
         llvm_id: u64,
         spill_slot: Location,
+        spill_slot_constant: u64,
         target_value_size_in_bit: u32,
+        successor_values: Vec<u64>,
+        call_fault: Option<CallFault>,
         mapping: MapRange,
         function_address: u64,
+        function_hash: Option<u64>,
     ) -> Self {
         assert!(address + base > 0);
 
@@ -70,10 +136,14 @@ impl MutationSite {
             address,
             llvm_instruction: llvm_id.try_into().unwrap(),
             spill_slot,
+            spill_slot_constant,
             target_value_size_in_bit,
+            successor_values,
+            call_fault,
             base,
             mapping,
             function_address,
+            function_hash,
         }
     }
 
@@ -93,6 +163,11 @@ impl MutationSite {
         self.function_address
     }
 
+    /// See [MutationSite::function_hash].
+    pub fn function_hash(&self) -> Option<u64> {
+        self.function_hash
+    }
+
     pub fn base(&self) -> u64 {
         self.base
     }
@@ -113,6 +188,20 @@ impl MutationSite {
         &self.spill_slot
     }
 
+    pub fn spill_slot_constant(&self) -> u64 {
+        self.spill_slot_constant
+    }
+
+    /// The address of the spilled value if the spill slot is a constant, i.e.,
+    /// of type Constant or ConstIndex.
+    pub fn constant_spill_slot_address(&self) -> Option<u64> {
+        match self.spill_slot.loc_type {
+            LocationType::Constant => Some(self.spill_slot.offset_or_constant as i64 as u64),
+            LocationType::ConstIndex => Some(self.spill_slot_constant),
+            _ => None,
+        }
+    }
+
     pub fn target_value_size_bit(&self) -> u32 {
         self.target_value_size_in_bit
     }
@@ -127,6 +216,16 @@ impl MutationSite {
         self.target_value_size_bit().div_ceil(8)
     }
 
+    /// See [MutationSite::successor_values].
+    pub fn successor_values(&self) -> &[u64] {
+        &self.successor_values
+    }
+
+    /// See [MutationSite::call_fault].
+    pub fn call_fault(&self) -> Option<CallFault> {
+        self.call_fault
+    }
+
     pub fn into_mutation_cache_entry(&self) -> Box<MutationCacheEntry> {
         self.into()
     }
@@ -163,6 +262,7 @@ pub fn from_stackmap(
     ));
     let is_pic = elf_file.ehdr.e_type == elf::abi::ET_DYN;
     let base = is_pic.then(|| mapping.start()).unwrap_or(0) as u64;
+    let function_hashes = function_hashes(elf_file);
 
     //let mut seen_vmas = HashSet::new();
 
@@ -174,21 +274,57 @@ pub fn from_stackmap(
                 log::warn!("StkMapRecord without recorded locations");
             }
             let locations = &record.locations;
-            assert_eq!(locations.len(), 2);
+            assert!(locations.len() >= 2);
 
             // Order is defined by the order we pushed these as argument for the
             // patch point intrinsic.
             let spill_slot_location = &locations[0];
             assert_matches!(
                 spill_slot_location.loc_type,
-                LocationType::Register | LocationType::Direct
+                LocationType::Register
+                    | LocationType::Direct
+                    | LocationType::Indirect
+                    | LocationType::Constant
+                    | LocationType::ConstIndex
             );
+            let spill_slot_constant = match spill_slot_location.loc_type {
+                LocationType::ConstIndex => location_constant(map, spill_slot_location).unwrap(),
+                _ => 0,
+            };
 
             assert!(locations[1].loc_type == LocationType::Constant);
             let target_value_size = locations[1].offset_or_constant;
             // The size of the recorded value must be positive.
             assert!(target_value_size > 0);
 
+            // For switches, the pass records the values selecting each successor
+            // as additional constants.
+            let llvm_instruction = LLVMInstruction::try_from(record.patch_point_id).ok();
+            let successor_values = match llvm_instruction {
+                Some(LLVMInstruction::Br | LLVMInstruction::Select) if target_value_size == 1 => {
+                    vec![1, 0]
+                }
+                Some(LLVMInstruction::Switch) if target_value_size <= 64 => {
+                    let value_mask = u64::MAX >> (64 - target_value_size);
+                    locations[2..]
+                        .iter()
+                        .map(|loc| location_constant(map, loc).unwrap() & value_mask)
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+
+            // Return value and errno sites of calls are followed by the kind of
+            // the site, the hash of the callee, and the ID of the call.
+            let call_fault = match (llvm_instruction, &locations[2..]) {
+                (Some(LLVMInstruction::Call), [kind, hash, call_id]) => Some(CallFault {
+                    kind: location_constant(map, kind).unwrap().try_into().unwrap(),
+                    callee_hash: location_constant(map, hash).unwrap(),
+                    call_id: location_constant(map, call_id).unwrap(),
+                }),
+                _ => None,
+            };
+
             let mut vma =
                 (function.function_address as usize + record.instruction_offset as usize) as u64;
             // Rebased function address
@@ -214,9 +350,13 @@ pub fn from_stackmap(
                 vma,
                 record.patch_point_id,
                 *spill_slot_location,
+                spill_slot_constant,
                 target_value_size.try_into().unwrap(),
+                successor_values,
+                call_fault,
                 mapping.clone(),
                 function_address,
+                function_hashes.get(&function.function_address).copied(),
             );
 
             // if !seen_vmas.insert(pp.vma()) {
@@ -231,6 +371,95 @@ pub fn from_stackmap(
     patch_points
 }
 
+/// Get the [callee_hash] of the name of each function defined in `elf_file`, keyed
+/// by the (unrebased) address of the function.
+fn function_hashes(elf_file: &elf::ElfBytes<elf::endian::AnyEndian>) -> HashMap<u64, u64> {
+    let mut ret = HashMap::new();
+    for table in [elf_file.symbol_table(), elf_file.dynamic_symbol_table()] {
+        let Ok(Some((symbols, strings))) = table else {
+            continue;
+        };
+        for symbol in symbols.iter() {
+            if symbol.st_symtype() != elf::abi::STT_FUNC || symbol.st_value == 0 {
+                continue;
+            }
+            if let Ok(name) = strings.get(symbol.st_name as usize) {
+                ret.entry(symbol.st_value)
+                    .or_insert_with(|| callee_hash(name));
+            }
+        }
+    }
+    ret
+}
+
+/// Identifies a [MutationSite] across builds of the same binary. In contrast to the
+/// [MutationSiteID], it does not depend on addresses, but on the function the site
+/// belongs to and the position of the site among the similar sites of that function.
+/// Thus, sites of functions that did not change between two builds are matched.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StableSiteId {
+    /// The file name of the binary the site belongs to.
+    pub module: String,
+    /// See [MutationSite::function_hash].
+    pub function_hash: u64,
+    pub llvm_instruction: LLVMInstruction,
+    pub target_value_size_in_bit: u32,
+    pub call_fault: Option<CallFault>,
+    /// The index of the site among the sites of the same function that agree in
+    /// all other fields, ordered by address.
+    pub ordinal: usize,
+}
+
+/// Get the [StableSiteId] of each site in `sites`. Sites whose function has no
+/// symbol do not have a stable identity and are skipped.
+pub fn stable_site_ids(sites: &[MutationSite]) -> HashMap<MutationSiteID, StableSiteId> {
+    let module_name = |site: &MutationSite| {
+        site.mapping()
+            .filename()
+            .as_deref()
+            .and_then(|path| Path::new(path).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let mut sites = sites
+        .iter()
+        .map(|site| (module_name(site), site))
+        .collect::<Vec<_>>();
+    sites.sort_by_key(|(module, site)| (module.clone(), site.address()));
+
+    let mut ordinals = HashMap::new();
+    let mut ret = HashMap::new();
+    for (module, site) in sites {
+        let Some(function_hash) = site.function_hash() else {
+            continue;
+        };
+        let mut stable_id = StableSiteId {
+            module,
+            function_hash,
+            llvm_instruction: site.llvm_ins(),
+            target_value_size_in_bit: site.target_value_size_bit(),
+            call_fault: site.call_fault(),
+            ordinal: 0,
+        };
+        let ordinal = ordinals.entry(stable_id.clone()).or_insert(0);
+        stable_id.ordinal = *ordinal;
+        *ordinal += 1;
+        ret.insert(site.id(), stable_id);
+    }
+    ret
+}
+
+/// Get the value of a Constant or ConstIndex location. Large constants are not
+/// encoded in the location, but stored in the constants table of the stack map.
+fn location_constant(map: &StackMap, location: &Location) -> Option<u64> {
+    match location.loc_type {
+        LocationType::Constant => Some(location.offset_or_constant as i64 as u64),
+        LocationType::ConstIndex => Some(map.large_constants[location.offset_or_constant as usize]),
+        _ => None,
+    }
+}
+
 pub fn elf_is_pic(path: impl AsRef<Path>) -> Option<bool> {
     let data = fs::read(path).unwrap();
     let file = match elf::ElfBytes::<elf::endian::AnyEndian>::minimal_parse(&data) {
@@ -242,7 +471,7 @@ pub fn elf_is_pic(path: impl AsRef<Path>) -> Option<bool> {
 
 impl From<&MutationSite> for Box<MutationCacheEntry> {
     fn from(pp: &MutationSite) -> Self {
-        MutationCacheEntry::new(
+        let mut entry = MutationCacheEntry::new(
             pp.id(),
             pp.llvm_ins(),
             pp.vma(),
@@ -250,6 +479,46 @@ impl From<&MutationSite> for Box<MutationCacheEntry> {
             *pp.spill_slot(),
             pp.target_value_size_bit(),
             0,
-        )
+        );
+        entry.set_spill_slot_constant(pp.spill_slot_constant());
+        entry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::location_constant;
+    use crate::llvm_stackmap::{Location, LocationType, StackMap};
+
+    fn location(loc_type: LocationType, offset_or_constant: i32) -> Location {
+        Location {
+            loc_type,
+            loc_size: 8,
+            offset_or_constant,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_location_constant() {
+        let mut map = StackMap::default();
+        map.large_constants = vec![0x1122_3344_5566_7788, u64::MAX - 1];
+
+        let constant = location(LocationType::Constant, 42);
+        assert_eq!(location_constant(&map, &constant), Some(42));
+        // Small constants are sign extended.
+        let constant = location(LocationType::Constant, -2);
+        assert_eq!(location_constant(&map, &constant), Some(u64::MAX - 1));
+
+        let const_index = location(LocationType::ConstIndex, 0);
+        assert_eq!(
+            location_constant(&map, &const_index),
+            Some(0x1122_3344_5566_7788)
+        );
+        let const_index = location(LocationType::ConstIndex, 1);
+        assert_eq!(location_constant(&map, &const_index), Some(u64::MAX - 1));
+
+        let indirect = location(LocationType::Indirect, 0);
+        assert_eq!(location_constant(&map, &indirect), None);
     }
 }
@@ -1,24 +1,151 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use nix::sys::signal::Signal;
 use serde::Serialize;
 use std::{
-    fs,
-    process::{Child, Command},
+    collections::HashSet,
+    fs, io,
+    os::unix::process::ExitStatusExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    config::Config,
+    config::{Config, HelperConfig, SessionMode, TopologyConfig, TopologyPeer},
     io_channels::OutputChannel,
+    packet_capture::{CaptureFilter, PacketCapture},
     sink::{self, AflSink},
     source::{self, Source},
 };
 
+/// The socket event after which a peer is considered ready. The argument selects
+/// the n-th (zero based) occurrence of the event during an execution.
 #[derive(Debug, Clone, Copy, Serialize, PartialEq)]
 pub enum ServerReadySignalKind {
+    /// The server returned from bind().
     Bind(usize),
+    /// The server returned from listen().
     Listen(usize),
+    /// The server entered a (blocking) accept().
+    Accept(usize),
+    /// The client returned from connect(). Only meaningful for client peers.
+    Connect(usize),
+}
+
+/// Socket events reported by the agent (Source) or the consumer runtime (AflSink).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketEvent {
+    Bind,
+    Listen,
+    Accept,
+    Connect,
+    /// A connected socket was closed.
+    Close,
+    /// Data was send or received via a connected socket.
+    Data,
+}
+
+impl SocketEvent {
+    fn nth(self, n: usize) -> Option<ServerReadySignalKind> {
+        match self {
+            SocketEvent::Bind => Some(ServerReadySignalKind::Bind(n)),
+            SocketEvent::Listen => Some(ServerReadySignalKind::Listen(n)),
+            SocketEvent::Accept => Some(ServerReadySignalKind::Accept(n)),
+            SocketEvent::Connect => Some(ServerReadySignalKind::Connect(n)),
+            SocketEvent::Close | SocketEvent::Data => None,
+        }
+    }
+}
+
+/// Number of socket events a peer reported during its current execution.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SocketEventCounters {
+    bind: usize,
+    listen: usize,
+    accept: usize,
+    connect: usize,
+    close: usize,
+    data: usize,
+}
+
+impl SocketEventCounters {
+    /// Record `event` and return whether it is the event `ready_on` is waiting for.
+    pub fn record(&mut self, event: SocketEvent, ready_on: ServerReadySignalKind) -> bool {
+        let nth = self.count(event);
+        self.add(event);
+        event.nth(nth) == Some(ready_on)
+    }
+
+    /// Record `event` without waiting for any specific event.
+    pub fn add(&mut self, event: SocketEvent) {
+        *self.counter_mut(event) += 1;
+    }
+
+    /// Number of times `event` was recorded so far.
+    pub fn count(&self, event: SocketEvent) -> usize {
+        match event {
+            SocketEvent::Bind => self.bind,
+            SocketEvent::Listen => self.listen,
+            SocketEvent::Accept => self.accept,
+            SocketEvent::Connect => self.connect,
+            SocketEvent::Close => self.close,
+            SocketEvent::Data => self.data,
+        }
+    }
+
+    fn counter_mut(&mut self, event: SocketEvent) -> &mut usize {
+        match event {
+            SocketEvent::Bind => &mut self.bind,
+            SocketEvent::Listen => &mut self.listen,
+            SocketEvent::Accept => &mut self.accept,
+            SocketEvent::Connect => &mut self.connect,
+            SocketEvent::Close => &mut self.close,
+            SocketEvent::Data => &mut self.data,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The connections a peer opened during its current execution, identified by
+/// their fd.
+#[derive(Debug, Default, Clone)]
+pub struct OpenConnections {
+    fds: HashSet<i32>,
+    /// Number of connections opened so far, including the closed ones.
+    opened: usize,
+}
+
+impl OpenConnections {
+    /// Record that the connection `fd` was established.
+    pub fn open(&mut self, fd: i32) {
+        self.fds.insert(fd);
+        self.opened += 1;
+    }
+
+    /// Record that the connection `fd` was closed.
+    pub fn close(&mut self, fd: i32) {
+        if !self.fds.remove(&fd) {
+            log::trace!("Closed connection {fd} was never reported as opened");
+        }
+    }
+
+    /// Number of connections that are currently open.
+    pub fn open_count(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// Number of connections opened so far, including the closed ones.
+    pub fn opened_count(&self) -> usize {
+        self.opened
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -89,20 +216,62 @@ impl Client<'_> {
     }
 
     pub fn wait_until_connect(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+        self.wait_for_connection(timeout, 0)
+    }
+
+    /// Wait until the `nth` (zero based) connection to the server is established.
+    pub fn wait_for_connection(
+        &mut self,
+        timeout: Duration,
+        nth: usize,
+    ) -> Result<WaitForPeerResult> {
         match self {
             Client::Source(source) => {
-                let source_uses_tcp = source.config().source.output_type == OutputChannel::Tcp;
-                if !source.config().source.is_server.unwrap_or(false) && source_uses_tcp {
-                    source.wait_until_connect(timeout)
+                let source_connects = matches!(
+                    source.config().source.output_type,
+                    OutputChannel::Tcp | OutputChannel::Unix
+                );
+                if !source.config().source.is_server.unwrap_or(false) && source_connects {
+                    source.wait_for_connection(timeout, nth)
                 } else {
                     Ok(WaitForPeerResult::Ready)
                 }
             }
-            Client::AflSink(_) => {
-                // not implemented yet
-                Ok(WaitForPeerResult::Ready)
+            Client::AflSink(sink) => sink.wait_for_connection(timeout, nth),
+        }
+    }
+
+    /// Wait until all connections of the session configured in `config` are established.
+    /// Without a session config, this is the same as [Client::wait_until_connect].
+    /// In [SessionMode::Concurrent], the session is established with its first
+    /// connection, since the others may be opened at any time while it is open.
+    pub fn wait_until_session_established(
+        &mut self,
+        config: &Config,
+        timeout: Duration,
+    ) -> Result<WaitForPeerResult> {
+        let session = match config.session.as_ref() {
+            Some(session) => session,
+            None => return self.wait_until_connect(timeout),
+        };
+
+        let connections = match session.mode {
+            SessionMode::Sequential | SessionMode::SharedTimeout => session.connections,
+            SessionMode::Concurrent => 1,
+        };
+        let deadline = Instant::now() + timeout;
+        for nth in 0..connections {
+            let timeout = match session.mode {
+                SessionMode::Sequential | SessionMode::Concurrent => timeout,
+                SessionMode::SharedTimeout => deadline.saturating_duration_since(Instant::now()),
+            };
+            let ret = self.wait_for_connection(timeout, nth)?;
+            if ret != WaitForPeerResult::Ready {
+                log::trace!("Connection {nth} of the session was not established: {ret:?}");
+                return Ok(ret);
             }
         }
+        Ok(WaitForPeerResult::Ready)
     }
 
     pub fn wait_for_child_termination(
@@ -169,6 +338,242 @@ impl Server<'_> {
     }
 }
 
+/// Interval in which we check whether a helper got ready.
+const HELPER_READY_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// An uninstrumented peer of a topology. Since it reports no socket events, its
+/// readiness is derived from the sockets listed in `/proc/net`.
+#[derive(Debug)]
+pub struct HelperPeer {
+    config: HelperConfig,
+    child: Option<Child>,
+}
+
+impl HelperPeer {
+    pub fn new(config: &HelperConfig) -> HelperPeer {
+        HelperPeer {
+            config: config.clone(),
+            child: None,
+        }
+    }
+
+    pub fn spawn(&mut self) -> Result<()> {
+        log::trace!("Spawning helper {:?}", self.config.bin_path);
+        let mut cmd = Command::new(&self.config.bin_path);
+        cmd.args(&self.config.arguments)
+            .envs(self.config.env.iter().cloned())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(working_dir) = self.config.working_dir.as_ref() {
+            cmd.current_dir(working_dir);
+        }
+
+        let child = cmd
+            .spawn()
+            .context(format!("Failed to spawn helper {:?}", self.config.bin_path))?;
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Wait until the helper has a socket on its server port that matches `ready_on`.
+    /// Bind is satisfied by any TCP or UDP socket, Listen only by listening TCP sockets.
+    pub fn wait_until_ready(
+        &mut self,
+        timeout: Duration,
+        ready_on: ServerReadySignalKind,
+    ) -> Result<WaitForPeerResult> {
+        let port = self
+            .config
+            .server_addr()
+            .expect("Helpers that have a ready-on event need a server address")
+            .port();
+        let listening = match ready_on {
+            ServerReadySignalKind::Listen(_) => true,
+            ServerReadySignalKind::Bind(_) => false,
+            _ => unreachable!("Unsupported ready-on event for helper: {:?}", ready_on),
+        };
+
+        let child = self.child.as_mut().expect("spawn() must be called first");
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                log::trace!("Helper terminated before getting ready: {status:?}");
+                self.child.take();
+                return Ok(match (status.code(), status.signal()) {
+                    (Some(code), _) => WaitForPeerResult::Terminated(code),
+                    (_, Some(signal)) => WaitForPeerResult::Signalled(
+                        Signal::try_from(signal).unwrap_or(Signal::SIGKILL),
+                    ),
+                    _ => unreachable!(),
+                });
+            }
+            if port_in_use(port, listening) {
+                return Ok(WaitForPeerResult::Ready);
+            }
+            if Instant::now() > deadline {
+                self.kill();
+                return Ok(WaitForPeerResult::TimedOut);
+            }
+            thread::sleep(HELPER_READY_POLL_INTERVAL);
+        }
+    }
+
+    /// Kill the helper (if still running) and wait for it to terminate.
+    pub fn kill(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for HelperPeer {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Whether any socket in our network namespace is bound to the local `port`. If `listening`
+/// is set, only TCP sockets in LISTEN state are considered.
+fn port_in_use(port: u16, listening: bool) -> bool {
+    // Socket state as found in the `st` column.
+    const TCP_LISTEN: &str = "0A";
+
+    let tables: &[&str] = if listening {
+        &["/proc/net/tcp", "/proc/net/tcp6"]
+    } else {
+        &[
+            "/proc/net/tcp",
+            "/proc/net/tcp6",
+            "/proc/net/udp",
+            "/proc/net/udp6",
+        ]
+    };
+
+    tables.iter().any(|table| {
+        let content = match fs::read_to_string(table) {
+            Ok(content) => content,
+            Err(_) => return false,
+        };
+        // The first line is a header. Each entry looks like
+        // `0: 0100007F:1F90 00000000:0000 0A ...` (sl, local_address, rem_address, st, ...).
+        content.lines().skip(1).any(|line| {
+            let columns = line.split_whitespace().collect::<Vec<_>>();
+            let local_port = columns
+                .get(1)
+                .and_then(|addr| addr.rsplit_once(':'))
+                .and_then(|(_, port)| u16::from_str_radix(port, 16).ok());
+            let state = columns.get(3);
+            local_port == Some(port)
+                && (!listening || state.is_some_and(|state| *state == TCP_LISTEN))
+        })
+    })
+}
+
+/// Remove the socket file left behind by the previous server instance. Otherwise,
+/// the next bind() on the same path fails with EADDRINUSE.
+pub(crate) fn remove_stale_unix_socket(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(anyhow!(err).context(format!(
+            "Failed to remove stale unix socket at {}",
+            path.display()
+        ))),
+    }
+}
+
+#[derive(Debug)]
+pub enum TopologySpawnResult {
+    /// All peers are running. The helper peers are killed as soon as they are dropped.
+    Ready(Vec<HelperPeer>),
+    /// A peer did not get ready and all peers spawned so far were killed.
+    PeerNotReady {
+        /// Whether this is the last peer, i.e., the one that initiates the session.
+        is_last: bool,
+        result: WaitForPeerResult,
+    },
+}
+
+/// Spawn the peers described by `topology` in order. Each peer is only spawned after
+/// the previous one got ready.
+pub fn spawn_topology(
+    topology: &TopologyConfig,
+    source: &mut Source,
+    sink: &mut AflSink,
+    timeout: Duration,
+) -> Result<TopologySpawnResult> {
+    let mut helpers = Vec::new();
+    let mut source_running = false;
+    let mut sink_running = false;
+
+    for (idx, peer) in topology.peers.iter().enumerate() {
+        let ret = match &peer.peer {
+            TopologyPeer::Source => {
+                source.spawn(timeout).context("Executing source peer")?;
+                source_running = true;
+                match peer.ready_on {
+                    Some(ready_on) => source.wait_for_socket_event(timeout, ready_on)?,
+                    None => WaitForPeerResult::Ready,
+                }
+            }
+            TopologyPeer::Sink => {
+                sink.spawn_child().context("Executing sink peer")?;
+                sink_running = true;
+                match peer.ready_on {
+                    Some(ready_on) => sink.wait_for_socket_event(timeout, ready_on)?,
+                    None => WaitForPeerResult::Ready,
+                }
+            }
+            TopologyPeer::Helper(helper_config) => {
+                let mut helper = HelperPeer::new(helper_config);
+                helper.spawn()?;
+                let ret = match peer.ready_on {
+                    Some(ready_on) => helper.wait_until_ready(timeout, ready_on)?,
+                    None => WaitForPeerResult::Ready,
+                };
+                helpers.push(helper);
+                ret
+            }
+        };
+
+        if ret != WaitForPeerResult::Ready {
+            log::trace!("Peer {idx} ({:?}) did not get ready: {ret:?}", peer.peer);
+            // The peer that did not get ready is already gone.
+            if source_running && !matches!(peer.peer, TopologyPeer::Source) {
+                source.wait_for_child_termination(timeout, true)?;
+            }
+            if sink_running && !matches!(peer.peer, TopologyPeer::Sink) {
+                sink.wait_for_child_termination(timeout, true, None)?;
+            }
+            return Ok(TopologySpawnResult::PeerNotReady {
+                is_last: idx == topology.peers.len() - 1,
+                result: ret,
+            });
+        }
+    }
+
+    Ok(TopologySpawnResult::Ready(helpers))
+}
+
+/// Wait for the consumer to terminate. If a session or an idle timeout is configured,
+/// the consumer is terminated as soon as the session ended. The socket activity of
+/// the `producer` is taken into account to decide whether the session is idle.
+pub fn wait_for_consumer_termination(
+    consumer: &mut AflSink,
+    producer: &mut Source,
+    config: &Config,
+    timeout: Duration,
+    issue_sigterm_after: Option<Duration>,
+) -> Result<sink::RunResult> {
+    if config.detects_session_end() {
+        consumer.wait_for_session_end(timeout, Some(producer))
+    } else {
+        consumer.wait_for_child_termination(timeout, false, issue_sigterm_after)
+    }
+}
+
 pub fn get_consumer<'a>(client: &'a mut Client, server: &'a mut Server) -> &'a mut AflSink {
     if let Client::AflSink(ref mut sink) = client {
         return sink;
@@ -178,6 +583,19 @@ pub fn get_consumer<'a>(client: &'a mut Client, server: &'a mut Server) -> &'a m
     unreachable!()
 }
 
+/// Get the consumer and the producer at once, e.g., to pass both to
+/// [wait_for_consumer_termination].
+pub fn get_consumer_and_producer<'a>(
+    client: &'a mut Client,
+    server: &'a mut Server,
+) -> (&'a mut AflSink, &'a mut Source) {
+    match (client, server) {
+        (Client::AflSink(sink), Server::Source(source)) => (sink, source),
+        (Client::Source(source), Server::AflSink(sink)) => (sink, source),
+        _ => unreachable!(),
+    }
+}
+
 pub fn get_producer<'a>(client: &'a mut Client, server: &'a mut Server) -> &'a mut Source {
     if let Client::Source(ref mut source) = client {
         return source;
@@ -187,39 +605,55 @@ pub fn get_producer<'a>(client: &'a mut Client, server: &'a mut Server) -> &'a m
     unreachable!()
 }
 
-pub fn configure_tcpdump(
+/// A packet capture whose recording is written to the `pcaps` folder of the work
+/// directory once it is finished.
+#[derive(Debug)]
+pub struct PcapRecording {
+    capture: PacketCapture,
+    path: PathBuf,
+}
+
+impl PcapRecording {
+    /// Stop the capture and write the recording to disk.
+    pub fn finish(self) -> Result<PathBuf> {
+        let pcap = self.capture.stop()?.to_pcapng();
+        fs::write(&self.path, pcap).context(format!("Failed to write pcap to {:?}", self.path))?;
+        log::info!("Recorded pcap written to {:?}", self.path);
+        Ok(self.path)
+    }
+}
+
+pub fn configure_pcap_recording(
     config: &Config,
     prefix: Option<String>,
     include_answers: bool,
-) -> Option<Child> {
+) -> Result<Option<PcapRecording>> {
+    if config.target_uses_unix_socket() {
+        log::warn!("Recording pcaps is not supported for targets using unix sockets");
+        return Ok(None);
+    }
+
+    let filter = CaptureFilter::from_config(config, include_answers)
+        .context("Server address not set in the config")?;
     let dst_port = config
-        .server_port()
-        .expect("Server port not set in the config");
+        .server_addr()
+        .context("Server address not set in the config")?
+        .port();
 
-    let mut pcap_dir = config.general.work_dir.clone();
-    pcap_dir.push("pcaps");
-    fs::create_dir_all(&pcap_dir).unwrap();
+    let mut pcap_path = config.general.work_dir.clone();
+    pcap_path.push("pcaps");
+    fs::create_dir_all(&pcap_path)
+        .with_context(|| format!("Failed to create pcap directory {pcap_path:?}"))?;
 
-    let mut pcap_path = pcap_dir.clone();
-    let mut fname = format!("dst-port-{dst_port}.pcap");
+    let mut fname = format!("dst-port-{dst_port}.pcapng");
     if let Some(prefix) = prefix {
         fname = format!("{}-{}", prefix, fname);
     }
     pcap_path.push(fname);
 
-    let mut cmd = Command::new("/usr/bin/tcpdump");
-    cmd.current_dir(&pcap_dir);
-    if include_answers {
-        cmd.args(["-v", &format!("port {dst_port}")]);
-    } else {
-        cmd.args(["-v", &format!("dst port {dst_port}")]);
-    }
-
-    cmd.args(["-i", "lo", "-U"]);
-    cmd.args(["-w", pcap_path.to_str().unwrap()]);
-
-    let child = Some(cmd.spawn().unwrap());
-    // Give tcpflow some time to spin up
-    thread::sleep(Duration::from_secs(2));
-    child
+    let capture = PacketCapture::start(filter).context("Failed to start packet capture")?;
+    Ok(Some(PcapRecording {
+        capture,
+        path: pcap_path,
+    }))
 }
@@ -0,0 +1,536 @@
+//! Recording of the network traffic between the peers of networked targets.
+//! Packets are captured via an AF_PACKET socket that is created in the network
+//! namespace of the calling thread and are stored in the pcapng format.
+
+use std::{
+    ffi::CStr,
+    io, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{config::Config, transcript::Transcript};
+
+/// Timeout of a single receive call. This bounds how long stopping a capture takes.
+const RECV_TIMEOUT: Duration = Duration::from_millis(10);
+/// Maximum number of bytes recorded per packet.
+const SNAPLEN: usize = 65535;
+/// `sll_pkttype` of packets sent by this host, see packet(7). Not exported by
+/// the libc version we depend on.
+const PACKET_OUTGOING: u8 = 4;
+
+/// Raw IPv4 or IPv6 packets without any link-layer header.
+const LINKTYPE_RAW: u16 = 101;
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const PCAPNG_INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+pub(crate) const IPPROTO_TCP: u8 = 6;
+pub(crate) const IPPROTO_UDP: u8 = 17;
+
+/// Describes which packets are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureFilter {
+    /// The host the server is listening on. If it is unspecified (0.0.0.0 or ::),
+    /// packets to and from any host are recorded.
+    pub host: IpAddr,
+    /// The port the server is listening on. If not set, all packets sent to `host`
+    /// are recorded.
+    pub port: Option<u16>,
+    /// Whether packets sent by the server are recorded as well.
+    pub include_answers: bool,
+}
+
+impl CaptureFilter {
+    /// Get the filter matching the traffic of the target described by `config`.
+    /// Returns None if the target does not use the network or uses AF_UNIX sockets,
+    /// since such traffic can not be captured.
+    pub fn from_config(config: &Config, include_answers: bool) -> Option<CaptureFilter> {
+        if !config.target_uses_network() || config.target_uses_unix_socket() {
+            return None;
+        }
+        let server_addr = config.server_addr()?;
+        // Sessions and topologies may use additional connections to other ports
+        // (e.g., FTP data channels), thus we do not filter by port in that case.
+        let port =
+            (config.session.is_none() && config.topology.is_none()).then_some(server_addr.port());
+        Some(CaptureFilter {
+            host: server_addr.ip(),
+            port,
+            include_answers,
+        })
+    }
+
+    /// Whether `packet`, a raw IPv4 or IPv6 packet, passes the filter.
+    fn matches(&self, packet: &[u8]) -> bool {
+        let Some(packet) = IpPacket::parse(packet) else {
+            return false;
+        };
+
+        let matches_endpoint = |addr: IpAddr, port: Option<u16>| {
+            (self.host.is_unspecified() || addr == self.host)
+                && self.port.map_or(true, |p| port == Some(p))
+        };
+        let (src_port, dst_port) = match packet.ports {
+            Some((src_port, dst_port)) => (Some(src_port), Some(dst_port)),
+            None => (None, None),
+        };
+
+        matches_endpoint(packet.dst, dst_port)
+            || (self.include_answers && matches_endpoint(packet.src, src_port))
+    }
+}
+
+/// The parts of a TCP header we care about.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TcpHeader {
+    pub seq: u32,
+    pub syn: bool,
+}
+
+/// The parts of a raw IPv4 or IPv6 packet we care about.
+#[derive(Debug)]
+pub(crate) struct IpPacket<'a> {
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub protocol: u8,
+    /// Source and destination port for TCP and UDP packets.
+    pub ports: Option<(u16, u16)>,
+    /// Set for TCP packets.
+    pub tcp: Option<TcpHeader>,
+    /// The application data carried by TCP and UDP packets.
+    pub payload: &'a [u8],
+}
+
+impl IpPacket<'_> {
+    /// Parse a raw IP packet. IPv6 extension headers are not supported, thus such
+    /// packets carry neither ports nor payload.
+    pub fn parse(packet: &[u8]) -> Option<IpPacket> {
+        let version = packet.first()? >> 4;
+        let (src, dst, protocol, l4) = match version {
+            4 => {
+                let header_len = ((packet[0] & 0x0f) as usize) * 4;
+                if header_len < 20 || packet.len() < header_len {
+                    return None;
+                }
+                let src: [u8; 4] = packet[12..16].try_into().unwrap();
+                let dst: [u8; 4] = packet[16..20].try_into().unwrap();
+                (
+                    IpAddr::from(Ipv4Addr::from(src)),
+                    IpAddr::from(Ipv4Addr::from(dst)),
+                    packet[9],
+                    &packet[header_len..],
+                )
+            }
+            6 => {
+                if packet.len() < 40 {
+                    return None;
+                }
+                let src: [u8; 16] = packet[8..24].try_into().unwrap();
+                let dst: [u8; 16] = packet[24..40].try_into().unwrap();
+                (
+                    IpAddr::from(Ipv6Addr::from(src)),
+                    IpAddr::from(Ipv6Addr::from(dst)),
+                    packet[6],
+                    &packet[40..],
+                )
+            }
+            _ => return None,
+        };
+
+        let mut tcp = None;
+        let (ports, payload) = match protocol {
+            IPPROTO_TCP | IPPROTO_UDP if l4.len() >= 4 => {
+                let ports = (
+                    u16::from_be_bytes([l4[0], l4[1]]),
+                    u16::from_be_bytes([l4[2], l4[3]]),
+                );
+                let header_len = if protocol == IPPROTO_TCP {
+                    if l4.len() < 20 {
+                        return None;
+                    }
+                    tcp = Some(TcpHeader {
+                        seq: u32::from_be_bytes(l4[4..8].try_into().unwrap()),
+                        syn: l4[13] & 0x02 != 0,
+                    });
+                    ((l4[12] >> 4) as usize) * 4
+                } else {
+                    8
+                };
+                (Some(ports), l4.get(header_len..).unwrap_or_default())
+            }
+            _ => (None, &[][..]),
+        };
+        Some(IpPacket {
+            src,
+            dst,
+            protocol,
+            ports,
+            tcp,
+            payload,
+        })
+    }
+}
+
+/// A single captured packet.
+#[derive(Debug)]
+struct Packet {
+    /// Time since the epoch at which the packet was received.
+    timestamp: Duration,
+    /// Length of the packet on the wire. This may exceed `data.len()` if the packet
+    /// was truncated to [SNAPLEN].
+    original_len: usize,
+    data: Vec<u8>,
+}
+
+/// The packets recorded by a [PacketCapture].
+#[derive(Debug)]
+pub struct Recording {
+    packets: Vec<Packet>,
+}
+
+impl Recording {
+    pub fn to_pcapng(&self) -> Vec<u8> {
+        serialize_pcapng(&self.packets)
+    }
+
+    /// Get the transcript of the messages recorded at or after `since`.
+    pub fn transcript(&self, since: SystemTime) -> Transcript {
+        let since = since.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let packets = self
+            .packets
+            .iter()
+            .filter(|packet| packet.timestamp >= since)
+            .filter_map(|packet| Some((packet.timestamp, IpPacket::parse(&packet.data)?)));
+        Transcript::from_packets(packets)
+    }
+}
+
+/// A running packet capture. The capture ends if either [PacketCapture::stop] is
+/// called or the capture is dropped.
+#[derive(Debug)]
+pub struct PacketCapture {
+    stop_requested: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Result<Vec<Packet>>>>,
+}
+
+impl PacketCapture {
+    /// Start capturing packets that pass `filter`. All packets sent after this
+    /// function returned are recorded.
+    pub fn start(filter: CaptureFilter) -> Result<PacketCapture> {
+        let loopback_idx = interface_index(CStr::from_bytes_with_nul(b"lo\0").unwrap())?;
+        // Traffic to loopback addresses only shows up on `lo`, everything else might
+        // be routed over any interface.
+        let ifindex = if filter.host.is_loopback() {
+            loopback_idx
+        } else {
+            0
+        };
+        let socket = open_packet_socket(ifindex).context("Failed to open AF_PACKET socket")?;
+
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let stop_requested_clone = stop_requested.clone();
+        let thread = thread::Builder::new()
+            .name("packet-capture".to_owned())
+            .spawn(move || capture_packets(&socket, filter, loopback_idx, &stop_requested_clone))?;
+
+        Ok(PacketCapture {
+            stop_requested,
+            thread: Some(thread),
+        })
+    }
+
+    /// Stop the capture and return the recorded packets. Packets that have already
+    /// been received by the kernel are still recorded.
+    pub fn stop(mut self) -> Result<Recording> {
+        let packets = self.join()?;
+        log::trace!("Captured {} packets", packets.len());
+        Ok(Recording { packets })
+    }
+
+    fn join(&mut self) -> Result<Vec<Packet>> {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        let thread = self.thread.take().expect("Capture already stopped");
+        thread
+            .join()
+            .map_err(|_| anyhow!("Packet capture thread panicked"))?
+    }
+}
+
+impl Drop for PacketCapture {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            let _ = self.join();
+        }
+    }
+}
+
+fn interface_index(name: &CStr) -> Result<libc::c_int> {
+    let idx = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if idx == 0 {
+        return Err(io::Error::last_os_error())
+            .context(format!("Failed to get index of interface {name:?}"));
+    }
+    Ok(idx.try_into().unwrap())
+}
+
+/// Open an AF_PACKET socket that receives the network-layer packets of the
+/// interface with index `ifindex`. An index of zero selects all interfaces.
+fn open_packet_socket(ifindex: libc::c_int) -> Result<OwnedFd> {
+    let protocol = (libc::ETH_P_ALL as u16).to_be();
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_PACKET,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            protocol.into(),
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error()).context("socket() failed");
+    }
+    // Safety: `fd` is a valid file descriptor that is not owned by anyone else.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as u16;
+    addr.sll_protocol = protocol;
+    addr.sll_ifindex = ifindex;
+    let ret = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error()).context("bind() failed");
+    }
+
+    let timeout = libc::timeval {
+        tv_sec: 0,
+        tv_usec: RECV_TIMEOUT.as_micros().try_into().unwrap(),
+    };
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &timeout as *const libc::timeval as *const libc::c_void,
+            mem::size_of::<libc::timeval>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error()).context("setsockopt(SO_RCVTIMEO) failed");
+    }
+
+    Ok(socket)
+}
+
+/// Receive packets until `stop_requested` is set and the socket's receive queue
+/// is drained.
+fn capture_packets(
+    socket: &OwnedFd,
+    filter: CaptureFilter,
+    loopback_idx: libc::c_int,
+    stop_requested: &AtomicBool,
+) -> Result<Vec<Packet>> {
+    let mut packets = Vec::new();
+    let mut buf = vec![0u8; SNAPLEN];
+
+    loop {
+        let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // MSG_TRUNC causes the original length to be returned, even if the packet
+        // did not fit into `buf`.
+        let ret = unsafe {
+            libc::recvfrom(
+                socket.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                libc::MSG_TRUNC,
+                &mut addr as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut addr_len,
+            )
+        };
+
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                    if stop_requested.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    continue;
+                }
+                io::ErrorKind::Interrupted => continue,
+                _ => return Err(err).context("recvfrom() failed"),
+            }
+        }
+
+        // Packets on `lo` are seen twice, once when sent and once when received.
+        if addr.sll_ifindex == loopback_idx && addr.sll_pkttype == PACKET_OUTGOING {
+            continue;
+        }
+
+        let original_len = ret as usize;
+        let data = &buf[..original_len.min(buf.len())];
+        if !filter.matches(data) {
+            continue;
+        }
+
+        packets.push(Packet {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            original_len,
+            data: data.to_vec(),
+        });
+    }
+
+    Ok(packets)
+}
+
+/// Append a pcapng block with the given `block_type` and `body` to `out`.
+fn push_pcapng_block(out: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = (12 + body.len() + padding) as u32;
+    out.extend_from_slice(&block_type.to_le_bytes());
+    out.extend_from_slice(&total_len.to_le_bytes());
+    out.extend_from_slice(body);
+    out.extend(std::iter::repeat(0).take(padding));
+    out.extend_from_slice(&total_len.to_le_bytes());
+}
+
+/// Serialize `packets` into a pcapng file with a single raw IP interface.
+fn serialize_pcapng(packets: &[Packet]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    // Version 1.0
+    shb.extend_from_slice(&1u16.to_le_bytes());
+    shb.extend_from_slice(&0u16.to_le_bytes());
+    // The section length is not specified.
+    shb.extend_from_slice(&(-1i64).to_le_bytes());
+    push_pcapng_block(&mut out, PCAPNG_SECTION_HEADER_BLOCK, &shb);
+
+    let mut idb = Vec::new();
+    idb.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+    idb.extend_from_slice(&0u16.to_le_bytes());
+    idb.extend_from_slice(&(SNAPLEN as u32).to_le_bytes());
+    push_pcapng_block(&mut out, PCAPNG_INTERFACE_DESCRIPTION_BLOCK, &idb);
+
+    for packet in packets {
+        // Timestamps are in microseconds, which is the default resolution.
+        let ts = packet.timestamp.as_micros() as u64;
+        let mut epb = Vec::with_capacity(20 + packet.data.len());
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(ts as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.original_len as u32).to_le_bytes());
+        epb.extend_from_slice(&packet.data);
+        push_pcapng_block(&mut out, PCAPNG_ENHANCED_PACKET_BLOCK, &epb);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::IpAddr, time::Duration};
+
+    use super::{serialize_pcapng, CaptureFilter, IpPacket, Packet};
+
+    /// An IPv4 TCP packet without payload.
+    fn tcp_packet(src: [u8; 4], src_port: u16, dst: [u8; 4], dst_port: u16) -> Vec<u8> {
+        let mut packet = vec![0u8; 40];
+        packet[0] = 0x45;
+        packet[9] = 6;
+        packet[12..16].copy_from_slice(&src);
+        packet[16..20].copy_from_slice(&dst);
+        packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+        packet[22..24].copy_from_slice(&dst_port.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn filter_by_port() {
+        let host: IpAddr = "127.0.0.1".parse().unwrap();
+        let mut filter = CaptureFilter {
+            host,
+            port: Some(4433),
+            include_answers: false,
+        };
+        let request = tcp_packet([127, 0, 0, 1], 50000, [127, 0, 0, 1], 4433);
+        let answer = tcp_packet([127, 0, 0, 1], 4433, [127, 0, 0, 1], 50000);
+        let unrelated = tcp_packet([127, 0, 0, 1], 50000, [127, 0, 0, 1], 8080);
+
+        assert!(filter.matches(&request));
+        assert!(!filter.matches(&answer));
+        assert!(!filter.matches(&unrelated));
+
+        filter.include_answers = true;
+        assert!(filter.matches(&answer));
+        assert!(!filter.matches(&unrelated));
+
+        filter.port = None;
+        assert!(filter.matches(&unrelated));
+        assert!(!filter.matches(&tcp_packet([10, 0, 0, 1], 1, [10, 0, 0, 2], 2)));
+    }
+
+    #[test]
+    fn filter_unspecified_host_matches_any_host() {
+        let filter = CaptureFilter {
+            host: "0.0.0.0".parse().unwrap(),
+            port: Some(4433),
+            include_answers: true,
+        };
+        assert!(filter.matches(&tcp_packet([10, 0, 0, 1], 50000, [10, 0, 0, 2], 4433)));
+        assert!(filter.matches(&tcp_packet([127, 0, 0, 1], 4433, [127, 0, 0, 1], 50000)));
+        assert!(!filter.matches(&tcp_packet([10, 0, 0, 1], 50000, [10, 0, 0, 2], 8080)));
+    }
+
+    #[test]
+    fn reject_ipv4_header_length_below_minimum() {
+        let mut packet = tcp_packet([127, 0, 0, 1], 50000, [127, 0, 0, 1], 4433);
+        assert!(IpPacket::parse(&packet).is_some());
+        for ihl in 0..5 {
+            packet[0] = 0x40 | ihl;
+            assert!(IpPacket::parse(&packet).is_none());
+        }
+    }
+
+    #[test]
+    fn pcapng_blocks_are_aligned() {
+        let packets = [Packet {
+            timestamp: Duration::from_secs(1),
+            original_len: 41,
+            data: vec![0x45; 41],
+        }];
+        let pcap = serialize_pcapng(&packets);
+
+        let mut offset = 0;
+        let mut block_types = Vec::new();
+        while offset < pcap.len() {
+            let block_type = u32::from_le_bytes(pcap[offset..offset + 4].try_into().unwrap());
+            let len = u32::from_le_bytes(pcap[offset + 4..offset + 8].try_into().unwrap());
+            assert_eq!(len % 4, 0);
+            let trailer = &pcap[offset + len as usize - 4..offset + len as usize];
+            assert_eq!(u32::from_le_bytes(trailer.try_into().unwrap()), len);
+            block_types.push(block_type);
+            offset += len as usize;
+        }
+        assert_eq!(offset, pcap.len());
+        assert_eq!(block_types, vec![0x0A0D_0D0A, 1, 6]);
+    }
+}
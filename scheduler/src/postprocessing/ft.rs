@@ -4,21 +4,22 @@ use std::{
         atomic::{self, AtomicBool},
         Arc,
     },
-    thread,
     time::{Duration, Instant},
 };
 
 use crate::{
     config::Config,
     fuzzer::queue::{Queue, QueueEntry, QueueEntryId},
-    networked::{configure_tcpdump, get_consumer, get_producer, Client, Server, WaitForPeerResult},
+    networked::{
+        configure_pcap_recording, get_consumer_and_producer, get_producer,
+        wait_for_consumer_termination, Client, Server, WaitForPeerResult,
+    },
     sink::AflSink,
     source::Source,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use fuzztruction_shared::mutation_cache::MutationCache;
-use nix::{sys::signal::Signal::SIGTERM, unistd::Pid};
 
 unsafe fn load_mutations(source: &mut Source, entry: &QueueEntry) -> Result<()> {
     let mutations = entry.mutations();
@@ -45,7 +46,14 @@ pub fn ft_reproduce_crashes(
     iterations: usize,
     _enable_rr: bool,
 ) -> Result<()> {
-    let tcpdump = configure_tcpdump(config, Some(format!("id:{}", target_qe_id.unwrap())), true);
+    if config.topology.is_some() {
+        return Err(anyhow!(
+            "Reproducing crashes is not supported for targets with a topology configured"
+        ));
+    }
+
+    let pcap_recording =
+        configure_pcap_recording(config, Some(format!("id:{}", target_qe_id.unwrap())), true)?;
 
     let queue_path = config.general.queue_path();
     log::info!("Loading queue at {queue_path:?} from disk...");
@@ -111,7 +119,7 @@ pub fn ft_reproduce_crashes(
         );
 
         client.spawn(timeout).unwrap();
-        let ret = client.wait_until_connect(timeout)?;
+        let ret = client.wait_until_session_established(config, timeout)?;
         match ret {
             WaitForPeerResult::Terminated(_)
             | WaitForPeerResult::Signalled(_)
@@ -123,10 +131,8 @@ pub fn ft_reproduce_crashes(
             WaitForPeerResult::Ready => (),
         }
 
-        let consumer = get_consumer(&mut client, &mut server);
-        let ret = consumer
-            .wait_for_child_termination(timeout, false, None)
-            .unwrap();
+        let (consumer, producer) = get_consumer_and_producer(&mut client, &mut server);
+        let ret = wait_for_consumer_termination(consumer, producer, config, timeout, None).unwrap();
         match ret {
             crate::sink::RunResult::Signalled(_) => {
                 log::info!("Success! Consumer crashed: {ret:?}");
@@ -152,13 +158,8 @@ pub fn ft_reproduce_crashes(
         log::info!("producer result: {:?}", producer_result);
     }
 
-    if let Some(mut tcpdump) = tcpdump {
-        thread::sleep(Duration::from_secs(2));
-        let child_pid = tcpdump.id();
-        let child_pid = Pid::from_raw(child_pid.try_into().unwrap());
-        let _ = nix::sys::signal::kill(child_pid, SIGTERM);
-        log::info!("Waiting for pcap recorder to terminate");
-        tcpdump.wait().unwrap();
+    if let Some(pcap_recording) = pcap_recording {
+        pcap_recording.finish()?;
     }
 
     Ok(())
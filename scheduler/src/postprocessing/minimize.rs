@@ -0,0 +1,448 @@
+//! Minimization of crashing queue entries. A crashing entry carries the mutations
+//! accumulated over its whole lineage, while usually only a few of them are
+//! required to trigger the crash. The mutations and the input of the source are
+//! reduced via delta debugging for as long as the sink still crashes with the
+//! same stack hash.
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use fuzztruction_shared::{
+    mutation_cache::MutationCache, mutation_cache_entry::MutationCacheEntry, types::MutationSiteID,
+};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    config::Config,
+    fuzzer::{
+        common::{common_calibrate, common_calibration_run, CalibrationError, InputType},
+        queue::{Input, Queue, QueueEntryId},
+    },
+    sanitizer::{stack_hash, STACK_HASH_FRAMES},
+    sink::{self, AflSink},
+    source::Source,
+};
+
+/// The input of the source is split into at most this many blocks.
+const MAX_INPUT_BLOCKS: usize = 256;
+
+/// A mutation that is required to trigger the crash.
+#[derive(Debug, Serialize)]
+struct EssentialPatchPoint {
+    id: MutationSiteID,
+    llvm_instruction: String,
+    operator: String,
+    vma: u64,
+    /// The mask up to its last non-zero byte, hex encoded.
+    msk: String,
+}
+
+impl From<&MutationCacheEntry> for EssentialPatchPoint {
+    fn from(entry: &MutationCacheEntry) -> Self {
+        let msk = entry.get_msk_as_slice();
+        let len = msk.iter().rposition(|b| *b != 0).map_or(0, |pos| pos + 1);
+        EssentialPatchPoint {
+            id: entry.id(),
+            llvm_instruction: format!("{:?}", entry.llvm_instruction()),
+            operator: format!("{:?}", entry.operator()),
+            vma: entry.vma(),
+            msk: hex::encode(&msk[..len]),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MinimizationReport {
+    entry_id: QueueEntryId,
+    minimized_entry_id: QueueEntryId,
+    stack_hash: String,
+    executions: usize,
+    patch_points_before: usize,
+    input_len_before: usize,
+    input_len: usize,
+    patch_points: Vec<EssentialPatchPoint>,
+}
+
+struct Minimizer<'a> {
+    config: &'a Config,
+    source: Source,
+    sink: AflSink,
+    timeout: Duration,
+    /// Number of executions a candidate has to trigger the crash.
+    attempts: usize,
+    /// The stack hash of the crash that must be preserved.
+    stack_hash: String,
+    exit_requested: Arc<AtomicBool>,
+    sink_input: Vec<u8>,
+    execs: usize,
+}
+
+impl Minimizer<'_> {
+    /// Replace the mutations of the source by `entries`.
+    fn apply(&mut self, entries: &[&MutationCacheEntry]) -> Result<()> {
+        let mc = MutationCache::from_iter(entries.iter().copied())?;
+        unsafe {
+            self.source.mutation_cache_replace(&mc)?;
+        }
+        self.source.sync_mutations()
+    }
+
+    /// Execute the source and the sink once and get the stack hash of the
+    /// sanitizer report, if the sink crashed.
+    fn run(&mut self, input: &[u8]) -> Result<Option<String>> {
+        self.execs += 1;
+        let result = common_calibration_run(
+            self.config,
+            &mut self.source,
+            &mut self.sink,
+            input,
+            self.timeout,
+            &mut self.sink_input,
+        );
+        // Always consume the report, thus it is not attributed to a later run.
+        let report = self.sink.get_latest_asan_report();
+
+        match result {
+            Ok(sink::RunResult::Signalled(_)) => {
+                Ok(report.and_then(|report| stack_hash(&report, STACK_HASH_FRAMES)))
+            }
+            Ok(_) => Ok(None),
+            Err(err) if err.downcast_ref::<CalibrationError>().is_some() => {
+                log::trace!("Execution failed: {err:#}");
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Check whether executing `input` with the mutations `entries` still
+    /// triggers the crash.
+    fn reproduces(&mut self, entries: &[&MutationCacheEntry], input: &[u8]) -> Result<bool> {
+        if self.exit_requested.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        self.apply(entries)?;
+        for _ in 0..self.attempts {
+            if self.run(input)?.as_deref() == Some(self.stack_hash.as_str()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Remove all entries that are not required to trigger the crash.
+    fn minimize_entries(
+        &mut self,
+        entries: Vec<Box<MutationCacheEntry>>,
+        input: &[u8],
+    ) -> Result<Vec<Box<MutationCacheEntry>>> {
+        let keep = ddmin(entries.len(), |keep| {
+            let candidate = keep.iter().map(|idx| entries[*idx].as_ref()).collect_vec();
+            self.reproduces(&candidate, input)
+        })?;
+
+        let mut entries = entries.into_iter().map(Some).collect_vec();
+        Ok(keep
+            .into_iter()
+            .map(|idx| entries[idx].take().unwrap())
+            .collect())
+    }
+
+    /// Zero all chunks of the masks that are not required to trigger the crash
+    /// and shrink the masks to their last non-zero chunk.
+    fn minimize_masks(
+        &mut self,
+        entries: &mut [Box<MutationCacheEntry>],
+        input: &[u8],
+    ) -> Result<()> {
+        for idx in 0..entries.len() {
+            let chunk_size = entries[idx].chunk_size_bytes().max(1) as usize;
+            let original = entries[idx].get_msk_as_slice().to_vec();
+            let chunks = original
+                .chunks(chunk_size)
+                .enumerate()
+                .filter(|(_, chunk)| chunk.iter().any(|b| *b != 0))
+                .map(|(chunk_idx, _)| chunk_idx)
+                .collect_vec();
+
+            let keep = ddmin(chunks.len(), |keep| {
+                let msk = entries[idx].get_msk_as_slice();
+                retain_chunks(msk, &original, chunk_size, keep.iter().map(|k| chunks[*k]));
+                let candidate = entries.iter().map(|e| e.as_ref()).collect_vec();
+                self.reproduces(&candidate, input)
+            })?;
+            let msk = entries[idx].get_msk_as_slice();
+            retain_chunks(msk, &original, chunk_size, keep.iter().map(|k| chunks[*k]));
+
+            let used = msk
+                .iter()
+                .rposition(|b| *b != 0)
+                .map_or(0, |pos| (pos / chunk_size + 1) * chunk_size)
+                .min(msk.len());
+            if used == 0 || used == msk.len() {
+                continue;
+            }
+            let shrunk = entries[idx].clone_with_new_msk(used as u32);
+            let candidate = entries
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    if i == idx {
+                        shrunk.as_ref()
+                    } else {
+                        e.as_ref()
+                    }
+                })
+                .collect_vec();
+            if self.reproduces(&candidate, input)? {
+                entries[idx] = shrunk;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove all blocks of `input` that are not required to trigger the crash.
+    fn minimize_input(
+        &mut self,
+        entries: &[Box<MutationCacheEntry>],
+        input: &[u8],
+    ) -> Result<Vec<u8>> {
+        let block_size = input.len().div_ceil(MAX_INPUT_BLOCKS).max(1);
+        let blocks = input.chunks(block_size).collect_vec();
+        let entries = entries.iter().map(|e| e.as_ref()).collect_vec();
+
+        let keep = ddmin(blocks.len(), |keep| {
+            let candidate = keep
+                .iter()
+                .flat_map(|idx| blocks[*idx])
+                .copied()
+                .collect_vec();
+            self.reproduces(&entries, &candidate)
+        })?;
+        Ok(keep.iter().flat_map(|idx| blocks[*idx]).copied().collect())
+    }
+}
+
+/// Set `msk` to `original` with all chunks except `chunks` zeroed.
+fn retain_chunks(
+    msk: &mut [u8],
+    original: &[u8],
+    chunk_size: usize,
+    chunks: impl Iterator<Item = usize>,
+) {
+    msk.fill(0);
+    for chunk in chunks {
+        let start = chunk * chunk_size;
+        let end = (start + chunk_size).min(msk.len());
+        msk[start..end].copy_from_slice(&original[start..end]);
+    }
+}
+
+/// Reduce the items `0..len` to a subset for which `test` still holds. Blocks of
+/// decreasing size are removed until not even a single item can be removed
+/// anymore. `test` is called with the items of a candidate in ascending order.
+fn ddmin(len: usize, mut test: impl FnMut(&[usize]) -> Result<bool>) -> Result<Vec<usize>> {
+    let mut current = (0..len).collect_vec();
+    let mut block_size = len.div_ceil(2).max(1);
+
+    loop {
+        let mut start = 0;
+        while start < current.len() {
+            let end = (start + block_size).min(current.len());
+            let candidate = current[..start]
+                .iter()
+                .chain(&current[end..])
+                .copied()
+                .collect_vec();
+            if test(&candidate)? {
+                current = candidate;
+            } else {
+                start = end;
+            }
+        }
+
+        if block_size == 1 {
+            return Ok(current);
+        }
+        block_size = block_size.div_ceil(2);
+    }
+}
+
+/// Minimize the crashing queue entry with the id `target_qe_id`. The minimized
+/// entry is added to the queue and a report of the patch points that are
+/// required to trigger the crash is written to the `minimized` directory.
+pub fn ft_minimize_crash(
+    config: &Config,
+    exit_requested: Arc<AtomicBool>,
+    timeout: Duration,
+    target_qe_id: u64,
+    attempts: usize,
+) -> Result<()> {
+    let queue_path = config.general.queue_path();
+    log::info!("Loading queue entry {target_qe_id} from {queue_path:?}...");
+    let mut queue = Queue::load(&queue_path, Some(&[target_qe_id]))?;
+    queue.reload_inputs()?;
+    let target_qe = queue
+        .entries()
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("There is no queue entry with id {target_qe_id}"))?;
+    if !target_qe.is_crash() {
+        return Err(anyhow!("Queue entry {target_qe_id} is not a crash"));
+    }
+
+    let mut source = Source::from_config(config, None, Some("crash-minimize"))?;
+    let mut sink = AflSink::from_config(config, None, Some("crash-minimize"))?;
+    source.start().context("Failed to start source")?;
+    sink.start().context("Failed to start sink")?;
+
+    let mut entries = match target_qe.mutations() {
+        Some(data) => {
+            let mut mc = MutationCache::new()?;
+            mc.load_bytes(data)?;
+            mc.iter().map(|e| e.clone_into_box()).collect_vec()
+        }
+        None => Vec::new(),
+    };
+    let patch_points_before = entries.len();
+    let input = target_qe.input_as_ref().data().to_vec();
+
+    let mut minimizer = Minimizer {
+        config,
+        source,
+        sink,
+        timeout,
+        attempts,
+        stack_hash: String::new(),
+        exit_requested: exit_requested.clone(),
+        sink_input: Vec::new(),
+        execs: 0,
+    };
+
+    minimizer.apply(&entries.iter().map(|e| e.as_ref()).collect_vec())?;
+    let mut reproduced = None;
+    for _ in 0..attempts {
+        reproduced = minimizer.run(&input)?;
+        if reproduced.is_some() {
+            break;
+        }
+    }
+    minimizer.stack_hash = reproduced.ok_or_else(|| {
+        anyhow!("Queue entry {target_qe_id} did not crash the sink with a sanitizer report")
+    })?;
+    log::info!("Reproduced crash with stack hash {}", minimizer.stack_hash);
+
+    entries = minimizer.minimize_entries(entries, &input)?;
+    log::info!(
+        "{} of {} mutation cache entries are required",
+        entries.len(),
+        patch_points_before
+    );
+    minimizer.minimize_masks(&mut entries, &input)?;
+    entries.retain(|e| !e.is_nop());
+
+    // The input of networked sources is not used.
+    let minimized_input = if config.target_uses_network() {
+        input.clone()
+    } else {
+        minimizer.minimize_input(&entries, &input)?
+    };
+    log::info!(
+        "The input was reduced from {} to {} bytes",
+        input.len(),
+        minimized_input.len()
+    );
+
+    if exit_requested.load(Ordering::Relaxed) {
+        log::warn!("Minimization was interrupted, the result is only partially minimized");
+    }
+
+    let minimized_path = config.general.minimized_path();
+    fs::create_dir_all(&minimized_path)?;
+    let qe_input = if minimized_input.len() == input.len() {
+        target_qe.input()
+    } else {
+        // The input data is not dumped with the queue, thus it needs a file to be
+        // loaded from.
+        let path = minimized_path.join(format!("{target_qe_id}.input"));
+        fs::write(&path, &minimized_input)?;
+        Input::from_path(&path)?
+    };
+
+    minimizer.apply(&entries.iter().map(|e| e.as_ref()).collect_vec())?;
+    let mut qe = common_calibrate(
+        config,
+        &mut minimizer.source,
+        &mut minimizer.sink,
+        &InputType::Input(&qe_input),
+        None,
+        None,
+        None,
+        None,
+        None,
+        true,
+        Some(target_qe.clone()),
+    )
+    .context("Failed to calibrate the minimized queue entry")?;
+    qe.set_parent(&target_qe);
+    let qe = queue.push(&qe);
+    queue.dump(&queue_path)?;
+
+    let report = MinimizationReport {
+        entry_id: target_qe.id(),
+        minimized_entry_id: qe.id(),
+        stack_hash: minimizer.stack_hash.clone(),
+        executions: minimizer.execs,
+        patch_points_before,
+        input_len_before: input.len(),
+        input_len: minimized_input.len(),
+        patch_points: entries.iter().map(|e| e.as_ref().into()).collect(),
+    };
+    let report_path = minimized_path.join(format!("{target_qe_id}.json"));
+    fs::write(&report_path, serde_json::to_vec_pretty(&report)?)?;
+
+    log::info!(
+        "Minimized queue entry {} to entry {} with {} of {} patch points in {} executions",
+        target_qe_id,
+        qe.id().0,
+        entries.len(),
+        patch_points_before,
+        minimizer.execs
+    );
+    log::info!("Report was written to {report_path:?}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ddmin, retain_chunks};
+
+    #[test]
+    fn ddmin_finds_required_items() {
+        let mut tests = 0;
+        let keep = ddmin(64, |candidate| {
+            tests += 1;
+            Ok(candidate.contains(&3) && candidate.contains(&42))
+        })
+        .unwrap();
+        assert_eq!(keep, vec![3, 42]);
+        assert!(tests < 64 * 2);
+
+        assert_eq!(ddmin(4, |_| Ok(true)).unwrap(), Vec::<usize>::new());
+        assert_eq!(ddmin(3, |c| Ok(c.len() == 3)).unwrap(), vec![0, 1, 2]);
+        assert_eq!(ddmin(0, |_| Ok(false)).unwrap(), Vec::<usize>::new());
+
+        let mut msk = [0u8; 5];
+        retain_chunks(&mut msk, &[1, 2, 3, 4, 5], 2, [0, 2].into_iter());
+        assert_eq!(msk, [1, 2, 0, 0, 5]);
+    }
+}
@@ -1,5 +1,8 @@
 mod ft;
 pub use ft::ft_reproduce_crashes;
 
+mod minimize;
+pub use minimize::ft_minimize_crash;
+
 mod pcap_extraction;
 pub use pcap_extraction::extract_pcaps;
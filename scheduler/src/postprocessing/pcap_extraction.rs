@@ -33,11 +33,18 @@ pub fn extract_pcaps(config: &Config, _exit_requested: Arc<AtomicBool>) -> Resul
         };
         let is_crash = entry.is_crash();
 
+        // Entries recorded via tcpdump use the classic pcap format.
+        let extension = if pcap.starts_with(&[0x0a, 0x0d, 0x0d, 0x0a]) {
+            "pcapng"
+        } else {
+            "pcap"
+        };
         let dst_name = format!(
-            "id:{},ts:{},is_crash={}.pcap",
+            "id:{},ts:{},is_crash={}.{}",
             entry.id().0,
             entry.creation_ts().unwrap_or(0),
-            is_crash
+            is_crash,
+            extension
         );
         let mut dst_path = pcap_folder.clone();
         dst_path.push(dst_name);
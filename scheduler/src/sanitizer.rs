@@ -0,0 +1,341 @@
+//! Parsing of the reports the sanitizers (ASAN, UBSAN, and MSAN) of the sink emit
+//! on a crash. Reports are identified by a hash over the top frames of the crashing
+//! stack, thus the same bug triggered via different inputs yields the same hash.
+
+use std::{fmt, path::Path};
+
+use hex::ToHex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Number of frames of the crashing stack that are considered by [stack_hash].
+pub const STACK_HASH_FRAMES: usize = 5;
+
+/// Prefixes of functions that belong to the sanitizer runtime. Whether these
+/// frames are on the stack depends on how a bug is triggered, not on the bug itself.
+const RUNTIME_FUNCTION_PREFIXES: &[&str] = &[
+    "__asan",
+    "__ubsan",
+    "__msan",
+    "__sanitizer",
+    "__interceptor_",
+    "__interception",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Sanitizer {
+    #[serde(rename = "asan")]
+    Address,
+    #[serde(rename = "ubsan")]
+    UndefinedBehavior,
+    #[serde(rename = "msan")]
+    Memory,
+    /// A report that only consists of a stack trace.
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sanitizer::Address => write!(f, "asan"),
+            Sanitizer::UndefinedBehavior => write!(f, "ubsan"),
+            Sanitizer::Memory => write!(f, "msan"),
+            Sanitizer::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A single frame of a stack trace, e.g.,
+/// `#1 0x55d0c3a1b2c4 in parse_record /src/parser.c:42:7` or
+/// `#1 0x7f2a5c21b2c4  (/usr/lib/libfoo.so+0x1b2c4)`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The function name, if the frame was symbolized.
+    pub function: Option<String>,
+    /// The source location, if the frame was symbolized.
+    pub location: Option<String>,
+    /// The module and the offset into it, if the frame was not symbolized to a
+    /// source location.
+    pub module: Option<(String, u64)>,
+}
+
+impl Frame {
+    /// Parse a frame from a single `line` of a report. Returns the index of the
+    /// frame and the frame itself.
+    pub fn parse(line: &str) -> Option<(usize, Frame)> {
+        let (idx, rest) = line.trim().strip_prefix('#')?.split_once(' ')?;
+        let idx = idx.parse().ok()?;
+        let rest = rest.trim_start();
+        let (address, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        if !address.starts_with("0x") {
+            return None;
+        }
+
+        let mut rest = rest.trim();
+        if let Some(pos) = rest.find(" (BuildId:") {
+            rest = rest[..pos].trim_end();
+        }
+
+        let mut ret = Frame::default();
+        let module = rest
+            .strip_suffix(')')
+            .and_then(|s| s.rsplit_once('('))
+            .and_then(|(head, module)| Some((head, module.rsplit_once("+0x")?)));
+        if let Some((head, (module, offset))) = module {
+            if let Ok(offset) = u64::from_str_radix(offset, 16) {
+                ret.module = Some((module.to_owned(), offset));
+                rest = head.trim_end();
+            }
+        }
+
+        if ret.module.is_none() {
+            if let Some((head, location)) = rest.rsplit_once(' ') {
+                let has_line = location
+                    .rsplit(':')
+                    .next()
+                    .is_some_and(|s| s.parse::<u32>().is_ok());
+                if !location.contains('(') && (location.contains('/') || has_line) {
+                    ret.location = Some(location.to_owned());
+                    rest = head.trim_end();
+                }
+            }
+        }
+
+        ret.function = rest
+            .strip_prefix("in ")
+            .map(|function| function.trim().to_owned())
+            .filter(|function| !function.is_empty());
+        Some((idx, ret))
+    }
+
+    /// Whether this frame belongs to the sanitizer runtime.
+    pub fn is_runtime(&self) -> bool {
+        let function = self.function.as_deref().unwrap_or_default();
+        let module = self.module.as_ref().map(|(m, _)| m.as_str());
+        RUNTIME_FUNCTION_PREFIXES
+            .iter()
+            .any(|prefix| function.starts_with(prefix))
+            || module.is_some_and(|m| m.contains("libclang_rt"))
+    }
+
+    /// A representation of this frame that does not depend on the addresses the
+    /// modules were loaded at.
+    pub fn key(&self) -> Option<String> {
+        match (&self.function, &self.module, &self.location) {
+            (Some(function), _, _) => Some(function.clone()),
+            (None, Some((module, offset)), _) => {
+                let module = Path::new(module)
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+                Some(format!("{}+{:#x}", module, offset))
+            }
+            (None, None, Some(location)) => Some(location.clone()),
+            (None, None, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.key(), &self.function, &self.location) {
+            (Some(key), Some(_), Some(location)) => write!(f, "{} at {}", key, location),
+            (Some(key), _, _) => write!(f, "{}", key),
+            (None, _, _) => write!(f, "??"),
+        }
+    }
+}
+
+/// A parsed sanitizer report.
+#[derive(Debug, Clone)]
+pub struct SanitizerReport {
+    pub sanitizer: Sanitizer,
+    /// The kind of bug as named by the sanitizer, e.g., `heap-buffer-overflow`.
+    pub bug_type: String,
+    /// The frames of the crashing stack, without the frames of the sanitizer runtime.
+    pub frames: Vec<Frame>,
+}
+
+impl SanitizerReport {
+    /// Parse `report`. Returns `None` if the report does not allow to identify the
+    /// location of the bug, i.e., has neither a stack trace nor a source location.
+    pub fn parse(report: &str) -> Option<SanitizerReport> {
+        let mut sanitizer = Sanitizer::Unknown;
+        let mut bug_type = String::from("unknown");
+        // UBSAN only prints a stack trace if requested via `print_stacktrace=1`.
+        let mut location = None;
+
+        for line in report.lines() {
+            // E.g., `/src/parser.c:42:7: runtime error: signed integer overflow: ...`
+            if let Some((head, description)) = line.split_once(": runtime error: ") {
+                sanitizer = Sanitizer::UndefinedBehavior;
+                bug_type = description
+                    .split(':')
+                    .next()
+                    .unwrap_or(description)
+                    .trim()
+                    .to_owned();
+                location = Some(head.trim().to_owned());
+                break;
+            }
+
+            // E.g., `==42==ERROR: AddressSanitizer: heap-buffer-overflow on address ...`
+            let header = [
+                ("AddressSanitizer: ", Sanitizer::Address),
+                ("UndefinedBehaviorSanitizer: ", Sanitizer::UndefinedBehavior),
+                ("MemorySanitizer: ", Sanitizer::Memory),
+            ]
+            .into_iter()
+            .find_map(|(name, kind)| Some((line.split_once(name)?.1, kind)));
+            if let Some((description, kind)) = header {
+                if !line.contains("ERROR: ") && !line.contains("WARNING: ") {
+                    continue;
+                }
+                sanitizer = kind;
+                if let Some(kind) = description.split_whitespace().next() {
+                    bug_type = kind.to_owned();
+                }
+                break;
+            }
+        }
+
+        let mut frames = parse_stack(report)
+            .into_iter()
+            .filter(|frame| !frame.is_runtime() && frame.key().is_some())
+            .collect::<Vec<_>>();
+        if frames.is_empty() {
+            frames.push(Frame {
+                location: Some(location?),
+                ..Default::default()
+            });
+        }
+
+        Some(SanitizerReport {
+            sanitizer,
+            bug_type,
+            frames,
+        })
+    }
+
+    /// Get a hash of the top `frames` frames of the crashing stack.
+    pub fn stack_hash(&self, frames: usize) -> String {
+        let mut digest = Sha256::new();
+        for key in self.frames.iter().take(frames).filter_map(Frame::key) {
+            digest.update(key);
+            digest.update(b"\n");
+        }
+        digest.finalize().encode_hex()
+    }
+
+    /// The name of the bucket of crashes this report belongs to. Reports of the
+    /// same sanitizer with the same [SanitizerReport::stack_hash] share a bucket.
+    pub fn bucket(&self) -> String {
+        let stack_hash = self.stack_hash(STACK_HASH_FRAMES);
+        format!("{}-{}", self.sanitizer, &stack_hash[..16])
+    }
+}
+
+/// Get the frames of the first stack trace in `report`, i.e., the stack of the
+/// crashing access. Stacks of allocations and frees that follow are ignored.
+pub fn parse_stack(report: &str) -> Vec<Frame> {
+    let mut ret = Vec::new();
+    for line in report.lines() {
+        match Frame::parse(line) {
+            Some((0, _)) | None if !ret.is_empty() => break,
+            Some((_, frame)) => ret.push(frame),
+            None => (),
+        }
+    }
+    ret
+}
+
+/// Get a hash of the top `frames` frames of the crashing stack in `report`,
+/// skipping frames of the sanitizer runtime. Returns `None` if the report can
+/// not be parsed.
+pub fn stack_hash(report: &str, frames: usize) -> Option<String> {
+    SanitizerReport::parse(report).map(|report| report.stack_hash(frames))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_stack, stack_hash, Frame, Sanitizer, SanitizerReport, STACK_HASH_FRAMES};
+
+    const REPORT: &str = "\
+==4242==ERROR: AddressSanitizer: heap-buffer-overflow on address 0x602000000011
+READ of size 1 at 0x602000000011 thread T0
+    #0 0x4a5c1f in __asan_memcpy (/work/sink+0x4a5c1f)
+    #1 0x55d0c3a1b2c4 in parse_record /src/parser.c:42:7
+    #2 0x55d0c3a1b3d5 in Parser::run(int, char const*) /src/parser.cc:7
+    #3 0x7f2a5c21b2c4  (/usr/lib/libfoo.so+0x1b2c4) (BuildId: 0123abcd)
+
+0x602000000011 is located 0 bytes to the right of 1-byte region
+allocated by thread T0 here:
+    #0 0x4a6d2f in malloc (/work/sink+0x4a6d2f)
+    #1 0x55d0c3a1b111 in main /src/main.c:3:5
+";
+
+    #[test]
+    fn crashing_stack_is_hashed() {
+        let frames = parse_stack(REPORT);
+        assert_eq!(frames.len(), 4);
+        assert!(frames[0].is_runtime());
+        assert_eq!(frames[1].function.as_deref(), Some("parse_record"));
+        assert_eq!(frames[1].location.as_deref(), Some("/src/parser.c:42:7"));
+        assert_eq!(
+            frames[2].function.as_deref(),
+            Some("Parser::run(int, char const*)")
+        );
+        assert_eq!(frames[3].key().as_deref(), Some("libfoo.so+0x1b2c4"));
+        assert!(Frame::parse("#1 in foo").is_none());
+
+        // The hash does not depend on addresses or the frames of the allocation.
+        let relocated = REPORT
+            .replace("0x55d0c3a1", "0x56aa0000")
+            .replace("in main", "in other");
+        let hash = stack_hash(REPORT, STACK_HASH_FRAMES).unwrap();
+        assert_eq!(stack_hash(&relocated, STACK_HASH_FRAMES).unwrap(), hash);
+        assert_ne!(
+            stack_hash(&REPORT.replace("parse_record", "other"), STACK_HASH_FRAMES).unwrap(),
+            hash
+        );
+        assert!(stack_hash("==1==ERROR: nothing", STACK_HASH_FRAMES).is_none());
+    }
+
+    #[test]
+    fn reports_are_parsed() {
+        let report = SanitizerReport::parse(REPORT).unwrap();
+        assert_eq!(report.sanitizer, Sanitizer::Address);
+        assert_eq!(report.bug_type, "heap-buffer-overflow");
+        assert_eq!(report.frames.len(), 3);
+        assert_eq!(
+            report.frames[0].to_string(),
+            "parse_record at /src/parser.c:42:7"
+        );
+        assert!(report.bucket().starts_with("asan-"));
+
+        // Without `print_stacktrace=1`, UBSAN only reports the source location.
+        let ubsan = "\
+/src/parser.c:42:7: runtime error: signed integer overflow: 2147483647 + 1 cannot be represented
+SUMMARY: UndefinedBehaviorSanitizer: undefined-behavior /src/parser.c:42:7 in
+";
+        let report = SanitizerReport::parse(ubsan).unwrap();
+        assert_eq!(report.sanitizer, Sanitizer::UndefinedBehavior);
+        assert_eq!(report.bug_type, "signed integer overflow");
+        assert_eq!(
+            report.frames[0].key().as_deref(),
+            Some("/src/parser.c:42:7")
+        );
+        let other_values = SanitizerReport::parse(&ubsan.replace("2147483647", "7")).unwrap();
+        assert_eq!(other_values.bucket(), report.bucket());
+
+        let msan = "\
+==7==WARNING: MemorySanitizer: use-of-uninitialized-value
+    #0 0x4a5c1f in check_header /src/header.c:9:3
+";
+        let report = SanitizerReport::parse(msan).unwrap();
+        assert_eq!(report.sanitizer, Sanitizer::Memory);
+        assert_eq!(report.bug_type, "use-of-uninitialized-value");
+        assert!(report.bucket().starts_with("msan-"));
+    }
+}
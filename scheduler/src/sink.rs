@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Context, Result};
 
 use byte_unit::n_mib_bytes;
+use fuzztruction_shared::constants::ENV_REPORT_SOCKET_DATA;
 use fuzztruction_shared::util::{try_get_child_exit_reason, wait_pid_timeout};
 use glob::glob;
 use itertools::Itertools;
@@ -9,12 +10,13 @@ use log::error;
 use nix::unistd::{setgid, setuid};
 
 use std::env::{self, set_current_dir};
-use std::net::{TcpStream, UdpSocket};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::os::unix::net::{UnixDatagram, UnixStream};
 use std::os::unix::prelude::AsRawFd;
 use std::path::Path;
 use std::process::Command;
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     convert::TryFrom,
     ffi::CString,
@@ -30,14 +32,20 @@ use libc::{SIGKILL, SIGTERM, STDIN_FILENO};
 
 use nix::sys::signal::Signal;
 
-use crate::config::Config;
+use crate::config::{Config, SessionMode};
 use crate::io_channels::InputChannel;
-use crate::networked::{NetworkedRunResult, ServerReadySignalKind, WaitForPeerResult};
+use crate::networked::{
+    remove_stale_unix_socket, NetworkedRunResult, OpenConnections, ServerReadySignalKind,
+    SocketEvent, SocketEventCounters, WaitForPeerResult,
+};
 use crate::sink_bitmap::{Bitmap, BITMAP_DEFAULT_MAP_SIZE};
+use crate::source::Source;
 
 use filedescriptor;
 
+const FS_OPT_ENABLED: u32 = 0x80000000;
 const FS_OPT_MAPSIZE: u32 = 0x40000000;
+const FS_OPT_AUTODICT: u32 = 0x10000000;
 
 // FDs used by the forkserver to communicate with us.
 // Hardcoded in AFLs config.h.
@@ -49,7 +57,33 @@ const AFL_WRITE_TO_PARENT_FD: i32 = FORKSRV_FD + 1;
 // Used by the fork server to send us messages that are specific to our sink impl.
 const AFL_FT_WRITE_TO_PARENT_FD: i32 = FORKSRV_FD + 2;
 
+// Messages send via AFL_FT_WRITE_TO_PARENT_FD. Listen and bind are reported by the consumer
+// runtime (consumer/aflpp-consumer/instrumentation/afl-compiler-rt.o.c), all other messages
+// by the socket hooks we preload into the consumer (consumer/socket-hooks/src/lib.rs).
+const FT_MSG_AFTER_LISTEN: i32 = 1;
+const FT_MSG_AFTER_BIND: i32 = 2;
+const FT_MSG_BEFORE_ACCEPT: i32 = 3;
+/// Send if a connection was established via connect().
+const FT_MSG_AFTER_CONNECT: i32 = 4;
+/// Send if a connected socket is closed.
+const FT_MSG_AFTER_CLOSE: i32 = 5;
+/// Send if data was send or received via a socket. Only reported if
+/// [ENV_REPORT_SOCKET_DATA] is set, since nobody reads the pipe while
+/// waiting for the child to terminate otherwise.
+const FT_MSG_SOCKET_DATA: i32 = 6;
+/// Send by the socket hooks once they are loaded, i.e., before the forkserver comes up.
+const FT_MSG_SOCKET_HOOKS_LOADED: i32 = 7;
+/// Send if a connection was established via accept().
+const FT_MSG_AFTER_ACCEPT: i32 = 8;
+/// The connect, accept, and close messages carry the fd of the connection in the
+/// bits above the message type.
+const FT_MSG_TYPE_MASK: i32 = 0xff;
+const FT_MSG_FD_SHIFT: u32 = 8;
+
 const AFL_SHM_ENV_VAR_NAME: &str = "__AFL_SHM_ID";
+/// Library that reports the socket events the consumer runtime does not report.
+/// It is resolved via the library search path (see data/ld_fuzztruction.conf).
+const SOCKET_HOOKS_LIB: &str = "libft_socket_hooks.so";
 const AFL_DEFAULT_TIMEOUT: Duration = Duration::from_millis(10000);
 
 const DEFAULT_TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(120);
@@ -168,6 +202,8 @@ pub struct AflSink {
     asan_log_file: PathBuf,
     /// Whether the bitmap was already resized in response of the bitmap size reported by the targets forkserver.
     bitmap_was_resize: bool,
+    /// Tokens of the autodictionary reported by the forkserver.
+    dictionary_tokens: Vec<Vec<u8>>,
     /// The pid of the child that was forked from the forkserver. None if there is currently no child running.
     child_pid: Option<i32>,
     /// Path where the llvm coverage report is stored if coverage is collected.
@@ -175,8 +211,13 @@ pub struct AflSink {
     workdir_file_allowlist: Vec<PathBuf>,
     purge_ctr: usize,
     enable_rr: bool,
-    bind_ctr: usize,
-    listen_ctr: usize,
+    socket_events: SocketEventCounters,
+    /// Connections of the current execution as reported by the socket hooks.
+    open_connections: OpenConnections,
+    /// Whether the socket hooks announced themselves when the forkserver came up.
+    /// If not (e.g., because the sink is linked statically), accept() and connect()
+    /// are never reported.
+    socket_hooks_loaded: bool,
 }
 
 impl AflSink {
@@ -247,13 +288,15 @@ impl AflSink {
             asan_log_file: asan_report_file.1,
             config: config.clone(),
             bitmap_was_resize: false,
+            dictionary_tokens: Vec::new(),
             child_pid: None,
             coverage_report: None,
             workdir_file_allowlist,
             purge_ctr: 0,
             enable_rr: false,
-            bind_ctr: 0,
-            listen_ctr: 0,
+            socket_events: SocketEventCounters::default(),
+            open_connections: OpenConnections::default(),
+            socket_hooks_loaded: false,
         })
     }
 
@@ -460,16 +503,39 @@ impl AflSink {
                     CString::new(format!("{}={}", AFL_SHM_ENV_VAR_NAME, map_shm_id)).unwrap();
                 envp.push(shm_env_var);
 
+                // Socket events besides listen() and bind() are reported by our socket hooks.
+                // A LD_PRELOAD set by the user is preserved.
+                let preload_socket_hooks = self.config.target_uses_network();
+                if preload_socket_hooks {
+                    let mut preload = SOCKET_HOOKS_LIB.to_owned();
+                    if let Some(var) = self.config.sink.env.iter().find(|v| v.0 == "LD_PRELOAD") {
+                        preload = format!("{}:{}", preload, var.1);
+                    }
+                    envp.push(CString::new(format!("LD_PRELOAD={}", preload)).unwrap());
+                }
+
                 let mut env_from_config = Vec::new();
-                self.config.sink.env.iter().for_each(|var| {
-                    env_from_config
-                        .push(CString::new(format!("{}={}", var.0, var.1).as_bytes()).unwrap())
-                });
+                self.config
+                    .sink
+                    .env
+                    .iter()
+                    .filter(|var| !(preload_socket_hooks && var.0 == "LD_PRELOAD"))
+                    .for_each(|var| {
+                        env_from_config
+                            .push(CString::new(format!("{}={}", var.0, var.1).as_bytes()).unwrap())
+                    });
 
                 let afl_maps_size =
                     CString::new(format!("AFL_MAP_SIZE={}", self.bitmap().size())).unwrap();
                 envp.push(afl_maps_size);
 
+                // Data transfers are used to detect idle sessions.
+                if self.config.sink.idle_timeout.is_some() {
+                    let report_socket_data =
+                        CString::new(format!("{}=1", ENV_REPORT_SOCKET_DATA)).unwrap();
+                    envp.push(report_socket_data);
+                }
+
                 // ASAN config
                 let asan_log_path = format!(
                     "log_path={}",
@@ -485,6 +551,8 @@ impl AflSink {
                     "handle_sigill=2",
                     "detect_stack_use_after_return=0",
                     "detect_odr_violation=0",
+                    // Our socket hooks are preloaded before a dynamically linked ASAN runtime.
+                    "verify_asan_link_order=0",
                     &asan_log_path,
                 ];
 
@@ -614,10 +682,17 @@ impl AflSink {
                 )));
             }
 
+            self.check_socket_hooks_loaded();
+
             // Process extended attributes used by AFL++.
             // Sett src/afl-forkserver.c:689 (afl_fsrv_start)
             let status = u32::from_ne_bytes(buffer);
             log::info!("Forkserver status: 0x{:x}", status);
+            if status & (FS_OPT_ENABLED | FS_OPT_AUTODICT) == (FS_OPT_ENABLED | FS_OPT_AUTODICT) {
+                log::info!("Got extended option FS_OPT_AUTODICT from forkserver");
+                self.receive_autodict()
+                    .context("Failed to receive autodictionary from forkserver")?;
+            }
             if status & FS_OPT_MAPSIZE == FS_OPT_MAPSIZE {
                 log::info!("Got extended option FS_OPT_MAPSIZE from forkserver");
                 let new_map_size = ((status & 0x00fffffe) >> 1) + 1;
@@ -656,6 +731,91 @@ impl AflSink {
         Ok(())
     }
 
+    /// Check whether the socket hooks announced themselves while the forkserver came up.
+    /// Other messages received during startup are discarded, as they are before each run.
+    fn check_socket_hooks_loaded(&mut self) {
+        let ft_receive_fd = self.ft_receive_fd.unwrap();
+        let mut buffer = [0u8; 4];
+
+        self.socket_hooks_loaded = false;
+        while AflSink::wait_for_data(&[ft_receive_fd], Duration::ZERO).is_ok() {
+            let ret = unsafe { libc::read(ft_receive_fd, buffer.as_mut_ptr() as *mut _, 4) };
+            if ret != 4 {
+                break;
+            }
+            if i32::from_le_bytes(buffer) == FT_MSG_SOCKET_HOOKS_LOADED {
+                self.socket_hooks_loaded = true;
+            }
+        }
+
+        if self.config.target_uses_network() && !self.socket_hooks_loaded {
+            log::warn!(
+                "{} was not loaded into the sink, accept() and connect() will not be reported",
+                SOCKET_HOOKS_LIB
+            );
+        }
+    }
+
+    /// Receive the autodictionary, i.e., the operands of comparisons AFL++ collected
+    /// while compiling the sink. The dictionary consists of tokens that are prefixed
+    /// by their length. See src/afl-forkserver.c (afl_fsrv_start).
+    fn receive_autodict(&mut self) -> Result<()> {
+        let ack = (FS_OPT_ENABLED | FS_OPT_AUTODICT).to_ne_bytes();
+        let ret = unsafe {
+            libc::write(
+                self.send_fd.unwrap(),
+                ack.as_ptr() as *const libc::c_void,
+                ack.len(),
+            )
+        };
+        if ret != 4 {
+            return Err(anyhow!(
+                "Failed to acknowledge FS_OPT_AUTODICT. ret={}",
+                ret
+            ));
+        }
+
+        let mut len = [0u8; 4];
+        self.read_from_forkserver(&mut len)?;
+        let mut dict = vec![0u8; u32::from_ne_bytes(len) as usize];
+        self.read_from_forkserver(&mut dict)?;
+
+        self.dictionary_tokens.clear();
+        let mut offset = 0;
+        while let Some(len) = dict.get(offset).map(|len| *len as usize) {
+            let Some(token) = dict.get((offset + 1)..(offset + 1 + len)) else {
+                break;
+            };
+            self.dictionary_tokens.push(token.to_vec());
+            offset += 1 + len;
+        }
+        log::info!(
+            "Received {} tokens from the forkserver",
+            self.dictionary_tokens.len()
+        );
+        Ok(())
+    }
+
+    /// Fill `buffer` with data sent by the forkserver.
+    fn read_from_forkserver(&self, buffer: &mut [u8]) -> Result<()> {
+        let mut offset = 0;
+        while offset < buffer.len() {
+            AflSink::wait_for_data(&[self.receive_fd.unwrap()], AFL_DEFAULT_TIMEOUT)?;
+            let ret = unsafe {
+                libc::read(
+                    self.receive_fd.unwrap(),
+                    buffer[offset..].as_mut_ptr() as *mut libc::c_void,
+                    buffer.len() - offset,
+                )
+            };
+            if ret <= 0 {
+                return Err(anyhow!("Failed to read from forkserver. ret={}", ret));
+            }
+            offset += ret as usize;
+        }
+        Ok(())
+    }
+
     unsafe fn set_fsize_limit(&mut self) {
         if !self.log_stdout && !self.log_stderr {
             // if we log stderr or stdout, the limit will cause our
@@ -786,9 +946,11 @@ impl AflSink {
     pub fn write_tcp(&self, data: &[u8]) -> Result<()> {
         let config = &self.config;
         assert!(config.sink.is_server.unwrap());
-        let port = config.sink.server_port.as_ref().unwrap();
+        let address = config
+            .sink
+            .server_addr()
+            .context("Neither server-address nor server-port set in the sink config")?;
 
-        let address = format!("127.0.0.1:{}", port);
         let mut tcp_client = TcpStream::connect(address)?;
 
         tcp_client.write_all(data)?;
@@ -799,10 +961,18 @@ impl AflSink {
     pub fn write_udp(&self, packages: &[&[u8]]) -> Result<()> {
         let config = &self.config;
         assert!(config.sink.is_server.unwrap());
-        let port = config.sink.server_port.as_ref().unwrap();
-        let remote = format!("127.0.0.1:{}", port);
-
-        let udp_client = UdpSocket::bind("127.0.0.1:0")?;
+        let remote = config
+            .sink
+            .server_addr()
+            .context("Neither server-address nor server-port set in the sink config")?;
+
+        // Bind to an ephemeral port of the same address family as the server.
+        let local: SocketAddr = if remote.is_ipv6() {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        };
+        let udp_client = UdpSocket::bind(local)?;
         udp_client.connect(remote)?;
         for package in packages {
             udp_client.send(package)?;
@@ -811,6 +981,39 @@ impl AflSink {
         Ok(())
     }
 
+    /// Send `packages` to the sink's AF_UNIX socket. Stream sockets receive
+    /// all packages as one continuous byte stream, while datagram sockets
+    /// receive one datagram per package.
+    pub fn write_unix(&self, packages: &[&[u8]]) -> Result<()> {
+        let config = &self.config;
+        assert!(config.sink.is_server.unwrap());
+        let path = config
+            .sink
+            .server_socket_path
+            .as_ref()
+            .context("server-socket-path not set in the sink config")?;
+
+        match UnixStream::connect(path) {
+            Ok(mut stream) => {
+                for package in packages {
+                    stream.write_all(package)?;
+                }
+                stream.flush()?;
+            }
+            Err(err) if err.raw_os_error() == Some(libc::EPROTOTYPE) => {
+                // The server bound a datagram socket.
+                let socket = UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                for package in packages {
+                    socket.send(package)?;
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        Ok(())
+    }
+
     pub fn run(&mut self, timeout: Duration) -> Result<RunResult> {
         self.spawn_child()?;
         self.wait_for_child_termination(timeout, false, None)
@@ -818,13 +1021,19 @@ impl AflSink {
 
     pub fn spawn_child(&mut self) -> Result<()> {
         self.bitmap().reset();
-        self.bind_ctr = 0;
-        self.listen_ctr = 0;
+        self.socket_events.reset();
+        self.open_connections.reset();
 
         if let Err(err) = self.maybe_purge_orphaned_files() {
             log::warn!("Orphan file purging failed: {err:?}");
         }
 
+        if self.config.target_uses_unix_socket() && self.config.sink.is_server.unwrap_or(false) {
+            if let Some(path) = self.config.sink.server_socket_path.as_ref() {
+                remove_stale_unix_socket(path)?;
+            }
+        }
+
         let buffer = [0u8; 4];
         let buf_ptr = buffer.as_ptr() as *mut libc::c_void;
 
@@ -878,59 +1087,237 @@ impl AflSink {
     }
 
     pub fn wait_for_server(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+        let ready_on = match self.config.sink.server_ready_on {
+            // Without the socket hooks, we fall back to the runtime's listen() report.
+            Some(ServerReadySignalKind::Accept(_)) if !self.socket_hooks_loaded => {
+                ServerReadySignalKind::Listen(0)
+            }
+            ready_on => ready_on.unwrap_or(ServerReadySignalKind::Listen(0)),
+        };
+        log::trace!("Waiting for server beeing ready for connections");
+        self.wait_for_socket_event(timeout, ready_on)
+    }
+
+    /// Wait until the client connected to the server. This relies on the socket
+    /// hooks reporting each completed `connect()` via the ft pipe. Sinks that
+    /// do not use a connection oriented channel, or that run without the socket
+    /// hooks, are considered ready right away.
+    pub fn wait_until_connect(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+        self.wait_for_connection(timeout, 0)
+    }
+
+    /// Same as [AflSink::wait_until_connect], but waits for the `nth` (zero based)
+    /// connection of the session.
+    pub fn wait_for_connection(
+        &mut self,
+        timeout: Duration,
+        nth: usize,
+    ) -> Result<WaitForPeerResult> {
+        if !matches!(
+            self.config.sink.input_type,
+            InputChannel::Tcp | InputChannel::Unix
+        ) || !self.socket_hooks_loaded
+        {
+            return Ok(WaitForPeerResult::Ready);
+        }
+
+        let first = match self.config.sink.server_ready_on {
+            Some(ServerReadySignalKind::Connect(n)) => n,
+            _ => 0,
+        };
+        log::trace!("Waiting for client to establish connection {nth}");
+        self.wait_for_socket_event(timeout, ServerReadySignalKind::Connect(first + nth))
+    }
+
+    /// Wait until the session of the current execution ended. A session ends if the
+    /// child closed all connections of the session (see [AflSink::session_closed]) or
+    /// if neither of the peers reported a socket event or exchanged any data for
+    /// `sink.idle-timeout`. The child reports both via the socket hooks, thus without
+    /// them we wait for the child to terminate. The `producer` is the weird peer, whose
+    /// agent reports its socket activity if an idle timeout is configured.
+    /// After the session ended, the child is terminated via SIGTERM, which we treat as
+    /// normal termination. If the child exits on its own, the session ends as well.
+    pub fn wait_for_session_end(
+        &mut self,
+        timeout: Duration,
+        mut producer: Option<&mut Source>,
+    ) -> Result<RunResult> {
+        let child_pid = self.child_pid.expect("spawn_child must be called first");
+        let deadline = Instant::now() + timeout;
+
+        if !self.socket_hooks_loaded {
+            // Neither closed connections nor data transfers are reported.
+            return self.wait_for_child_termination(timeout, false, None);
+        }
+
+        let idle_timeout = self.config.sink.idle_timeout;
+        let receive_fd = self.receive_fd.unwrap();
+        let ft_receive_fd = self.ft_receive_fd.unwrap();
+        let mut fds = vec![receive_fd, ft_receive_fd];
+        let producer_fd = if idle_timeout.is_some() {
+            producer
+                .as_ref()
+                .and_then(|producer| producer.message_queue_fd())
+        } else {
+            None
+        };
+        fds.extend(producer_fd);
+        let mut last_activity = Instant::now();
+
+        loop {
+            if self.session_closed() {
+                log::trace!("All connections of the session are closed");
+                break;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let wait_for = idle_timeout.map_or(remaining, |idle_timeout| {
+                (last_activity + idle_timeout)
+                    .saturating_duration_since(Instant::now())
+                    .min(remaining)
+            });
+
+            match AflSink::wait_for_data(&fds, wait_for) {
+                Ok(ready_fds) => {
+                    if ready_fds.contains(&receive_fd) {
+                        return self.handle_child_exit_msg();
+                    }
+                    if ready_fds.contains(&ft_receive_fd) {
+                        if let Some(event) = self.read_socket_event()? {
+                            self.socket_events.add(event);
+                        }
+                        last_activity = Instant::now();
+                    }
+                    if producer_fd.is_some_and(|fd| ready_fds.contains(&fd)) {
+                        let activity = producer
+                            .as_mut()
+                            .unwrap()
+                            .poll_socket_activity()
+                            .context("Failed to poll the socket activity of the producer")?;
+                        if activity {
+                            last_activity = Instant::now();
+                        }
+                    }
+                }
+                Err(err)
+                    if !matches!(
+                        err.downcast_ref::<SinkError>(),
+                        Some(SinkError::CommunicationTimeoutError(_))
+                    ) =>
+                {
+                    return Err(err.context("Failed to wait for the end of the session"));
+                }
+                Err(_) if wait_for < remaining => {
+                    log::trace!("Session was idle for {:?}", idle_timeout.unwrap());
+                    break;
+                }
+                Err(_) => {
+                    // The session did not end in time, let the timeout handling kill the child.
+                    return self.wait_for_child_termination(Duration::ZERO, false, None);
+                }
+            }
+        }
+
+        log::trace!("Session ended, sending SIGTERM");
+        let _ = nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(child_pid),
+            nix::sys::signal::SIGTERM,
+        );
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        self.wait_for_child_termination(remaining, false, None)
+    }
+
+    /// Whether the child closed all connections of the configured session. In
+    /// [SessionMode::Concurrent], these are all connections it opened, given that it
+    /// opened at least `session.connections`. Otherwise, the session is closed once
+    /// `session.connections` connections were closed.
+    fn session_closed(&self) -> bool {
+        let Some(session) = self.config.session.as_ref() else {
+            return false;
+        };
+        match session.mode {
+            SessionMode::Sequential | SessionMode::SharedTimeout => {
+                self.socket_events.count(SocketEvent::Close) >= session.connections
+            }
+            SessionMode::Concurrent => {
+                self.open_connections.opened_count() >= session.connections
+                    && self.open_connections.open_count() == 0
+            }
+        }
+    }
+
+    /// Read the next socket event reported by the child via the ft pipe. Connections
+    /// that were opened or closed are recorded in `open_connections`.
+    fn read_socket_event(&mut self) -> Result<Option<SocketEvent>> {
+        let mut buffer = [0u8; 4];
+        let ret = unsafe {
+            libc::read(
+                self.ft_receive_fd.unwrap(),
+                buffer.as_mut_ptr() as *mut _,
+                4,
+            )
+        };
+        if ret != 4 {
+            return Err(anyhow!("Failed to read socket event message."));
+        }
+
+        let child_msg = i32::from_le_bytes(buffer);
+        let fd = child_msg >> FT_MSG_FD_SHIFT;
+        let event = match child_msg & FT_MSG_TYPE_MASK {
+            FT_MSG_AFTER_LISTEN => Some(SocketEvent::Listen),
+            FT_MSG_AFTER_BIND => Some(SocketEvent::Bind),
+            FT_MSG_BEFORE_ACCEPT => Some(SocketEvent::Accept),
+            FT_MSG_AFTER_CONNECT => {
+                self.open_connections.open(fd);
+                Some(SocketEvent::Connect)
+            }
+            // The server already reported that it is ready via FT_MSG_BEFORE_ACCEPT.
+            FT_MSG_AFTER_ACCEPT => {
+                self.open_connections.open(fd);
+                None
+            }
+            FT_MSG_AFTER_CLOSE => {
+                self.open_connections.close(fd);
+                Some(SocketEvent::Close)
+            }
+            FT_MSG_SOCKET_DATA => Some(SocketEvent::Data),
+            // Send again by processes the sink exec'ed, since they inherit LD_PRELOAD.
+            FT_MSG_SOCKET_HOOKS_LOADED => None,
+            _ => {
+                log::warn!("Unknown message received via the ft pipe: {child_msg}");
+                None
+            }
+        };
+        Ok(event)
+    }
+
+    /// Wait until the child reported the socket event described by `ready_on`
+    /// via the ft pipe. If the child does not report it in time, it is killed.
+    pub fn wait_for_socket_event(
+        &mut self,
+        timeout: Duration,
+        ready_on: ServerReadySignalKind,
+    ) -> Result<WaitForPeerResult> {
         let mut buffer = [0u8; 4];
         let child_pid = self.child_pid.expect("spawn_child must be called first");
 
-        log::trace!("Waiting for server beeing ready for connections");
         match AflSink::wait_for_data(
             &[self.receive_fd.unwrap(), self.ft_receive_fd.unwrap()],
             timeout,
         ) {
             Ok(ready_fds) => {
-                if ready_fds.contains(&self.receive_fd.unwrap()) {
-                    self.handle_child_exit_msg().map(|e| e.into())
-                } else if ready_fds.contains(&self.ft_receive_fd.unwrap()) {
-                    // Check if the server indicates that it is ready to accept connections
-                    let ret = unsafe {
-                        libc::read(
-                            self.ft_receive_fd.unwrap(),
-                            buffer.as_mut_ptr() as *mut _,
-                            4,
-                        )
-                    };
-                    if ret != 4 {
-                        return Err(anyhow!(
-                            "Failed to read server listen/accept/ready message."
-                        ));
-                    }
-
-                    let ready_signal_kind = self
-                        .config
-                        .sink
-                        .server_ready_on
-                        .unwrap_or(ServerReadySignalKind::Listen(0));
-                    let child_msg = i32::from_le_bytes(buffer);
-
-                    if child_msg == 1 {
-                        self.listen_ctr += 1;
-                    } else if child_msg == 2 {
-                        self.bind_ctr += 1;
-                    }
-
-                    // See consumer/aflpp-consumer/instrumentation/afl-compiler-rt.o.c for the origin of these constants :)
-                    if child_msg == 1
-                        && ready_signal_kind == ServerReadySignalKind::Listen(self.listen_ctr - 1)
-                        || child_msg == 2
-                            && ready_signal_kind == ServerReadySignalKind::Bind(self.bind_ctr - 1)
-                    {
+                // Pending socket events are consumed first, since a child that
+                // reported an event and exited right after might have both fds ready.
+                if ready_fds.contains(&self.ft_receive_fd.unwrap()) {
+                    let event = self.read_socket_event()?;
+                    if event.is_some_and(|event| self.socket_events.record(event, ready_on)) {
                         Ok(WaitForPeerResult::Ready)
                     } else {
-                        log::trace!(
-                            "Got {child_msg}, but we are waiting for {:?}.",
-                            ready_signal_kind
-                        );
-                        self.wait_for_server(timeout)
+                        log::trace!("Got {event:?}, but we are waiting for {ready_on:?}.");
+                        self.wait_for_socket_event(timeout, ready_on)
                     }
+                } else if ready_fds.contains(&self.receive_fd.unwrap()) {
+                    self.handle_child_exit_msg().map(|e| e.into())
                 } else {
                     unreachable!("Some if these ready fds are unexpected: {ready_fds:?}");
                 }
@@ -1122,6 +1509,11 @@ impl AflSink {
         &mut self.bitmap
     }
 
+    /// The tokens of the autodictionary the forkserver reported on startup.
+    pub fn dictionary_tokens(&self) -> &[Vec<u8>] {
+        &self.dictionary_tokens
+    }
+
     pub fn get_latest_asan_report(&self) -> Option<String> {
         let report_path = &self.asan_log_file;
         let reports =
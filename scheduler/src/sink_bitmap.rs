@@ -305,6 +305,22 @@ impl Bitmap {
         new_map
     }
 
+    /// Mark the edges set in `covered_edges`, a bitmap created via [Bitmap::minimize],
+    /// as seen in this virgin map. The hit counts are not part of `covered_edges`,
+    /// thus only the bucket of a single hit is cleared and other hit counts of the
+    /// edges are still reported as [BitmapStatus::NewHit].
+    pub fn mark_edges_seen(&mut self, covered_edges: &Bitmap) {
+        let covered = covered_edges.data();
+        for (idx, virgin) in self.data_mut().iter_mut().enumerate() {
+            if covered
+                .get(idx / 8)
+                .is_some_and(|b| b & (1 << (idx % 8)) != 0)
+            {
+                *virgin &= !1;
+            }
+        }
+    }
+
     pub fn edges(&self) -> Vec<usize> {
         let mut ret = Vec::new();
         for (idx, val) in self.data().iter().enumerate() {
@@ -475,6 +491,27 @@ mod test {
         assert_eq!(virgin[0], 0xff & !0b11);
     }
 
+    #[test]
+    fn test_mark_edges_seen() {
+        const SIZE: usize = 32;
+        let mut bm = Bitmap::new_in_mem(SIZE, 0);
+        bm[3] = 1;
+        bm[17] = 4;
+        let covered_edges = bm.minimize();
+
+        let mut virgin = Bitmap::new_in_mem(SIZE, 0xff);
+        virgin.mark_edges_seen(&covered_edges);
+        assert_eq!(virgin.data().iter().filter(|b| **b != 0xff).count(), 2);
+
+        let mut bm = Bitmap::new_in_mem(SIZE, 0);
+        bm[3] = 1;
+        assert_eq!(bm.has_new_bit(&mut virgin), BitmapStatus::NoChange);
+        bm[3] = 2;
+        assert_eq!(bm.has_new_bit(&mut virgin), BitmapStatus::NewHit);
+        bm[4] = 1;
+        assert_eq!(bm.has_new_bit(&mut virgin), BitmapStatus::NewEdge);
+    }
+
     #[test]
     fn test_classify_counts() {
         const SIZE: usize = 32;
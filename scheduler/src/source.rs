@@ -4,19 +4,18 @@ use byte_unit::n_mib_bytes;
 use fuzztruction_shared::{
     aux_messages::{AuxStreamMessage, AuxStreamType},
     aux_stream::AuxStreamAssembler,
-    constants::{ENV_LOG_LEVEL, ENV_SHM_NAME, PATCH_POINT_SIZE},
-    dwarf::DwarfReg,
+    constants::{ENV_LOG_LEVEL, ENV_REPORT_SOCKET_DATA, ENV_SHM_NAME, PATCH_POINT_SIZE},
     log_utils::LogRecordWrapper,
     messages::{
-        ChildPid, HelloMessage, Message, MessageType, MsgHeader, ReceivableMessages, RunMessage,
-        SyncMutations, TerminatedMessage, TracePointStat,
+        AfterBind, AfterListen, BeforeAccept, ChildPid, HelloMessage, Message, MessageType,
+        MsgHeader, ReceivableMessages, RunMessage, SocketAddress, SyncMutations, TerminatedMessage,
+        TracePointStat,
     },
     mutation_cache::{MutationCacheEntryFlags, MutationCacheError},
     types::MutationSiteID,
     util::{current_log_level, wait_pid_timeout},
 };
 use jail::jail::{Jail, JailBuilder};
-use llvm_stackmap::LocationType;
 use std::{
     collections::HashMap,
     fmt::Debug,
@@ -53,11 +52,14 @@ use libc::{self};
 use crate::{
     config::Config,
     mutation_site,
-    networked::{NetworkedRunResult, WaitForPeerResult},
+    networked::{remove_stale_unix_socket, NetworkedRunResult, WaitForPeerResult},
 };
 use crate::{constants::MAX_PATCHPOINT_CNT, llvm_stackmap::StackMap};
 use crate::{mutation_cache::MutationCache, mutation_site::MutationSite};
-use crate::{networked::ServerReadySignalKind, trace::Trace};
+use crate::{
+    networked::{ServerReadySignalKind, SocketEvent, SocketEventCounters},
+    trace::Trace,
+};
 
 use crate::io_channels::*;
 
@@ -232,9 +234,11 @@ pub struct Source {
     workdir_file_whitelist: Vec<PathBuf>,
     child_pid: Option<ChildPid>,
     msg_buffer: Option<Vec<ReceivableMessages>>,
+    /// A TerminatedMessage received by [Source::poll_socket_activity] that is yet
+    /// to be processed by [Source::wait_for_child_termination].
+    pending_termination: Option<TerminatedMessage>,
     purge_ctr: usize,
-    bind_ctr: usize,
-    listen_ctr: usize,
+    socket_events: SocketEventCounters,
 }
 
 impl Source {
@@ -500,9 +504,9 @@ impl Source {
             workdir_file_whitelist,
             child_pid: None,
             msg_buffer: None,
+            pending_termination: None,
             purge_ctr: 0,
-            bind_ctr: 0,
-            listen_ctr: 0,
+            socket_events: SocketEventCounters::default(),
         })
     }
 
@@ -655,6 +659,18 @@ impl Source {
                     .expect("Failed to format ENV_FT_SHM_NAME");
                     envp.push(env_shm_name);
 
+                    // Data transfers are used to detect idle sessions.
+                    if self
+                        .config
+                        .as_ref()
+                        .is_some_and(|config| config.sink.idle_timeout.is_some())
+                    {
+                        let env_report_socket_data =
+                            CString::new(format!("{}=1", ENV_REPORT_SOCKET_DATA).as_bytes())
+                                .unwrap();
+                        envp.push(env_report_socket_data);
+                    }
+
                     // Resolve symbols at the start, thus we do not have to do it
                     // after each fork.
                     let ld_bind_now = CString::new("LD_BIND_NOW=1".as_bytes())
@@ -858,32 +874,13 @@ impl Source {
         }
     }
 
-    /// Remove all patch points that recorded a const live value.
-    // fn remove_const_types(patch_points: &mut Vec<MutationSite>) {
-    //     patch_points.retain(|e| {
-    //         e.location().loc_type != LocationType::Constant
-    //             && e.location().loc_type != LocationType::ConstIndex
-    //     });
-    // }
-
-    /// Remove all patch points that recorded a frame index
-    #[allow(unused)]
-    fn remove_direct_types(patch_points: &mut Vec<MutationSite>) {
-        patch_points.retain(|e| e.spill_slot().loc_type != LocationType::Direct);
-    }
-
-    #[allow(unused)]
-    fn remove_indirect_types(patch_points: &mut Vec<MutationSite>) {
-        patch_points.retain(|e| e.spill_slot().loc_type != LocationType::Indirect);
-    }
-
     fn remove_unsupported_loc_size(patch_points: &mut Vec<MutationSite>) {
-        log::info!("Removing target values with loc_size > 8");
-        patch_points.retain(|e| e.target_value_size_byte() <= 8);
-    }
-
-    fn remove_rsp_spill_slots(patch_points: &mut Vec<MutationSite>) {
-        patch_points.retain(|e| e.spill_slot().dwarf_regnum != DwarfReg::Rsp as u16);
+        // Values wider than 8 byte are mutated in 8 byte steps (see the agent's JIT).
+        log::info!("Removing target values with loc_size > 32 or not a multiple of 8 if > 8");
+        patch_points.retain(|e| {
+            let size = e.target_value_size_bit();
+            size <= 64 || (size <= 256 && size % 64 == 0)
+        });
     }
 
     /// Resolve a path retrived from the child into a local path.
@@ -932,6 +929,7 @@ impl Source {
         self.retain_whitelisted(&mut patch_points);
         self.limit_max_number(&mut patch_points);
         self.remove_blocklisted_instructions(&mut patch_points);
+        self.retain_fault_callees(&mut patch_points);
 
         let mut patchpoint_type_frequency = HashMap::new();
         for pp in patch_points.iter() {
@@ -970,12 +968,9 @@ impl Source {
         );
 
         let pp_before_filtering = patch_points.len();
-        //Source::remove_const_types(&mut patch_points);
-        //Source::remove_direct_types(&mut patch_points);
-        //Source::remove_indirect_types(&mut patch_points);
         Source::remove_duplicated_vmas(&mut patch_points);
         Source::remove_unsupported_loc_size(&mut patch_points);
-        Source::remove_rsp_spill_slots(&mut patch_points);
+        self.remove_unwritable_constant_spill_slots(&mut patch_points);
 
         self.check_alignment(&mut patch_points);
 
@@ -1014,6 +1009,34 @@ impl Source {
         }
     }
 
+    /// Remove all patch points whose spill slot is a constant address that is not
+    /// located in a writable mapping. The agent writes through these addresses,
+    /// thus, e.g., null or small constants would crash the target.
+    fn remove_unwritable_constant_spill_slots(&self, patch_points: &mut Vec<MutationSite>) {
+        let mappings = self.mappings.as_ref().unwrap();
+        let old_size = patch_points.len();
+
+        patch_points.retain(|pp| {
+            let Some(start) = pp.constant_spill_slot_address() else {
+                return true;
+            };
+            let end = start.saturating_add(pp.target_value_size_byte() as u64);
+            mappings.iter().any(|mapping| {
+                mapping.is_write()
+                    && mapping.start() as u64 <= start
+                    && end <= (mapping.start() + mapping.size()) as u64
+            })
+        });
+
+        let removed_cnt = old_size - patch_points.len();
+        if removed_cnt > 0 {
+            log::warn!(
+                "Removed {} patch points with a constant spill slot that is not writable.",
+                removed_cnt
+            );
+        }
+    }
+
     fn remove_blocklisted_instructions(&self, patch_points: &mut Vec<MutationSite>) {
         if let Some(ref blocklist) = self
             .config
@@ -1027,6 +1050,21 @@ impl Source {
         }
     }
 
+    /// Remove all call fault sites whose callee is not listed in `fault-callees`.
+    fn retain_fault_callees(&self, patch_points: &mut Vec<MutationSite>) {
+        if let Some(callees) = self.config.as_ref().unwrap().source.fault_callees.as_ref() {
+            log::info!("Only calls of the following functions may be faulted: {callees:?}");
+            let hashes = callees
+                .iter()
+                .map(|name| mutation_site::callee_hash(name))
+                .collect::<HashSet<_>>();
+            patch_points.retain(|pp| {
+                pp.call_fault()
+                    .map_or(true, |fault| hashes.contains(&fault.callee_hash))
+            });
+        }
+    }
+
     fn retain_whitelisted(&self, patch_points: &mut Vec<MutationSite>) {
         if let Some(allowed_patch_points) = self
             .config
@@ -1398,10 +1436,18 @@ impl Source {
 
     pub fn spawn(&mut self, timeout: Duration) -> Result<()> {
         self.purge_workdir();
-        self.bind_ctr = 0;
-        self.listen_ctr = 0;
+        self.socket_events.reset();
+
+        if let Some(config) = self.config.as_ref() {
+            if config.target_uses_unix_socket() && config.source.is_server.unwrap_or(false) {
+                if let Some(path) = config.source.server_socket_path.as_ref() {
+                    remove_stale_unix_socket(path)?;
+                }
+            }
+        }
 
         self.msg_buffer = Some(Vec::new());
+        self.pending_termination = None;
 
         if cfg!(debug) && self.input_file.0.stream_len().unwrap() == 0 {
             log::warn!("Running without input! Is this intentional?");
@@ -1431,6 +1477,15 @@ impl Source {
     }
 
     pub fn wait_until_connect(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+        self.wait_for_connection(timeout, 0)
+    }
+
+    /// Wait until the `nth` (zero based) connection of the session is established.
+    pub fn wait_for_connection(
+        &mut self,
+        timeout: Duration,
+        nth: usize,
+    ) -> Result<WaitForPeerResult> {
         assert!(!self
             .config
             .as_ref()
@@ -1439,6 +1494,57 @@ impl Source {
             .is_server
             .unwrap_or(false));
 
+        let ready_on = self.client_ready_on(nth);
+        self.wait_for_socket_event(timeout, ready_on)
+    }
+
+    /// The event after which this Source is ready to accept connections if it is a server.
+    fn server_ready_on(&self) -> ServerReadySignalKind {
+        self.config()
+            .source
+            .server_ready_on
+            .unwrap_or(ServerReadySignalKind::Listen(0))
+    }
+
+    /// The event after which the `nth` connection of this Source to its server is
+    /// established if it is a client.
+    fn client_ready_on(&self, nth: usize) -> ServerReadySignalKind {
+        let first = match self.config().source.server_ready_on {
+            Some(ServerReadySignalKind::Connect(n)) => n,
+            _ => 0,
+        };
+        ServerReadySignalKind::Connect(first + nth)
+    }
+
+    /// Whether a bind(), listen(), or accept() reported by the agent operated on the configured
+    /// `server-address`. Sockets of unknown address family and configurations without an
+    /// explicit `server-address` always match.
+    fn is_server_address(&self, address: &SocketAddress) -> bool {
+        let expected = match self.config.as_ref().and_then(|c| c.source.server_address) {
+            Some(expected) => expected,
+            None => return true,
+        };
+        match address.to_socket_addr() {
+            Some(actual) => {
+                actual.port() == expected.port()
+                    && (actual.ip() == expected.ip() || actual.ip().is_unspecified())
+            }
+            None => true,
+        }
+    }
+
+    pub fn wait_until_listening(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+        let ready_on = self.server_ready_on();
+        self.wait_for_socket_event(timeout, ready_on)
+    }
+
+    /// Wait until the agent reported the socket event described by `ready_on`. Server side
+    /// events on sockets that are not bound to the `server-address` are ignored.
+    pub fn wait_for_socket_event(
+        &mut self,
+        timeout: Duration,
+        ready_on: ServerReadySignalKind,
+    ) -> Result<WaitForPeerResult> {
         let mq_recv = self
             .mq_recv
             .as_ref()
@@ -1464,12 +1570,39 @@ impl Source {
 
             let header = MsgHeader::try_from_bytes(&buf)?;
             match header.id {
-                MessageType::AfterBind | MessageType::AfterListen => {
-                    return self.wait_until_connect(timeout)
-                }
                 MessageType::AfterConnect => {
                     log::trace!("Got new message: {header:#?}");
-                    return Ok(WaitForPeerResult::Ready);
+                    if self.socket_events.record(SocketEvent::Connect, ready_on) {
+                        return Ok(WaitForPeerResult::Ready);
+                    } else {
+                        return self.wait_for_socket_event(timeout, ready_on);
+                    }
+                }
+                MessageType::AfterBind | MessageType::AfterListen | MessageType::BeforeAccept => {
+                    let (event, address) = match header.id {
+                        MessageType::AfterBind => {
+                            (SocketEvent::Bind, AfterBind::try_from_bytes(&buf)?.address)
+                        }
+                        MessageType::AfterListen => (
+                            SocketEvent::Listen,
+                            AfterListen::try_from_bytes(&buf)?.address,
+                        ),
+                        _ => (
+                            SocketEvent::Accept,
+                            BeforeAccept::try_from_bytes(&buf)?.address,
+                        ),
+                    };
+                    log::trace!("Got new message: {header:#?}, address={address:?}");
+                    if self.is_server_address(&address)
+                        && self.socket_events.record(event, ready_on)
+                    {
+                        return Ok(WaitForPeerResult::Ready);
+                    } else {
+                        return self.wait_for_socket_event(timeout, ready_on);
+                    }
+                }
+                MessageType::SocketData => {
+                    log::trace!("Ignoring message {header:#?}");
                 }
                 MessageType::MsgIdTracePointStat => {
                     log::trace!("Got MsgIdTracePointStat message");
@@ -1510,7 +1643,11 @@ impl Source {
         }
     }
 
-    pub fn wait_until_listening(&mut self, timeout: Duration) -> Result<WaitForPeerResult> {
+    pub fn wait_for_child_termination(
+        &mut self,
+        timeout: Duration,
+        send_sigkill_immediately: bool,
+    ) -> Result<RunResult> {
         let mq_recv = self
             .mq_recv
             .as_ref()
@@ -1520,6 +1657,18 @@ impl Source {
         // Scratch buffer.
         let mut buf: Vec<u8> = vec![0; mq_recv.attributes().max_msg_len];
 
+        if send_sigkill_immediately {
+            log::trace!("Sending sigkill to child since send_sigkill_immediately was set");
+            // Not many things we can do if this fails, also this may just be caused by
+            // the child vanishing before it received the signal.
+            let _ = kill_child_process_group(self.child_pid.as_ref().unwrap());
+        }
+
+        if let Some(msg) = self.pending_termination.take() {
+            log::trace!("Processing MsgIdTerminated message received while polling");
+            return Ok(self.terminated_run_result(&msg));
+        }
+
         // Wait for reply
         loop {
             // Get the next message
@@ -1527,49 +1676,20 @@ impl Source {
                 Ok(_) => (),
                 Err(_) => {
                     self.kill_child_and_wait_for_termination()?;
-                    return Ok((RunResult::TimedOut {
+                    return Ok(RunResult::TimedOut {
                         msgs: self.msg_buffer.take().unwrap(),
-                    })
-                    .into());
+                    });
                 }
             }
 
             let header = MsgHeader::try_from_bytes(&buf)?;
             match header.id {
-                MessageType::AfterConnect => return self.wait_until_listening(timeout),
-                MessageType::AfterBind => {
-                    self.bind_ctr += 1;
-                    log::trace!("Got new message: {header:#?}");
-                    if self
-                        .config
-                        .as_ref()
-                        .unwrap()
-                        .source
-                        .server_ready_on
-                        .unwrap_or(ServerReadySignalKind::Listen(0))
-                        == ServerReadySignalKind::Bind(self.bind_ctr - 1)
-                    {
-                        return Ok(WaitForPeerResult::Ready);
-                    } else {
-                        return self.wait_until_listening(timeout);
-                    }
-                }
-                MessageType::AfterListen => {
-                    log::trace!("Got new message: {header:#?}");
-                    self.listen_ctr += 1;
-                    if self
-                        .config
-                        .as_ref()
-                        .unwrap()
-                        .source
-                        .server_ready_on
-                        .unwrap_or(ServerReadySignalKind::Listen(0))
-                        == ServerReadySignalKind::Listen(self.listen_ctr - 1)
-                    {
-                        return Ok(WaitForPeerResult::Ready);
-                    } else {
-                        return self.wait_until_listening(timeout);
-                    }
+                MessageType::AfterBind
+                | MessageType::AfterListen
+                | MessageType::AfterConnect
+                | MessageType::BeforeAccept
+                | MessageType::SocketData => {
+                    log::trace!("Ignoring message {header:#?}");
                 }
                 MessageType::MsgIdTracePointStat => {
                     log::trace!("Got MsgIdTracePointStat message");
@@ -1582,24 +1702,8 @@ impl Source {
                 }
                 MessageType::MsgIdTerminated => {
                     let msg = TerminatedMessage::try_from_bytes(&buf)?;
-                    let exit_code = msg.exit_code;
                     log::trace!("Received MsgIdTerminated message.");
-                    if exit_code >= 0 {
-                        log::trace!("Child terminated");
-                        return Ok((RunResult::Terminated {
-                            exit_code,
-                            msgs: self.msg_buffer.take().unwrap(),
-                        })
-                        .into());
-                    } else {
-                        /* Signals are represented by negative exit codes */
-                        log::trace!("Child was signalled");
-                        return Ok((RunResult::Signalled {
-                            signal: Signal::try_from(libc::WTERMSIG(-exit_code)).unwrap(),
-                            msgs: self.msg_buffer.take().unwrap(),
-                        })
-                        .into());
-                    }
+                    return Ok(self.terminated_run_result(&msg));
                 }
                 _ => {
                     let err_msg = format!("Unexpected bytes received: {:#?}", &buf[..64]);
@@ -1610,11 +1714,43 @@ impl Source {
         }
     }
 
-    pub fn wait_for_child_termination(
-        &mut self,
-        timeout: Duration,
-        send_sigkill_immediately: bool,
-    ) -> Result<RunResult> {
+    /// Get the result of the execution that was terminated as described by `msg`.
+    fn terminated_run_result(&mut self, msg: &TerminatedMessage) -> RunResult {
+        let exit_code = msg.exit_code;
+        if exit_code >= 0 {
+            log::trace!("Child terminated");
+            RunResult::Terminated {
+                exit_code,
+                msgs: self.msg_buffer.take().unwrap(),
+            }
+        } else {
+            /* Signals are represented by negative exit codes */
+            log::trace!("Child was signalled");
+            let signal = match Signal::try_from(-exit_code) {
+                Ok(signal) => signal,
+                Err(err) => {
+                    log::error!("Failed to convert exit code {exit_code} to signal: {err}");
+                    Signal::SIGBUS
+                }
+            };
+            log::trace!("Received signal is : {signal:?}");
+            RunResult::Signalled {
+                signal,
+                msgs: self.msg_buffer.take().unwrap(),
+            }
+        }
+    }
+
+    /// The fd of the queue the agent sends its messages to. It becomes readable once
+    /// a message, e.g., a report of socket activity, is pending.
+    pub fn message_queue_fd(&self) -> Option<i32> {
+        self.mq_recv.as_ref().map(|mq| mq.as_raw_fd())
+    }
+
+    /// Receive all pending messages of the agent without blocking and return whether
+    /// it reported any socket activity, i.e., a socket event or a data transfer.
+    /// A TerminatedMessage is kept for [Source::wait_for_child_termination].
+    pub fn poll_socket_activity(&mut self) -> Result<bool> {
         let mq_recv = self
             .mq_recv
             .as_ref()
@@ -1623,34 +1759,31 @@ impl Source {
 
         // Scratch buffer.
         let mut buf: Vec<u8> = vec![0; mq_recv.attributes().max_msg_len];
+        let mut activity = false;
 
-        if send_sigkill_immediately {
-            log::trace!("Sending sigkill to child since send_sigkill_immediately was set");
-            // Not many things we can do if this fails, also this may just be caused by
-            // the child vanishing before it received the signal.
-            let _ = kill_child_process_group(self.child_pid.as_ref().unwrap());
-        }
-
-        // Wait for reply
-        loop {
-            // Get the next message
-            match Source::receive_message(aux_stream_handler, mq_recv, timeout, &mut buf) {
-                Ok(_) => (),
-                Err(_) => {
-                    self.kill_child_and_wait_for_termination()?;
-                    return Ok(RunResult::TimedOut {
-                        msgs: self.msg_buffer.take().unwrap(),
-                    });
+        while self.pending_termination.is_none() {
+            if let Err(err) =
+                Source::receive_message(aux_stream_handler, mq_recv, Duration::ZERO, &mut buf)
+            {
+                if matches!(
+                    err.downcast_ref::<io::Error>().map(|err| err.kind()),
+                    Some(io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+                ) {
+                    break;
                 }
+                return Err(err.context("Failed to poll the messages of the agent"));
             }
 
             let header = MsgHeader::try_from_bytes(&buf)?;
             match header.id {
-                MessageType::AfterBind | MessageType::AfterListen | MessageType::AfterConnect => {
-                    log::trace!("Ignoring message {header:#?}");
+                MessageType::AfterBind
+                | MessageType::AfterListen
+                | MessageType::AfterConnect
+                | MessageType::BeforeAccept
+                | MessageType::SocketData => {
+                    activity = true;
                 }
                 MessageType::MsgIdTracePointStat => {
-                    log::trace!("Got MsgIdTracePointStat message");
                     self.msg_buffer
                         .as_mut()
                         .unwrap()
@@ -1659,33 +1792,8 @@ impl Source {
                         ));
                 }
                 MessageType::MsgIdTerminated => {
-                    let msg = TerminatedMessage::try_from_bytes(&buf)?;
-                    let exit_code = msg.exit_code;
-                    log::trace!("Received MsgIdTerminated message.");
-                    if exit_code >= 0 {
-                        log::trace!("Child terminated");
-                        return Ok(RunResult::Terminated {
-                            exit_code,
-                            msgs: self.msg_buffer.take().unwrap(),
-                        });
-                    } else {
-                        /* Signals are represented by negative exit codes */
-                        log::trace!("Child was signalled");
-                        let signal = match Signal::try_from(-exit_code) {
-                            Ok(signal) => signal,
-                            Err(err) => {
-                                log::error!(
-                                    "Failed to convert exit code {exit_code} to signal: {err}"
-                                );
-                                Signal::SIGBUS
-                            }
-                        };
-                        log::trace!("Received signal is : {signal:?}");
-                        return Ok(RunResult::Signalled {
-                            signal,
-                            msgs: self.msg_buffer.take().unwrap(),
-                        });
-                    }
+                    log::trace!("Received MsgIdTerminated message while polling");
+                    self.pending_termination = Some(TerminatedMessage::try_from_bytes(&buf)?);
                 }
                 _ => {
                     let err_msg = format!("Unexpected bytes received: {:#?}", &buf[..64]);
@@ -1694,6 +1802,7 @@ impl Source {
                 }
             }
         }
+        Ok(activity)
     }
 
     fn kill_child_and_wait_for_termination(&mut self) -> Result<()> {
@@ -1887,7 +1996,7 @@ impl Source {
                 new_file.write_all(data).unwrap();
                 // drop new_file here, we do not need and open fd for this input mode.
             }
-            InputChannel::Tcp | InputChannel::Udp => {
+            InputChannel::Tcp | InputChannel::Udp | InputChannel::Unix => {
                 log::trace!("impl for write to networked apps needed")
             }
         }
@@ -1911,7 +2020,10 @@ impl Source {
             self.read_from_output_file(data);
         } else if self.output_channel == OutputChannel::Stdout {
             self.read_from_stdout(data);
-        } else if matches!(self.output_channel, OutputChannel::Tcp | OutputChannel::Udp) {
+        } else if matches!(
+            self.output_channel,
+            OutputChannel::Tcp | OutputChannel::Udp | OutputChannel::Unix
+        ) {
             //no op
         } else {
             unreachable!();
@@ -11,12 +11,17 @@ pub struct Trace {
     /// map are considered uncovered (i.e., not executed).
     exec_cnt: HashMap<MutationSiteID, u64>,
     exec_order: HashMap<MutationSiteID, u64>,
+    /// The values observed during the first hits of a PatchPointID. See
+    /// [TracePointStat::values].
+    #[serde(default)]
+    values: HashMap<MutationSiteID, Vec<u64>>,
 }
 
 impl Trace {
     pub fn from_trace_point_stats(msgs: &[&TracePointStat]) -> Trace {
         let mut exec_cnt = HashMap::new();
         let mut exec_order = HashMap::new();
+        let mut values = HashMap::new();
 
         msgs.iter().for_each(|e| {
             if e.cnt > TRACE_EXEC_CNT_LIMIT {
@@ -30,11 +35,15 @@ impl Trace {
 
             exec_cnt.insert(e.patch_point_id, e.cnt);
             exec_order.insert(e.patch_point_id, e.execution_index.unwrap().get());
+            if !e.values().is_empty() {
+                values.insert(e.patch_point_id, e.values().to_vec());
+            }
         });
 
         Trace {
             exec_cnt,
             exec_order,
+            values,
         }
     }
 
@@ -56,6 +65,12 @@ impl Trace {
         covered
     }
 
+    /// The values observed during the first hits of `pp`, where the n-th element
+    /// was observed during the n-th hit.
+    pub fn values(&self, pp: MutationSiteID) -> Option<&[u64]> {
+        self.values.get(&pp).map(|values| values.as_slice())
+    }
+
     pub fn hits_mapping(&self) -> &HashMap<MutationSiteID, u64> {
         &self.exec_cnt
     }
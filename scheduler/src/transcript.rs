@@ -0,0 +1,528 @@
+//! Transcripts of the application messages exchanged by the peers of networked
+//! targets. They are derived from the packets recorded during calibration and
+//! allow to inspect and replay an execution without reassembling streams by hand.
+
+use std::{collections::HashMap, fmt, net::SocketAddr, time::Duration};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::packet_capture::{IpPacket, TcpHeader, IPPROTO_TCP, IPPROTO_UDP};
+
+/// Python script that replays one side of a transcript. The placeholders are
+/// substituted by [Transcript::replay_script].
+const REPLAY_SCRIPT_TEMPLATE: &str = r#"#!/usr/bin/env python3
+"""Replays the @ROLE@ side of a session recorded by fuzztruction."""
+import socket
+import sys
+
+ROLE = "@ROLE@"
+# Seconds to wait for a message of the other peer.
+RECV_TIMEOUT = 1.0
+# (protocol, server host, server port, client host, client port) of each connection.
+CONNECTIONS = [
+@CONNECTIONS@
+]
+# (connection, sent by us, data) in the order the messages were exchanged.
+MESSAGES = [
+@MESSAGES@
+]
+
+
+def open_connection(idx):
+    protocol, host, port, _, _ = CONNECTIONS[idx]
+    family = socket.AF_INET6 if ":" in host else socket.AF_INET
+    kind = socket.SOCK_STREAM if protocol == "tcp" else socket.SOCK_DGRAM
+    sock = socket.socket(family, kind)
+    sock.settimeout(RECV_TIMEOUT)
+    if ROLE == "client":
+        sock.connect((host, port))
+        return sock
+    sock.setsockopt(socket.SOL_SOCKET, socket.SO_REUSEADDR, 1)
+    sock.bind((host, port))
+    if protocol == "udp":
+        # Connected once the client is known, see connect_udp_client().
+        return sock
+    sock.listen(1)
+    sock.settimeout(None)
+    conn, _ = sock.accept()
+    sock.close()
+    conn.settimeout(RECV_TIMEOUT)
+    return conn
+
+
+def connect_udp_client(sock, idx):
+    """Connect the server's UDP socket to the client, unless it already is."""
+    try:
+        sock.getpeername()
+        return
+    except OSError:
+        pass
+    try:
+        # Learn the client from its first datagram without consuming it.
+        _, addr = sock.recvfrom(65535, socket.MSG_PEEK)
+    except socket.timeout:
+        # The client did not send anything, assume it uses the recorded address.
+        addr = (CONNECTIONS[idx][3], CONNECTIONS[idx][4])
+    sock.connect(addr)
+
+
+def send(sock, idx, data):
+    if CONNECTIONS[idx][0] == "tcp":
+        sock.sendall(data)
+        return
+    if ROLE == "server":
+        connect_udp_client(sock, idx)
+    sock.send(data)
+
+
+def receive(sock, idx, expected):
+    received = b""
+    try:
+        if CONNECTIONS[idx][0] == "udp":
+            # Each message is exactly one datagram.
+            if ROLE == "server":
+                connect_udp_client(sock, idx)
+            received = sock.recv(65535)
+        else:
+            while len(received) < len(expected):
+                data = sock.recv(65535)
+                if not data:
+                    break
+                received += data
+    except socket.timeout:
+        pass
+    if received != expected:
+        print(f"Received {len(received)} bytes, expected {len(expected)} bytes", file=sys.stderr)
+
+
+def main():
+    sockets = {}
+    for idx, ours, data in MESSAGES:
+        if idx not in sockets:
+            sockets[idx] = open_connection(idx)
+        sock = sockets[idx]
+        if ours:
+            send(sock, idx, data)
+        else:
+            receive(sock, idx, data)
+    for sock in sockets.values():
+        sock.close()
+
+
+if __name__ == "__main__":
+    main()
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Sender {
+    Client,
+    Server,
+}
+
+impl fmt::Display for Sender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sender::Client => write!(f, "client"),
+            Sender::Server => write!(f, "server"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportProtocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for TransportProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportProtocol::Tcp => write!(f, "tcp"),
+            TransportProtocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// A connection or, in case of UDP, a flow between two peers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Connection {
+    pub protocol: TransportProtocol,
+    /// The endpoint that sent the first packet.
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    /// Index into [Transcript::connections].
+    pub connection: usize,
+    pub sender: Sender,
+    /// Time since the first packet of the transcript.
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+}
+
+/// The ordered application messages of an execution.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transcript {
+    pub connections: Vec<Connection>,
+    pub messages: Vec<Message>,
+}
+
+/// Reassembly state of one direction of a TCP connection.
+#[derive(Debug, Default)]
+struct TcpStream {
+    /// The sequence number of the next byte of the stream.
+    next_seq: Option<u32>,
+    /// Segments that were received ahead of `next_seq`, with the time they were
+    /// received at.
+    pending: Vec<(u32, Duration, Vec<u8>)>,
+}
+
+impl TcpStream {
+    /// Add a segment and return the data that extends the stream in order, together
+    /// with the time the first part of it was received at. Retransmitted data is
+    /// dropped and segments received out of order are held back until the gap in
+    /// front of them is filled.
+    fn add_segment(
+        &mut self,
+        header: TcpHeader,
+        ts: Duration,
+        payload: &[u8],
+    ) -> Option<(Duration, Vec<u8>)> {
+        // The SYN occupies one sequence number.
+        let seq = if header.syn {
+            let seq = header.seq.wrapping_add(1);
+            self.next_seq.get_or_insert(seq);
+            seq
+        } else {
+            header.seq
+        };
+        if payload.is_empty() {
+            return None;
+        }
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        self.pending.push((seq, ts, payload.to_vec()));
+        self.deliver(next_seq)
+    }
+
+    /// Deliver all pending segments that start at or before `next_seq`.
+    fn deliver(&mut self, mut next_seq: u32) -> Option<(Duration, Vec<u8>)> {
+        let mut ret: Option<(Duration, Vec<u8>)> = None;
+        while let Some(idx) = self
+            .pending
+            .iter()
+            .position(|(seq, _, _)| next_seq.wrapping_sub(*seq) <= 1 << 31)
+        {
+            let (seq, ts, data) = self.pending.swap_remove(idx);
+            let duplicate_len = next_seq.wrapping_sub(seq) as usize;
+            if duplicate_len >= data.len() {
+                continue;
+            }
+            next_seq = next_seq.wrapping_add((data.len() - duplicate_len) as u32);
+            ret.get_or_insert_with(|| (ts, Vec::new()))
+                .1
+                .extend_from_slice(&data[duplicate_len..]);
+        }
+        self.next_seq = Some(next_seq);
+        ret
+    }
+
+    /// Deliver the segments that are still held back, e.g., because the capture
+    /// missed the segment in front of them. Gaps are skipped.
+    fn flush(&mut self) -> Option<(Duration, Vec<u8>)> {
+        let next_seq = self.next_seq?;
+        let (first_seq, _, _) = self
+            .pending
+            .iter()
+            .min_by_key(|(seq, _, _)| seq.wrapping_sub(next_seq))?;
+        let first_seq = *first_seq;
+        let mut ret = self.deliver(first_seq)?;
+        while let Some((ts, data)) = self.flush() {
+            ret.1.extend_from_slice(&data);
+            ret.0 = ret.0.min(ts);
+        }
+        Some(ret)
+    }
+}
+
+impl Transcript {
+    /// Build a transcript from raw IP packets and the time they were received at.
+    /// TCP streams are reassembled by their sequence numbers and consecutive data
+    /// sent in the same direction of a connection is merged into a single message,
+    /// since TCP does not preserve message boundaries.
+    pub(crate) fn from_packets<'a>(
+        packets: impl Iterator<Item = (Duration, IpPacket<'a>)>,
+    ) -> Transcript {
+        let mut ret = Transcript::default();
+        let mut start_ts = None;
+        let mut tcp_streams: HashMap<(usize, Sender), TcpStream> = HashMap::new();
+
+        for (ts, packet) in packets {
+            let protocol = match packet.protocol {
+                IPPROTO_TCP => TransportProtocol::Tcp,
+                IPPROTO_UDP => TransportProtocol::Udp,
+                _ => continue,
+            };
+            let Some((src_port, dst_port)) = packet.ports else {
+                continue;
+            };
+            let src = SocketAddr::new(packet.src, src_port);
+            let dst = SocketAddr::new(packet.dst, dst_port);
+            let start_ts = *start_ts.get_or_insert(ts);
+
+            let existing = ret.connections.iter().position(|c| {
+                c.protocol == protocol
+                    && ((c.client == src && c.server == dst)
+                        || (c.client == dst && c.server == src))
+            });
+            let (connection, sender) = match existing {
+                Some(idx) if ret.connections[idx].client == src => (idx, Sender::Client),
+                Some(idx) => (idx, Sender::Server),
+                None => {
+                    ret.connections.push(Connection {
+                        protocol,
+                        client: src,
+                        server: dst,
+                    });
+                    (ret.connections.len() - 1, Sender::Client)
+                }
+            };
+
+            match packet.tcp {
+                Some(header) => {
+                    let stream = tcp_streams.entry((connection, sender)).or_default();
+                    if let Some((ts, data)) = stream.add_segment(header, ts, packet.payload) {
+                        ret.push_stream_data(connection, sender, ts.saturating_sub(start_ts), data);
+                    }
+                }
+                // Each datagram is a message, even an empty one, since it might be
+                // what makes the client known to the server.
+                None => ret.messages.push(Message {
+                    connection,
+                    sender,
+                    timestamp: ts.saturating_sub(start_ts),
+                    data: packet.payload.to_vec(),
+                }),
+            }
+        }
+
+        let start_ts = start_ts.unwrap_or_default();
+        for ((connection, sender), mut stream) in tcp_streams.into_iter().sorted_by_key(|e| e.0) {
+            if let Some((ts, data)) = stream.flush() {
+                ret.push_stream_data(connection, sender, ts.saturating_sub(start_ts), data);
+            }
+        }
+
+        ret
+    }
+
+    /// Append data of a TCP stream, merging it into the last message if that one
+    /// was sent in the same direction of the same connection.
+    fn push_stream_data(
+        &mut self,
+        connection: usize,
+        sender: Sender,
+        timestamp: Duration,
+        data: Vec<u8>,
+    ) {
+        match self.messages.last_mut() {
+            Some(last) if last.connection == connection && last.sender == sender => {
+                last.data.extend_from_slice(&data)
+            }
+            _ => self.messages.push(Message {
+                connection,
+                sender,
+                timestamp,
+                data,
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// The number of application bytes in this transcript.
+    pub fn len_bytes(&self) -> usize {
+        self.messages.iter().map(|m| m.data.len()).sum()
+    }
+
+    /// Get a Python script that replays the side of `role`, i.e., it sends the
+    /// messages of `role` and waits for the messages of the other peer.
+    pub fn replay_script(&self, role: Sender) -> String {
+        let connections = self
+            .connections
+            .iter()
+            .map(|c| {
+                format!(
+                    "    (\"{}\", \"{}\", {}, \"{}\", {}),",
+                    c.protocol,
+                    c.server.ip(),
+                    c.server.port(),
+                    c.client.ip(),
+                    c.client.port()
+                )
+            })
+            .join("\n");
+        let messages = self
+            .messages
+            .iter()
+            .map(|m| {
+                let ours = if m.sender == role { "True" } else { "False" };
+                format!(
+                    "    ({}, {}, bytes.fromhex(\"{}\")),",
+                    m.connection,
+                    ours,
+                    hex::encode(&m.data)
+                )
+            })
+            .join("\n");
+
+        REPLAY_SCRIPT_TEMPLATE
+            .replace("@ROLE@", &role.to_string())
+            .replace("@CONNECTIONS@", &connections)
+            .replace("@MESSAGES@", &messages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::packet_capture::IpPacket;
+
+    use super::{Sender, Transcript, TransportProtocol};
+
+    const SYN: u8 = 0x02;
+
+    /// An IPv4 TCP packet carrying `payload`.
+    fn tcp_packet(src_port: u16, dst_port: u16, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 40];
+        packet[0] = 0x45;
+        packet[9] = 6;
+        packet[12..16].copy_from_slice(&[127, 0, 0, 1]);
+        packet[16..20].copy_from_slice(&[127, 0, 0, 1]);
+        packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+        packet[22..24].copy_from_slice(&dst_port.to_be_bytes());
+        packet[24..28].copy_from_slice(&seq.to_be_bytes());
+        packet[32] = 5 << 4;
+        packet[33] = flags;
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    /// An IPv4 UDP packet carrying `payload`.
+    fn udp_packet(src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; 28];
+        packet[0] = 0x45;
+        packet[9] = 17;
+        packet[12..16].copy_from_slice(&[127, 0, 0, 1]);
+        packet[16..20].copy_from_slice(&[127, 0, 0, 1]);
+        packet[20..22].copy_from_slice(&src_port.to_be_bytes());
+        packet[22..24].copy_from_slice(&dst_port.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn transcript_of(packets: &[Vec<u8>]) -> Transcript {
+        Transcript::from_packets(packets.iter().enumerate().map(|(idx, p)| {
+            (
+                Duration::from_millis(idx as u64),
+                IpPacket::parse(p).unwrap(),
+            )
+        }))
+    }
+
+    #[test]
+    fn messages_are_merged_per_direction() {
+        let transcript = transcript_of(&[
+            tcp_packet(50000, 4433, 100, SYN, b""),
+            tcp_packet(4433, 50000, 500, SYN, b""),
+            tcp_packet(50000, 4433, 101, 0, b"HEL"),
+            tcp_packet(50000, 4433, 104, 0, b"LO"),
+            tcp_packet(4433, 50000, 501, 0, b"WORLD"),
+        ]);
+
+        assert_eq!(transcript.connections.len(), 1);
+        assert_eq!(transcript.connections[0].server.port(), 4433);
+        assert_eq!(transcript.messages.len(), 2);
+        assert_eq!(transcript.messages[0].sender, Sender::Client);
+        assert_eq!(transcript.messages[0].data, b"HELLO");
+        assert_eq!(transcript.messages[0].timestamp, Duration::from_millis(2));
+        assert_eq!(transcript.messages[1].sender, Sender::Server);
+        assert_eq!(transcript.messages[1].data, b"WORLD");
+    }
+
+    #[test]
+    fn retransmitted_and_reordered_segments_are_reassembled() {
+        let transcript = transcript_of(&[
+            tcp_packet(50000, 4433, u32::MAX, SYN, b""),
+            tcp_packet(50000, 4433, 0, 0, b"AB"),
+            // Received before the segment in front of it.
+            tcp_packet(50000, 4433, 4, 0, b"EF"),
+            tcp_packet(50000, 4433, 2, 0, b"CD"),
+            // Retransmits, the last one partially overlapping new data.
+            tcp_packet(50000, 4433, 2, 0, b"CD"),
+            tcp_packet(50000, 4433, 0, 0, b"AB"),
+            tcp_packet(50000, 4433, 5, 0, b"FGH"),
+        ]);
+
+        assert_eq!(transcript.messages.len(), 1);
+        assert_eq!(transcript.messages[0].data, b"ABCDEFGH");
+        assert_eq!(transcript.messages[0].timestamp, Duration::from_millis(1));
+    }
+
+    #[test]
+    fn segments_after_a_missing_segment_are_kept() {
+        let transcript = transcript_of(&[
+            tcp_packet(50000, 4433, 0, SYN, b""),
+            tcp_packet(50000, 4433, 1, 0, b"AB"),
+            tcp_packet(50000, 4433, 5, 0, b"EF"),
+        ]);
+
+        assert_eq!(transcript.messages.len(), 1);
+        assert_eq!(transcript.messages[0].data, b"ABEF");
+    }
+
+    #[test]
+    fn server_speaks_first() {
+        let transcript = transcript_of(&[
+            tcp_packet(50000, 21, 0, SYN, b""),
+            tcp_packet(21, 50000, 0, SYN, b""),
+            tcp_packet(21, 50000, 1, 0, b"220 ready"),
+            tcp_packet(50000, 21, 1, 0, b"USER"),
+        ]);
+        assert_eq!(transcript.connections[0].server.port(), 21);
+        let senders: Vec<_> = transcript.messages.iter().map(|m| m.sender).collect();
+        assert_eq!(senders, [Sender::Server, Sender::Client]);
+
+        // The empty datagram is what makes the client known to the server.
+        let transcript = transcript_of(&[
+            udp_packet(50000, 5684, b""),
+            udp_packet(5684, 50000, b"hello"),
+            udp_packet(50000, 5684, b"hi"),
+        ]);
+        assert_eq!(transcript.connections.len(), 1);
+        assert_eq!(transcript.connections[0].protocol, TransportProtocol::Udp);
+        let messages: Vec<_> = transcript
+            .messages
+            .iter()
+            .map(|m| (m.sender, m.data.as_slice()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (Sender::Client, &b""[..]),
+                (Sender::Server, &b"hello"[..]),
+                (Sender::Client, &b"hi"[..]),
+            ]
+        );
+
+        let script = transcript.replay_script(Sender::Server);
+        assert!(script.contains("(\"udp\", \"127.0.0.1\", 5684, \"127.0.0.1\", 50000),"));
+        assert!(script.contains("(0, False, bytes.fromhex(\"\")),"));
+        assert!(script.contains("(0, True, bytes.fromhex(\"68656c6c6f\")),"));
+    }
+}
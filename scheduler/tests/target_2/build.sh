@@ -0,0 +1,2 @@
+#!/bin/bash
+cc -g generator.S -L/home/user/fuzztruction/target/debug -lgenerator_agent -o generator
@@ -3,6 +3,7 @@ use std::{path::PathBuf, str::FromStr, sync::Mutex, time::Duration};
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use scheduler::{
     io_channels::{InputChannel, OutputChannel},
+    llvm_stackmap::LocationType,
     logging::setup_logger,
     mutation_cache::MutationCache,
     mutation_cache_ops::MutationCacheOpsEx,
@@ -17,11 +18,20 @@ fn tests_path() -> PathBuf {
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Mutate the single patch point of `target` and check whether the target
+/// observes the mask. The patch point's spill slot must be of type `loc_type`.
 #[allow(clippy::read_zero_byte_vec)]
-fn test_instrumentation(workdir: PathBuf, total_size: usize, access_size_bits: usize) {
+fn test_instrumentation(
+    workdir: PathBuf,
+    target: &str,
+    loc_type: LocationType,
+    total_size: usize,
+    access_size_bits: usize,
+) {
     let tests_path = tests_path();
     let mut bin_path = tests_path.clone();
-    bin_path.push("target_1/generator");
+    bin_path.push(target);
+    bin_path.push("generator");
 
     let access_total_size = total_size;
 
@@ -41,6 +51,7 @@ fn test_instrumentation(workdir: PathBuf, total_size: usize, access_size_bits: u
     source.start().unwrap();
     let mut patch_points = Vec::clone(&source.get_patchpoints().unwrap());
     assert_eq!(patch_points.len(), 1);
+    assert_eq!(patch_points[0].spill_slot().loc_type, loc_type);
     assert!(access_size_bits <= 8);
 
     unsafe {
@@ -146,7 +157,7 @@ fn test_instrumentation_bytes_all() {
     setup_logging(log_path);
 
     for n in [1, 2, 3, 4, 5, 6, 7, 8, 9, 100, 120, 500, 1024, 5555, 10000] {
-        test_instrumentation(workdir.clone(), n, 8);
+        test_instrumentation(workdir.clone(), "target_1", LocationType::Register, n, 8);
     }
 }
 
@@ -162,7 +173,36 @@ fn test_instrumentation_bits_all() {
 
     for n in [1, 2, 3, 4, 5, 6, 7, 8, 9, 100, 120, 500, 1024, 5555, 10000] {
         for access_size in 1..7 {
-            test_instrumentation(workdir.clone(), n, access_size);
+            test_instrumentation(
+                workdir.clone(),
+                "target_1",
+                LocationType::Register,
+                n,
+                access_size,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_instrumentation_indirect() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let workdir = tmp_dir.into_path();
+    eprintln!("workdir: {:?}", &workdir);
+
+    let mut log_path = workdir.clone();
+    log_path.push("log.txt");
+    setup_logging(log_path);
+
+    for n in [1, 2, 3, 8, 9, 100, 1024] {
+        for access_size in [1, 3, 8] {
+            test_instrumentation(
+                workdir.clone(),
+                "target_2",
+                LocationType::Indirect,
+                n,
+                access_size,
+            );
         }
     }
 }